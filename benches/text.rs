@@ -0,0 +1,94 @@
+//! Per-frame cost of the demo's FPS overlay, rasterizing glyphs every frame
+//! as the demo used to, and with glyphs cached by `gfx::text`.
+//!
+//! `cargo bench --bench text`
+
+use std::time::{Duration, Instant};
+use gfx::{
+    canvas::{Canvas, Color},
+    text::{Font, FontSet, TextStyle, draw_text},
+};
+use rusttype::{Scale, point};
+
+const FONT_DATA: &[u8] = include_bytes!("../data/Inconsolata-Regular.ttf");
+
+fn main() {
+    // `cargo test --benches` runs this without `--bench`, a few iterations are enough there
+    let iterations = if std::env::args().any(|arg| arg == "--bench") { 2000 } else { 10 };
+
+    // the demo draws the overlay over an opaque background
+    let mut canvas = Canvas::new(1280, 720).unwrap();
+    for y in 0..canvas.height() {
+        for x in 0..canvas.width() {
+            canvas.set((x, y), Color { r: 0, g: 0, b: 0, a: 255 });
+        }
+    }
+    let scale = Scale::uniform(20.0);
+
+    let uncached_font = rusttype::Font::try_from_bytes(FONT_DATA).unwrap();
+    let uncached = measure(iterations, |i| {
+        let (ms, fps) = overlay(i);
+        draw_str_uncached(&mut canvas, &ms, &uncached_font, scale, point(0.0, 0.0));
+        draw_str_uncached(&mut canvas, &fps, &uncached_font, scale, point(0.0, 20.0));
+    });
+
+    let fonts = FontSet::from(Font::try_from_bytes(FONT_DATA).unwrap());
+    let style = TextStyle::new(&fonts, 20.0);
+    let cached = measure(iterations, |i| {
+        let (ms, fps) = overlay(i);
+        draw_text(&mut canvas, &ms, (0.0, 0.0), &style);
+        draw_text(&mut canvas, &fps, (0.0, 20.0), &style);
+    });
+
+    println!("FPS overlay, {} frames", iterations);
+    println!("    uncached: {:>10.3} us per frame", uncached.as_secs_f64() * 1e6);
+    println!("    cached:   {:>10.3} us per frame", cached.as_secs_f64() * 1e6);
+    println!("    speedup:  {:>10.1}x", uncached.as_secs_f64() / cached.as_secs_f64());
+}
+
+/// Overlay text of frame `i`, changing every frame like the real one.
+fn overlay(i: usize) -> (String, String) {
+    let elapsed = 0.016 + (i % 100) as f64 * 1e-5;
+    (
+        format!("{:8.3} ms per frame", elapsed * 1000.0),
+        format!("{:8.3} fps", elapsed.recip()),
+    )
+}
+
+fn measure(iterations: usize, mut frame: impl FnMut(usize)) -> Duration {
+    // warm up, this also fills glyph cache
+    for i in 0..iterations / 10 {
+        frame(i);
+    }
+
+    let start = Instant::now();
+    for i in 0..iterations {
+        frame(i);
+    }
+    start.elapsed() / iterations as u32
+}
+
+/// `draw_str` as the demo had it before glyph caching.
+fn draw_str_uncached(
+    canvas: &mut Canvas,
+    s: &str,
+    font: &rusttype::Font,
+    scale: Scale,
+    start: rusttype::Point<f32>
+) {
+    let vmetrics = font.v_metrics(scale);
+    for g in font.layout(s, scale, start) {
+        if let Some(bbox) = g.pixel_bounding_box() {
+            g.draw(|x, y, v| {
+                let n = (255.0 * v) as u8;
+                canvas.set(
+                    (
+                        (bbox.min.x as f32 + x as f32) as usize,
+                        (vmetrics.ascent + bbox.min.y as f32 + y as f32) as usize
+                    ),
+                    Color { r: n, g: n, b: n, a: n }
+                );
+            });
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! Application loop: events, updates at a fixed rate, rendering and frame pacing.
+//!
+//! An `App` reacts to events, advances its state in `update` and draws it in `render`.
+//! A `Runner` drives it on a backend. With `Timestep::Fixed` updates always advance
+//! the same amount of time however long frames take, and `render` gets how far the
+//! clock is between the last update and the next one to interpolate positions with:
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use gfx::{app::{App, FrameTime, Runner}, backend::scripted::Scripted, canvas::Canvas, input::Event};
+//! struct Ball { previous: f32, position: f32, closed: bool }
+//!
+//! impl App for Ball {
+//!     fn update(&mut self, dt: Duration) {
+//!         self.previous = self.position;
+//!         self.position += 100.0 * dt.as_secs_f32();
+//!     }
+//!
+//!     fn render(&mut self, canvas: &mut Canvas, frame: &FrameTime) {
+//!         let x = self.previous + (self.position - self.previous) * frame.alpha;
+//!         // draw the ball at `x`
+//!     }
+//!
+//!     fn event(&mut self, event: &Event) {
+//!         self.closed |= *event == Event::Close;
+//!     }
+//!
+//!     fn should_exit(&self) -> bool {
+//!         self.closed
+//!     }
+//! }
+//!
+//! # let mut backend = Scripted::new((1280, 720), Vec::new());
+//! let mut canvas = Canvas::new(1280, 720).unwrap();
+//! let mut runner = Runner::new();
+//! runner.frame_cap = Some(Duration::from_secs(1) / 60);
+//! runner.run(&mut backend, &mut canvas, &mut Ball { previous: 0.0, position: 0.0, closed: false }).unwrap();
+//! ```
+//!
+//! `Runner::deterministic` runs on a `FakeClock` instead, for tests and offline rendering:
+//! every frame takes exactly one step of clock time, so runs repeat exactly.
+
+use std::{
+    cell::Cell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+use crate::{
+    backend::Backend,
+    canvas::Canvas,
+    input::Event,
+    profile_scope,
+    resize::{Presenter, ResizePolicy},
+    Error,
+};
+
+/// Time left before a deadline that is spun away instead of slept, as sleeps overshoot
+/// by up to the scheduler's tick.
+const SPIN: Duration = Duration::from_millis(2);
+
+pub trait App {
+    /// Advances the state by `dt`, the step of `Timestep::Fixed` or the length of the
+    /// previous frame with `Timestep::Variable`.
+    fn update(&mut self, dt: Duration);
+
+    /// Draws the state onto `canvas`, which is presented afterwards.
+    fn render(&mut self, canvas: &mut Canvas, frame: &FrameTime);
+
+    /// Called for every event before the frame's updates. Cursor positions are in canvas pixels.
+    fn event(&mut self, event: &Event);
+
+    /// Whether the runner should return, checked after the events and updates of every frame.
+    /// Backends only ask to close with `Event::Close`, apps that do not exit on it run on.
+    fn should_exit(&self) -> bool {
+        false
+    }
+}
+
+/// Timing of the frame being rendered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameTime {
+    /// Number of the frame, from 0.
+    pub index: u64,
+    /// Clock time at the start of the frame since the start of the run.
+    pub time: Duration,
+    /// Clock time since the start of the previous frame, zero for the first one.
+    pub elapsed: Duration,
+    /// How far the clock is from the last update to the next one, from 0 to 1, for
+    /// interpolating between the previous and the current state. Always 1 with `Timestep::Variable`.
+    pub alpha: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timestep {
+    /// Updates by this much, as many times per frame as the clock advanced by it. Not zero.
+    Fixed(Duration),
+    /// One update per frame by the length of the previous frame.
+    Variable,
+}
+
+/// Source of time for a `Runner`.
+pub trait Clock {
+    /// Time since an arbitrary start, never decreasing.
+    fn now(&self) -> Duration;
+
+    /// Returns once `now` reaches `deadline`.
+    fn wait_until(&mut self, deadline: Duration);
+}
+
+/// Real time, waits by sleeping and spinning the last `SPIN` for precision.
+#[derive(Clone, Copy, Debug)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn wait_until(&mut self, deadline: Duration) {
+        let now = self.now();
+        if deadline > now + SPIN {
+            std::thread::sleep(deadline - now - SPIN);
+        }
+        while self.now() < deadline {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// Clock that only moves when told to, or when waited on. Clones share the time,
+/// so an app can hold one and `advance` it to stand for the work it does.
+#[derive(Clone, Debug, Default)]
+pub struct FakeClock {
+    now: Rc<Cell<Duration>>,
+}
+
+impl FakeClock {
+    /// Clock at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+
+    fn wait_until(&mut self, deadline: Duration) {
+        self.now.set(self.now.get().max(deadline));
+    }
+}
+
+/// Runs an `App` on a backend.
+pub struct Runner<C = SystemClock> {
+    pub timestep: Timestep,
+    /// Shortest time from the start of a frame to the start of the next one, `None` to not wait.
+    pub frame_cap: Option<Duration>,
+    /// Most updates in one frame. Time that would need more is dropped, so a slow frame
+    /// does not make the next one slower to catch up.
+    pub max_updates: u32,
+    /// Number of frames after which `run` returns, `None` to run until the app exits.
+    pub frame_limit: Option<u64>,
+    pub policy: ResizePolicy,
+    clock: C,
+}
+
+impl Runner {
+    /// 60 updates per second, uncapped, on real time.
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock::new())
+    }
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Runner<FakeClock> {
+    /// Fixed steps of `step` and frames exactly `step` apart on `clock`, so every frame
+    /// but the first has one update and an alpha of 0, whatever the real time.
+    pub fn deterministic(step: Duration, clock: FakeClock) -> Self {
+        let mut runner = Self::with_clock(clock);
+        runner.timestep = Timestep::Fixed(step);
+        runner.frame_cap = Some(step);
+        runner
+    }
+}
+
+impl<C: Clock> Runner<C> {
+    /// 60 updates per second, uncapped, on `clock`.
+    pub fn with_clock(clock: C) -> Self {
+        Self {
+            timestep: Timestep::Fixed(Duration::from_secs(1) / 60),
+            frame_cap: None,
+            max_updates: 8,
+            frame_limit: None,
+            policy: ResizePolicy::Reallocate,
+            clock,
+        }
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Runs `app` until it exits or `frame_limit` frames are presented, drawing on `canvas`,
+    /// which is resized by `policy` and holds the last frame afterwards. Panics on a
+    /// `Timestep::Fixed` of zero.
+    pub fn run(&mut self, backend: &mut impl Backend, canvas: &mut Canvas, app: &mut impl App) -> Result<(), Error> {
+        if let Timestep::Fixed(step) = self.timestep {
+            assert!(step > Duration::ZERO, "Runner::run. Timestep::Fixed step must not be 0");
+        }
+        let mut presenter = Presenter::new(self.policy);
+        let mut events = Vec::new();
+
+        let start = self.clock.now();
+        let mut previous = start;
+        let mut deadline = start;
+        let mut lag = Duration::ZERO;
+        if self.frame_limit == Some(0) {
+            return Ok(());
+        }
+        for index in 0.. {
+            let now = self.clock.now();
+            let elapsed = now - previous;
+            previous = now;
+
+            events.clear();
+            backend.poll_events(&mut events);
+            presenter.map_events(&mut events);
+            for event in &events {
+                app.event(event);
+            }
+
+            let alpha = match self.timestep {
+                Timestep::Fixed(step) => {
+                    profile_scope!("update");
+                    lag += elapsed;
+                    let mut updates = 0;
+                    while lag >= step {
+                        if updates == self.max_updates {
+                            lag = Duration::from_nanos((lag.as_nanos() % step.as_nanos()) as u64);
+                            break;
+                        }
+                        app.update(step);
+                        lag -= step;
+                        updates += 1;
+                    }
+                    lag.as_secs_f32() / step.as_secs_f32()
+                },
+                Timestep::Variable => {
+                    profile_scope!("update");
+                    app.update(elapsed);
+                    1.0
+                },
+            };
+            if app.should_exit() {
+                break;
+            }
+
+            presenter.fit(canvas, backend.size())?;
+            {
+                profile_scope!("render");
+                app.render(canvas, &FrameTime { index, time: now - start, elapsed, alpha });
+            }
+            presenter.present(backend, canvas)?;
+            if self.frame_limit == Some(index + 1) {
+                break;
+            }
+
+            if let Some(cap) = self.frame_cap {
+                profile_scope!("wait");
+                // deadlines follow each other by `cap` so sleeps overshooting do not add up,
+                // unless the frame took so long that the next one is late already
+                deadline += cap;
+                let now = self.clock.now();
+                if deadline < now {
+                    deadline = now;
+                }
+                self.clock.wait_until(deadline);
+            }
+        }
+        Ok(())
+    }
+}
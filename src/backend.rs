@@ -0,0 +1,28 @@
+//! Windows and other places frames are shown in, and where input comes from.
+//!
+//! Every backend presents canvases and translates its platform's input into
+//! `gfx::input::Event`s, so the rest of the application does not care which
+//! one it runs on.
+
+use crate::{canvas::Canvas, input::Event, Error};
+
+mod keysym;
+pub mod scripted;
+pub mod terminal;
+pub mod vnc;
+#[cfg(windows)]
+pub mod win32;
+#[cfg(all(unix, feature = "x11"))]
+pub mod x11;
+
+pub trait Backend {
+    /// Appends events that arrived since the previous call to `events`.
+    fn poll_events(&mut self, events: &mut Vec<Event>);
+
+    /// Shows `canvas`. One sized differently from the drawable area is stretched to it
+    /// by backends that can scale, and shown unscaled at the top left corner by others.
+    fn present(&mut self, canvas: &Canvas) -> Result<(), Error>;
+
+    /// Size of the drawable area in pixels.
+    fn size(&self) -> (usize, usize);
+}
@@ -0,0 +1,56 @@
+//! X11 key symbols, which both Xlib and the RFB protocol use for keys.
+
+use crate::input::Key;
+
+/// Key of a key symbol. Shifted symbols of a US layout map to the keys they are on,
+/// since RFB sends the symbol as typed and not the unshifted one.
+pub(super) fn key(keysym: u32) -> Key {
+    let c = std::char::from_u32(keysym).unwrap_or('\0');
+    match keysym {
+        0x30..=0x39 => Key::digit(keysym - 0x30).unwrap(),
+        0x41..=0x5a | 0x61..=0x7a => Key::letter(c).unwrap(),
+        0xffbe..=0xffc9 => Key::function(keysym - 0xffbe + 1).unwrap(),
+        0xff1b => Key::Escape,
+        0xff0d | 0xff8d => Key::Enter,
+        0xff09 | 0xfe20 => Key::Tab,
+        0xff08 => Key::Backspace,
+        0x20 => Key::Space,
+        0xff63 => Key::Insert,
+        0xffff => Key::Delete,
+        0xff50 => Key::Home,
+        0xff57 => Key::End,
+        0xff55 => Key::PageUp,
+        0xff56 => Key::PageDown,
+        0xff51 => Key::Left,
+        0xff53 => Key::Right,
+        0xff52 => Key::Up,
+        0xff54 => Key::Down,
+        0xffe1 | 0xffe2 => Key::Shift,
+        0xffe3 | 0xffe4 => Key::Control,
+        0xffe9 | 0xffea => Key::Alt,
+        0x2d | 0x5f => Key::Minus,
+        0x3d | 0x2b => Key::Equal,
+        0x5b | 0x7b => Key::LeftBracket,
+        0x5d | 0x7d => Key::RightBracket,
+        0x5c | 0x7c => Key::Backslash,
+        0x3b | 0x3a => Key::Semicolon,
+        0x27 | 0x22 => Key::Quote,
+        0x60 | 0x7e => Key::Grave,
+        0x2c | 0x3c => Key::Comma,
+        0x2e | 0x3e => Key::Period,
+        0x2f | 0x3f => Key::Slash,
+        _ => match "!@#$%^&*()".find(c) {
+            Some(digit) => Key::digit((digit as u32 + 1) % 10).unwrap(),
+            None => Key::Other(keysym),
+        },
+    }
+}
+
+/// Character typed with a key symbol, `None` for keys that do not type one.
+pub(super) fn char(keysym: u32) -> Option<char> {
+    match keysym {
+        0x20..=0x7e | 0xa0..=0xff => std::char::from_u32(keysym),
+        0x0100_0000..=0x0110_ffff => std::char::from_u32(keysym - 0x0100_0000).filter(|c| !c.is_control()),
+        _ => None,
+    }
+}
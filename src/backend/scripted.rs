@@ -0,0 +1,72 @@
+//! Backend replaying prepared input, for tests.
+
+use std::collections::VecDeque;
+use crate::{
+    canvas::{Canvas, Color},
+    input::Event,
+    Error,
+};
+use super::Backend;
+
+/// Returns one prepared frame of events per `poll_events` call, then `Event::Close`
+/// on every call once they run out. Presented canvases are counted and the last one kept.
+pub struct Scripted {
+    frames: VecDeque<Vec<Event>>,
+    size: (usize, usize),
+    presented: usize,
+    last_frame: Vec<Color>,
+}
+
+impl Scripted {
+    /// Backend with a drawable area of `size`, changed by `Event::Resize` in the script.
+    pub fn new(size: (usize, usize), frames: impl IntoIterator<Item = Vec<Event>>) -> Self {
+        Self {
+            frames: frames.into_iter().collect(),
+            size,
+            presented: 0,
+            last_frame: Vec::new(),
+        }
+    }
+
+    /// Frames of the script not polled yet.
+    pub fn remaining(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Number of `present` calls.
+    pub fn presented(&self) -> usize {
+        self.presented
+    }
+
+    /// Pixels of the last presented canvas, empty if none was.
+    pub fn last_frame(&self) -> &[Color] {
+        &self.last_frame
+    }
+}
+
+impl Backend for Scripted {
+    fn poll_events(&mut self, events: &mut Vec<Event>) {
+        match self.frames.pop_front() {
+            Some(frame) => {
+                for event in &frame {
+                    if let Event::Resize { width, height } = *event {
+                        self.size = (width, height);
+                    }
+                }
+                events.extend(frame);
+            },
+            None => events.push(Event::Close),
+        }
+    }
+
+    fn present(&mut self, canvas: &Canvas) -> Result<(), Error> {
+        self.presented += 1;
+        self.last_frame.clear();
+        self.last_frame.extend_from_slice(canvas.pixels());
+        Ok(())
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+}
@@ -0,0 +1,397 @@
+//! Backend drawing in a terminal with escape sequences, for machines without a windowing system.
+//!
+//! `Mode::HalfBlocks` draws two pixels per character cell with `▀` in 24-bit
+//! color and only rewrites cells that changed since the previous frame.
+//! `Mode::Sixel` sends the whole image as sixel graphics with a 216 color
+//! palette, and nothing if the frame did not change.
+//!
+//! There is no input, other than `Event::Resize` when the terminal changes size.
+
+use std::{
+    fmt::Write as _,
+    io::{self, Stdout, Write},
+};
+use crate::{
+    canvas::{Canvas, Color},
+    input::Event,
+    Error,
+};
+use super::Backend;
+
+/// Environment variable choosing the mode of `Terminal::stdout`, `sixel` or `blocks`.
+pub const MODE_VAR: &str = "GFX_TERMINAL";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    HalfBlocks,
+    Sixel,
+}
+
+impl Mode {
+    /// Mode set by `MODE_VAR`, otherwise `Sixel` for terminals known to support it.
+    /// Terminals are told apart by `TERM` and `TERM_PROGRAM`, so this is a guess.
+    pub fn detect() -> Self {
+        match std::env::var(MODE_VAR).as_deref() {
+            Ok("sixel") => return Self::Sixel,
+            Ok("blocks") => return Self::HalfBlocks,
+            _ => {},
+        }
+
+        let term = std::env::var("TERM").unwrap_or_default();
+        let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        let sixel_term = term.contains("sixel")
+            || ["mlterm", "foot", "yaft", "contour"].iter().any(|name| term.starts_with(name));
+        let sixel_program = ["WezTerm", "mintty", "iTerm.app"].contains(&program.as_str());
+        if sixel_term || sixel_program {
+            Self::Sixel
+        } else {
+            Self::HalfBlocks
+        }
+    }
+}
+
+/// Colors of the upper and lower halves of a cell.
+type Cell = ([u8; 3], [u8; 3]);
+
+pub struct Terminal<W: Write = Stdout> {
+    pub mode: Mode,
+    /// Size in character cells canvases are scaled to in `Mode::HalfBlocks`.
+    pub cells: (usize, usize),
+    /// Size in pixels canvases are scaled to in `Mode::Sixel`.
+    pub pixels: (usize, usize),
+    out: W,
+    /// Follows the size of the terminal on standard output.
+    fit: bool,
+    /// Mode and size of the image on screen, `None` before the first frame.
+    drawn: Option<(Mode, (usize, usize))>,
+    previous_cells: Vec<Cell>,
+    previous_pixels: Vec<[u8; 3]>,
+    /// Colors the terminal currently draws with.
+    foreground: Option<[u8; 3]>,
+    background: Option<[u8; 3]>,
+}
+
+impl Terminal<Stdout> {
+    /// Draws on standard output, in the mode from `Mode::detect`, filling the terminal
+    /// except for its last line and following its size where it can be queried.
+    pub fn stdout() -> Self {
+        let mut terminal = Self::new(io::stdout(), Mode::detect(), (80, 23));
+        terminal.fit = true;
+        terminal.fit_terminal();
+        terminal
+    }
+}
+
+impl<W: Write> Terminal<W> {
+    /// Draws to `out` in `cells`, with sixel images 8 by 16 pixels per cell.
+    pub fn new(out: W, mode: Mode, cells: (usize, usize)) -> Self {
+        Self {
+            mode,
+            cells,
+            pixels: (cells.0 * 8, cells.1 * 16),
+            out,
+            fit: false,
+            drawn: None,
+            previous_cells: Vec::new(),
+            previous_pixels: Vec::new(),
+            foreground: None,
+            background: None,
+        }
+    }
+
+    /// Returns whether the size changed.
+    fn fit_terminal(&mut self) -> bool {
+        let size = match terminal_size() {
+            Some(size) => size,
+            None => return false,
+        };
+        let rows = size.rows.saturating_sub(1).max(1);
+        let cells = (size.columns.max(1), rows);
+        let pixels = if size.width > 0 && size.height > 0 {
+            (size.width, size.height * rows / size.rows)
+        } else {
+            (cells.0 * 8, cells.1 * 16)
+        };
+
+        let changed = cells != self.cells || pixels != self.pixels;
+        if changed {
+            self.cells = cells;
+            self.pixels = pixels;
+            self.redraw();
+        }
+        changed
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    /// Draws the whole next frame, for when something else wrote to the terminal.
+    pub fn redraw(&mut self) {
+        self.drawn = None;
+        self.previous_cells.clear();
+        self.previous_pixels.clear();
+    }
+
+    fn write_half_blocks(&mut self, canvas: &Canvas) -> io::Result<()> {
+        let (columns, rows) = self.cells;
+        let pixels = resample(canvas, (columns, rows * 2));
+
+        let cells: Vec<Cell> = (0..rows * columns)
+            .map(|i| {
+                let (row, column) = (i / columns, i % columns);
+                (pixels[2 * row * columns + column], pixels[(2 * row + 1) * columns + column])
+            })
+            .collect();
+
+        let mut out = String::new();
+        // where the terminal's cursor is
+        let mut cursor = None;
+        for row in 0..rows {
+            for column in 0..columns {
+                let cell = cells[row * columns + column];
+                if self.previous_cells.get(row * columns + column) == Some(&cell) {
+                    continue;
+                }
+
+                if cursor != Some((row, column)) {
+                    write!(out, "\x1b[{};{}H", row + 1, column + 1).unwrap();
+                }
+                let (top, bottom) = cell;
+                if self.background != Some(bottom) {
+                    write!(out, "\x1b[48;2;{};{};{}m", bottom[0], bottom[1], bottom[2]).unwrap();
+                    self.background = Some(bottom);
+                }
+                if top == bottom {
+                    out.push(' ');
+                } else {
+                    if self.foreground != Some(top) {
+                        write!(out, "\x1b[38;2;{};{};{}m", top[0], top[1], top[2]).unwrap();
+                        self.foreground = Some(top);
+                    }
+                    out.push('▀');
+                }
+                cursor = Some((row, column + 1));
+            }
+        }
+
+        self.previous_cells = cells;
+        self.out.write_all(out.as_bytes())
+    }
+
+    fn write_sixel(&mut self, canvas: &Canvas) -> io::Result<()> {
+        let pixels = resample(canvas, self.pixels);
+        if pixels == self.previous_pixels {
+            return Ok(());
+        }
+        let mut out = String::from("\x1b[H");
+        encode_sixel(&mut out, &pixels, self.pixels);
+        self.previous_pixels = pixels;
+        self.out.write_all(out.as_bytes())
+    }
+}
+
+impl<W: Write> Backend for Terminal<W> {
+    fn poll_events(&mut self, events: &mut Vec<Event>) {
+        if self.fit && self.fit_terminal() {
+            let (width, height) = self.size();
+            events.push(Event::Resize { width, height });
+        }
+    }
+
+    fn present(&mut self, canvas: &Canvas) -> Result<(), Error> {
+        let layout = (self.mode, self.size());
+        if self.drawn != Some(layout) {
+            // hide the cursor and clear the screen
+            self.redraw();
+            self.out.write_all(b"\x1b[?25l\x1b[0m\x1b[2J")?;
+            self.foreground = None;
+            self.background = None;
+            self.drawn = Some(layout);
+        }
+        match self.mode {
+            Mode::HalfBlocks => self.write_half_blocks(canvas)?,
+            Mode::Sixel => self.write_sixel(canvas)?,
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    /// Half-block pixels or sixel pixels canvases are scaled to.
+    fn size(&self) -> (usize, usize) {
+        match self.mode {
+            Mode::HalfBlocks => (self.cells.0, self.cells.1 * 2),
+            Mode::Sixel => self.pixels,
+        }
+    }
+}
+
+impl<W: Write> Drop for Terminal<W> {
+    fn drop(&mut self) {
+        if self.drawn.is_some() {
+            // show the cursor again, below the image. Sixel images leave it there by themselves
+            if self.mode == Mode::HalfBlocks {
+                let _ = write!(self.out, "\x1b[{};1H", self.cells.1 + 1);
+            }
+            let _ = self.out.write_all(b"\x1b[0m\x1b[?25h\n");
+            let _ = self.out.flush();
+        }
+    }
+}
+
+/// Colors of `canvas` scaled to `width` by `height`, each the average of the pixels it covers.
+/// Alpha is ignored.
+fn resample(canvas: &Canvas, (width, height): (usize, usize)) -> Vec<[u8; 3]> {
+    let mut result = Vec::with_capacity(width * height);
+    if canvas.width() == 0 || canvas.height() == 0 {
+        result.resize(width * height, [0; 3]);
+        return result;
+    }
+
+    // pixels from `start` up to `end`, at least one
+    let span = |i: usize, to: usize, from: usize| {
+        let start = i * from / to;
+        let end = ((i + 1) * from / to).max(start + 1).min(from);
+        start.min(from - 1)..end
+    };
+    for y in 0..height {
+        let ys = span(y, height, canvas.height());
+        for x in 0..width {
+            let xs = span(x, width, canvas.width());
+            let mut sum = [0u32; 3];
+            for sy in ys.clone() {
+                for sx in xs.clone() {
+                    let Color { r, g, b, .. } = canvas.get((sx, sy));
+                    sum[0] += r as u32;
+                    sum[1] += g as u32;
+                    sum[2] += b as u32;
+                }
+            }
+            let count = (ys.len() * xs.len()) as u32;
+            result.push([
+                ((sum[0] + count / 2) / count) as u8,
+                ((sum[1] + count / 2) / count) as u8,
+                ((sum[2] + count / 2) / count) as u8,
+            ]);
+        }
+    }
+    result
+}
+
+/// Sixel image of `pixels`, quantized to a 6x6x6 color cube with ordered dithering.
+fn encode_sixel(out: &mut String, pixels: &[[u8; 3]], (width, height): (usize, usize)) {
+    const BAYER: [[u32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+    let indices: Vec<u8> = pixels
+        .iter()
+        .enumerate()
+        .map(|(i, rgb)| {
+            let threshold = BAYER[(i / width) % 4][(i % width) % 4];
+            // 51 between levels, spread over 16 thresholds
+            let level = |c: u8| ((c as u32 * 5 * 16 + threshold * 255 + 8) / (255 * 16)).min(5) as u8;
+            level(rgb[0]) * 36 + level(rgb[1]) * 6 + level(rgb[2])
+        })
+        .collect();
+
+    // pixels keep their ratio, the image its size
+    write!(out, "\x1bPq\"1;1;{};{}", width, height).unwrap();
+    let mut used = [false; 216];
+    for &index in &indices {
+        used[index as usize] = true;
+    }
+    for (index, _) in used.iter().enumerate().filter(|(_, &used)| used) {
+        let percent = |level: usize| level * 100 / 5;
+        write!(out, "#{};2;{};{};{}", index, percent(index / 36), percent(index / 6 % 6), percent(index % 6)).unwrap();
+    }
+
+    for band in (0..height).step_by(6) {
+        let rows = band..(band + 6).min(height);
+        let mut colors: Vec<u8> = rows.clone().flat_map(|y| indices[y * width..(y + 1) * width].iter().copied()).collect();
+        colors.sort_unstable();
+        colors.dedup();
+
+        for (n, &color) in colors.iter().enumerate() {
+            if n > 0 {
+                // back to the start of the band
+                out.push('$');
+            }
+            write!(out, "#{}", color).unwrap();
+
+            let sixel = |x: usize| {
+                let bits = rows.clone().enumerate().fold(0u8, |bits, (bit, y)| {
+                    bits | ((indices[y * width + x] == color) as u8) << bit
+                });
+                (63 + bits) as char
+            };
+            // run-length encoded, empty sixels at the end are left out
+            let mut x = 0;
+            let mut end = width;
+            while end > 0 && sixel(end - 1) == '?' {
+                end -= 1;
+            }
+            while x < end {
+                let c = sixel(x);
+                let mut run = 1;
+                while x + run < end && sixel(x + run) == c {
+                    run += 1;
+                }
+                if run > 3 {
+                    write!(out, "!{}{}", run, c).unwrap();
+                } else {
+                    (0..run).for_each(|_| out.push(c));
+                }
+                x += run;
+            }
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+}
+
+struct TerminalSize {
+    columns: usize,
+    rows: usize,
+    /// In pixels, 0 if the terminal does not tell.
+    width: usize,
+    height: usize,
+}
+
+/// Size of the terminal on standard output.
+#[cfg(target_os = "linux")]
+fn terminal_size() -> Option<TerminalSize> {
+    use std::os::raw::{c_int, c_ulong, c_ushort};
+
+    #[repr(C)]
+    struct WinSize {
+        rows: c_ushort,
+        columns: c_ushort,
+        width: c_ushort,
+        height: c_ushort,
+    }
+    extern "C" {
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    }
+    const STDOUT: c_int = 1;
+    const TIOCGWINSZ: c_ulong = 0x5413;
+
+    let mut size = WinSize { rows: 0, columns: 0, width: 0, height: 0 };
+    if unsafe { ioctl(STDOUT, TIOCGWINSZ, &mut size as *mut WinSize) } != 0 || size.rows == 0 || size.columns == 0 {
+        return None;
+    }
+    Some(TerminalSize {
+        columns: size.columns as usize,
+        rows: size.rows as usize,
+        width: size.width as usize,
+        height: size.height as usize,
+    })
+}
+
+/// Size of the terminal from `COLUMNS` and `LINES`, which shells set but do not always export.
+#[cfg(not(target_os = "linux"))]
+fn terminal_size() -> Option<TerminalSize> {
+    let var = |name| std::env::var(name).ok()?.parse::<usize>().ok().filter(|&n| n > 0);
+    Some(TerminalSize { columns: var("COLUMNS")?, rows: var("LINES")?, width: 0, height: 0 })
+}
@@ -0,0 +1,698 @@
+//! Backend serving frames to VNC viewers over the RFB protocol, versions 3.3 to 3.8.
+//!
+//! Any number of viewers can connect, without authentication, so bind to a local
+//! address or tunnel the port. Updates carry the 64x64 tiles that changed since the
+//! viewer's previous update, in the first of Raw, Zlib and ZRLE that the viewer lists.
+//! Keys and the pointer of every viewer become input events, clipboard text is ignored.
+
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+};
+use miniz_oxide::deflate::core::{self as deflate, CompressorOxide, TDEFLFlush};
+use crate::{
+    canvas::{Canvas, Color, Rect},
+    input::{Event, Key, MouseButton},
+    Error,
+};
+use super::{Backend, keysym};
+
+/// Port of VNC display `:0`, viewers connect to `DEFAULT_PORT + n` for display `:n`.
+pub const DEFAULT_PORT: u16 = 5900;
+
+/// Side of the tiles changes are tracked in, also the tile size of ZRLE.
+const TILE: usize = 64;
+
+const BLACK: Color = Color { r: 0, g: 0, b: 0, a: 255 };
+
+/// Listens for viewers and sends them what is presented.
+pub struct Server {
+    listener: TcpListener,
+    name: String,
+    size: (usize, usize),
+    /// Last presented canvas, cropped or padded with black to `size`.
+    frame: Vec<Color>,
+    clients: Vec<Client>,
+}
+
+impl Server {
+    /// Listens on `address` for viewers of a `width` by `height` framebuffer, shown as `name`.
+    /// Port 0 picks a free one, see `local_addr`.
+    pub fn bind(address: impl ToSocketAddrs, name: &str, width: usize, height: usize) -> Result<Self, Error> {
+        if width > u16::MAX as usize || height > u16::MAX as usize {
+            let message = "RFB framebuffers are at most 65535 pixels wide and high";
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message).into());
+        }
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            name: name.to_owned(),
+            size: (width, height),
+            frame: vec![BLACK; width * height],
+            clients: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Number of connected viewers, including ones still in the handshake.
+    pub fn clients(&self) -> usize {
+        self.clients.len()
+    }
+
+    fn tiles(&self) -> (usize, usize) {
+        (self.size.0.div_ceil(TILE), self.size.1.div_ceil(TILE))
+    }
+
+    /// Sends updates to viewers waiting for one.
+    fn update_clients(&mut self) {
+        let tiles = self.tiles();
+        for client in &mut self.clients {
+            client.send_update(&self.frame, self.size, tiles);
+            client.flush();
+        }
+    }
+}
+
+impl Backend for Server {
+    fn poll_events(&mut self, events: &mut Vec<Event>) {
+        let tiles = self.tiles();
+        while let Ok((stream, _)) = self.listener.accept() {
+            if let Ok(client) = Client::new(stream, tiles) {
+                self.clients.push(client);
+            }
+        }
+        for client in &mut self.clients {
+            client.receive(events, &self.name, self.size);
+        }
+        self.update_clients();
+        self.clients.retain(|client| {
+            if client.closed {
+                client.release(events);
+            }
+            !client.closed
+        });
+    }
+
+    /// Viewers that fail to take updates are dropped, so this does not fail.
+    fn present(&mut self, canvas: &Canvas) -> Result<(), Error> {
+        let (width, height) = self.size;
+        let (columns, rows) = self.tiles();
+        let pixels = canvas.pixels();
+        for row in 0..rows {
+            for column in 0..columns {
+                let mut changed = false;
+                for y in row * TILE..((row + 1) * TILE).min(height) {
+                    for x in column * TILE..((column + 1) * TILE).min(width) {
+                        let color = if x < canvas.width() && y < canvas.height() {
+                            pixels[y * canvas.width() + x]
+                        } else {
+                            BLACK
+                        };
+                        let pixel = &mut self.frame[y * width + x];
+                        if *pixel != color {
+                            *pixel = color;
+                            changed = true;
+                        }
+                    }
+                }
+                if changed {
+                    for client in &mut self.clients {
+                        client.dirty[row * columns + column] = true;
+                    }
+                }
+            }
+        }
+        self.update_clients();
+        Ok(())
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Version,
+    Security,
+    Init,
+    Normal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Raw,
+    Zlib,
+    Zrle,
+}
+
+impl Encoding {
+    fn from_id(id: i32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Raw),
+            6 => Some(Self::Zlib),
+            16 => Some(Self::Zrle),
+            _ => None,
+        }
+    }
+
+    fn id(self) -> i32 {
+        match self {
+            Self::Raw => 0,
+            Self::Zlib => 6,
+            Self::Zrle => 16,
+        }
+    }
+}
+
+/// How a viewer wants pixels sent. Only true color formats are supported.
+#[derive(Clone, Copy, Debug)]
+struct PixelFormat {
+    bits_per_pixel: u8,
+    depth: u8,
+    big_endian: bool,
+    true_color: bool,
+    /// Red, green and blue.
+    max: [u16; 3],
+    shift: [u8; 3],
+}
+
+impl PixelFormat {
+    /// 32-bit little endian BGRX, the memory layout of `Color`.
+    const NATIVE: Self = Self {
+        bits_per_pixel: 32,
+        depth: 24,
+        big_endian: false,
+        true_color: true,
+        max: [255, 255, 255],
+        shift: [16, 8, 0],
+    };
+
+    fn parse(bytes: &[u8]) -> Self {
+        let u16_at = |i: usize| u16::from_be_bytes([bytes[i], bytes[i + 1]]);
+        Self {
+            bits_per_pixel: bytes[0],
+            depth: bytes[1],
+            big_endian: bytes[2] != 0,
+            true_color: bytes[3] != 0,
+            max: [u16_at(4), u16_at(6), u16_at(8)],
+            shift: [bytes[10], bytes[11], bytes[12]],
+        }
+    }
+
+    fn is_supported(&self) -> bool {
+        self.true_color && [8, 16, 32].contains(&self.bits_per_pixel) && self.shift.iter().all(|&shift| shift < 32)
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[self.bits_per_pixel, self.depth, self.big_endian as u8, self.true_color as u8]);
+        for max in &self.max {
+            out.extend_from_slice(&max.to_be_bytes());
+        }
+        out.extend_from_slice(&self.shift);
+        out.extend_from_slice(&[0; 3]);
+    }
+
+    fn value(&self, color: Color) -> u32 {
+        [color.r, color.g, color.b].iter().zip(&self.max).zip(&self.shift)
+            .map(|((&channel, &max), &shift)| ((channel as u32 * max as u32 + 127) / 255) << shift)
+            .fold(0, |value, channel| value | channel)
+    }
+
+    /// Range of the bytes of a pixel value that are sent, in the order of `to_be_bytes`.
+    fn bytes(&self) -> std::ops::Range<usize> {
+        4 - self.bits_per_pixel as usize / 8..4
+    }
+
+    /// Range of the bytes of a pixel value that ZRLE sends, in the order of `to_be_bytes`.
+    /// Three bytes for 32-bit formats whose colors fit in three.
+    fn compact_bytes(&self) -> std::ops::Range<usize> {
+        let bytes = self.bits_per_pixel as usize / 8;
+        let used = self.value(Color { r: 255, g: 255, b: 255, a: 255 });
+        if bytes == 4 && self.depth <= 24 && used <= 0xff_ffff {
+            1..4
+        } else if bytes == 4 && self.depth <= 24 && used & 0xff == 0 {
+            0..3
+        } else {
+            self.bytes()
+        }
+    }
+
+    fn push(&self, color: Color, bytes: &std::ops::Range<usize>, out: &mut Vec<u8>) {
+        let value = self.value(color).to_be_bytes();
+        let value = &value[bytes.clone()];
+        if self.big_endian {
+            out.extend_from_slice(value);
+        } else {
+            out.extend(value.iter().rev());
+        }
+    }
+}
+
+/// A connected viewer.
+struct Client {
+    stream: TcpStream,
+    state: State,
+    /// Minor protocol version agreed on, 3, 7 or 8.
+    minor: u8,
+    input: Vec<u8>,
+    /// Bytes still to come of a message that is thrown away as it arrives.
+    skip: usize,
+    output: Vec<u8>,
+    /// Bytes of `output` already sent.
+    sent: usize,
+    format: PixelFormat,
+    encoding: Encoding,
+    /// Zlib and ZRLE rectangles each continue a stream of their own.
+    zlib: Option<Box<CompressorOxide>>,
+    zrle: Option<Box<CompressorOxide>>,
+    update_requested: bool,
+    /// Tiles changed since the last update sent.
+    dirty: Vec<bool>,
+    buttons: u8,
+    pointer: (isize, isize),
+    keys: Vec<Key>,
+    closed: bool,
+}
+
+impl Client {
+    fn new(stream: TcpStream, (columns, rows): (usize, usize)) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        let mut client = Self {
+            stream,
+            state: State::Version,
+            minor: 8,
+            input: Vec::new(),
+            skip: 0,
+            output: Vec::new(),
+            sent: 0,
+            format: PixelFormat::NATIVE,
+            encoding: Encoding::Raw,
+            zlib: None,
+            zrle: None,
+            update_requested: false,
+            dirty: vec![true; columns * rows],
+            buttons: 0,
+            pointer: (-1, -1),
+            keys: Vec::new(),
+            closed: false,
+        };
+        client.output.extend_from_slice(b"RFB 003.008\n");
+        client.flush();
+        Ok(client)
+    }
+
+    /// Reads and handles whatever the viewer sent.
+    fn receive(&mut self, events: &mut Vec<Event>, name: &str, size: (usize, usize)) {
+        // messages sent right before disconnecting are still handled
+        let mut disconnected = false;
+        let mut buffer = [0; 4096];
+        while !disconnected {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => disconnected = true,
+                Ok(read) => {
+                    let skipped = read.min(self.skip);
+                    self.skip -= skipped;
+                    self.input.extend_from_slice(&buffer[skipped..read]);
+                },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(_) => disconnected = true,
+            }
+        }
+
+        let mut input = std::mem::take(&mut self.input);
+        let mut handled = 0;
+        while !self.closed {
+            let skipped = (input.len() - handled).min(self.skip);
+            self.skip -= skipped;
+            handled += skipped;
+            match self.handle(&input[handled..], events, name, size) {
+                Some(length) => handled += length,
+                None => break,
+            }
+        }
+        input.drain(..handled);
+        self.input = input;
+        self.closed |= disconnected;
+    }
+
+    /// Handles the message at the start of `input`, returns its length or `None`
+    /// if it did not arrive whole yet.
+    fn handle(&mut self, input: &[u8], events: &mut Vec<Event>, name: &str, (width, height): (usize, usize)) -> Option<usize> {
+        let need = |length: usize| if input.len() >= length { Some(length) } else { None };
+        let u16_at = |i: usize| u16::from_be_bytes([input[i], input[i + 1]]);
+        let u32_at = |i: usize| u32::from_be_bytes([input[i], input[i + 1], input[i + 2], input[i + 3]]);
+
+        match self.state {
+            State::Version => {
+                let length = need(12)?;
+                let minor = match &input[..12] {
+                    b"RFB 003.003\n" | b"RFB 003.005\n" => 3,
+                    b"RFB 003.007\n" => 7,
+                    version if version.starts_with(b"RFB 003.") => 8,
+                    _ => {
+                        self.closed = true;
+                        return Some(length);
+                    },
+                };
+                self.minor = minor;
+                if minor == 3 {
+                    // the server picks the security type, None
+                    self.output.extend_from_slice(&1u32.to_be_bytes());
+                    self.state = State::Init;
+                } else {
+                    self.output.extend_from_slice(&[1, 1]);
+                    self.state = State::Security;
+                }
+                Some(length)
+            },
+            State::Security => {
+                let length = need(1)?;
+                if input[0] != 1 {
+                    if self.minor == 8 {
+                        let reason = b"only security type None is supported";
+                        self.output.extend_from_slice(&1u32.to_be_bytes());
+                        self.output.extend_from_slice(&(reason.len() as u32).to_be_bytes());
+                        self.output.extend_from_slice(reason);
+                        self.flush();
+                    }
+                    self.closed = true;
+                    return Some(length);
+                }
+                if self.minor == 8 {
+                    self.output.extend_from_slice(&0u32.to_be_bytes());
+                }
+                self.state = State::Init;
+                Some(length)
+            },
+            State::Init => {
+                // whether to disconnect other viewers, they are always shared
+                let length = need(1)?;
+                self.output.extend_from_slice(&(width as u16).to_be_bytes());
+                self.output.extend_from_slice(&(height as u16).to_be_bytes());
+                self.format.write(&mut self.output);
+                self.output.extend_from_slice(&(name.len() as u32).to_be_bytes());
+                self.output.extend_from_slice(name.as_bytes());
+                self.state = State::Normal;
+                Some(length)
+            },
+            State::Normal => match *input.first()? {
+                0 => {
+                    let length = need(20)?;
+                    let format = PixelFormat::parse(&input[4..20]);
+                    if format.is_supported() {
+                        self.format = format;
+                    } else {
+                        self.closed = true;
+                    }
+                    Some(length)
+                },
+                2 => {
+                    let length = need(4 + 4 * u16_at(2) as usize)?;
+                    self.encoding = (0..u16_at(2) as usize)
+                        .find_map(|i| Encoding::from_id(u32_at(4 + 4 * i) as i32))
+                        .unwrap_or(Encoding::Raw);
+                    Some(length)
+                },
+                3 => {
+                    let length = need(10)?;
+                    if input[1] == 0 {
+                        let rect = Rect::new((u16_at(2) as isize, u16_at(4) as isize), (u16_at(6) as usize, u16_at(8) as usize));
+                        self.mark_dirty(rect, width);
+                    }
+                    self.update_requested = true;
+                    Some(length)
+                },
+                4 => {
+                    let length = need(8)?;
+                    self.key(input[1] != 0, u32_at(4), events);
+                    Some(length)
+                },
+                5 => {
+                    let length = need(6)?;
+                    self.pointer(input[1], (u16_at(2) as isize, u16_at(4) as isize), events);
+                    Some(length)
+                },
+                6 => {
+                    // cut text is ignored, so it is not kept until it arrives whole
+                    let length = need(8)?;
+                    self.skip = u32_at(4) as usize;
+                    Some(length)
+                },
+                _ => {
+                    self.closed = true;
+                    None
+                },
+            },
+        }
+    }
+
+    /// Marks the tiles `rect` touches, of a framebuffer `width` wide.
+    fn mark_dirty(&mut self, rect: Rect, width: usize) {
+        let columns = width.div_ceil(TILE);
+        let rows = self.dirty.len() / columns.max(1);
+        let tiles = Rect::new((0, 0), (columns, rows));
+        let touched = Rect::from_corners(
+            (rect.x / TILE as isize, rect.y / TILE as isize),
+            ((rect.right() + TILE as isize - 1) / TILE as isize, (rect.bottom() + TILE as isize - 1) / TILE as isize),
+        ).intersection(&tiles);
+        for row in touched.y..touched.bottom() {
+            for column in touched.x..touched.right() {
+                self.dirty[row as usize * columns + column as usize] = true;
+            }
+        }
+    }
+
+    fn key(&mut self, down: bool, keysym: u32, events: &mut Vec<Event>) {
+        let key = keysym::key(keysym);
+        if down {
+            let repeat = self.keys.contains(&key);
+            if !repeat {
+                self.keys.push(key);
+            }
+            events.push(Event::KeyDown { key, repeat });
+            let shortcut = self.keys.iter().any(|&held| held == Key::Control || held == Key::Alt);
+            if let Some(c) = keysym::char(keysym).filter(|_| !shortcut) {
+                events.push(Event::Text(c));
+            }
+        } else {
+            self.keys.retain(|&held| held != key);
+            events.push(Event::KeyUp { key });
+        }
+    }
+
+    fn pointer(&mut self, buttons: u8, position: (isize, isize), events: &mut Vec<Event>) {
+        if position != self.pointer {
+            self.pointer = position;
+            events.push(Event::MouseMove { x: position.0, y: position.1 });
+        }
+        let changed = buttons ^ self.buttons;
+        self.buttons = buttons;
+        for bit in 0..8usize {
+            if changed & 1 << bit == 0 {
+                continue;
+            }
+            let pressed = buttons & 1 << bit != 0;
+            // bits are X11 buttons 1 to 8, 4 to 7 are wheel notches
+            let button = match bit {
+                0 => MouseButton::Left,
+                1 => MouseButton::Middle,
+                2 => MouseButton::Right,
+                7 => MouseButton::X1,
+                wheel => {
+                    if pressed {
+                        let (x, y) = [(0.0, 1.0), (0.0, -1.0), (-1.0, 0.0), (1.0, 0.0)][wheel - 3];
+                        events.push(Event::MouseWheel { x, y });
+                    }
+                    continue;
+                },
+            };
+            events.push(if pressed { Event::MouseDown { button } } else { Event::MouseUp { button } });
+        }
+    }
+
+    /// Releases the keys and buttons held, for a viewer that disconnected.
+    fn release(&self, events: &mut Vec<Event>) {
+        events.extend(self.keys.iter().map(|&key| Event::KeyUp { key }));
+        let buttons = [(0, MouseButton::Left), (1, MouseButton::Middle), (2, MouseButton::Right), (7, MouseButton::X1)];
+        for &(bit, button) in &buttons {
+            if self.buttons & 1 << bit != 0 {
+                events.push(Event::MouseUp { button });
+            }
+        }
+    }
+
+    /// Sends the dirty tiles if the viewer asked for an update and took the previous one.
+    fn send_update(&mut self, frame: &[Color], (width, height): (usize, usize), (columns, rows): (usize, usize)) {
+        if self.state != State::Normal || !self.update_requested || self.sent < self.output.len() {
+            return;
+        }
+
+        // runs of dirty tiles in a row of them
+        let mut rects = Vec::new();
+        for row in 0..rows {
+            let mut column = 0;
+            while column < columns {
+                if !self.dirty[row * columns + column] {
+                    column += 1;
+                    continue;
+                }
+                let start = column;
+                while column < columns && self.dirty[row * columns + column] {
+                    column += 1;
+                }
+                let rect = Rect::new(((start * TILE) as isize, (row * TILE) as isize), ((column - start) * TILE, TILE));
+                rects.push(rect.intersection(&Rect::new((0, 0), (width, height))));
+            }
+        }
+        if rects.is_empty() {
+            return;
+        }
+        self.dirty.iter_mut().for_each(|dirty| *dirty = false);
+        self.update_requested = false;
+
+        self.output.extend_from_slice(&[0, 0]);
+        self.output.extend_from_slice(&(rects.len() as u16).to_be_bytes());
+        for rect in rects {
+            for value in &[rect.x as u16, rect.y as u16, rect.width as u16, rect.height as u16] {
+                self.output.extend_from_slice(&value.to_be_bytes());
+            }
+            self.output.extend_from_slice(&self.encoding.id().to_be_bytes());
+            let rows = (rect.y as usize..rect.bottom() as usize)
+                .map(|y| &frame[y * width + rect.x as usize..y * width + rect.right() as usize]);
+            match self.encoding {
+                Encoding::Raw => {
+                    let bytes = self.format.bytes();
+                    for row in rows {
+                        for &color in row {
+                            self.format.push(color, &bytes, &mut self.output);
+                        }
+                    }
+                },
+                Encoding::Zlib => {
+                    let bytes = self.format.bytes();
+                    let mut data = Vec::new();
+                    for row in rows {
+                        for &color in row {
+                            self.format.push(color, &bytes, &mut data);
+                        }
+                    }
+                    let compressor = self.zlib.get_or_insert_with(new_compressor);
+                    compress(compressor, &data, &mut self.output);
+                },
+                Encoding::Zrle => {
+                    let rows: Vec<_> = rows.collect();
+                    let mut data = Vec::new();
+                    for tile_rows in rows.chunks(TILE) {
+                        for x in (0..rect.width).step_by(TILE) {
+                            let tile: Vec<_> = tile_rows.iter().map(|row| &row[x..(x + TILE).min(rect.width)]).collect();
+                            zrle_tile(&tile, &self.format, &mut data);
+                        }
+                    }
+                    let compressor = self.zrle.get_or_insert_with(new_compressor);
+                    compress(compressor, &data, &mut self.output);
+                },
+            }
+        }
+    }
+
+    /// Writes as much of the output as the socket takes without blocking.
+    fn flush(&mut self) {
+        while self.sent < self.output.len() {
+            match self.stream.write(&self.output[self.sent..]) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                },
+                Ok(written) => self.sent += written,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(_) => {
+                    self.closed = true;
+                    break;
+                },
+            }
+        }
+        self.output.clear();
+        self.sent = 0;
+    }
+}
+
+fn new_compressor() -> Box<CompressorOxide> {
+    Box::new(CompressorOxide::new(deflate::create_comp_flags_from_zip_params(6, 15, 0)))
+}
+
+/// Appends `data` compressed with a sync flush, so the viewer can decompress it
+/// whole, and preceded by its length.
+fn compress(compressor: &mut CompressorOxide, data: &[u8], out: &mut Vec<u8>) {
+    let start = out.len();
+    out.extend_from_slice(&[0; 4]);
+    deflate::compress_to_output(compressor, data, TDEFLFlush::Sync, |compressed| {
+        out.extend_from_slice(compressed);
+        true
+    });
+    let length = (out.len() - start - 4) as u32;
+    out[start..start + 4].copy_from_slice(&length.to_be_bytes());
+}
+
+/// Appends a ZRLE tile: solid, with a palette of up to 16 colors, or raw.
+fn zrle_tile(rows: &[&[Color]], format: &PixelFormat, out: &mut Vec<u8>) {
+    let bytes = format.compact_bytes();
+    let mut palette = Vec::new();
+    for &color in rows.iter().flat_map(|row| row.iter()) {
+        if !palette.contains(&color) {
+            palette.push(color);
+            if palette.len() > 16 {
+                break;
+            }
+        }
+    }
+
+    match palette.len() {
+        1 => {
+            out.push(1);
+            format.push(palette[0], &bytes, out);
+        },
+        2..=16 => {
+            out.push(palette.len() as u8);
+            for &color in &palette {
+                format.push(color, &bytes, out);
+            }
+            let bits = match palette.len() {
+                2 => 1,
+                3..=4 => 2,
+                _ => 4,
+            };
+            // indices packed from the most significant bit, rows start on a byte
+            for row in rows {
+                let mut byte = 0u8;
+                let mut used = 0;
+                for color in row.iter() {
+                    let index = palette.iter().position(|entry| entry == color).unwrap() as u8;
+                    byte |= index << (8 - bits - used);
+                    used += bits;
+                    if used == 8 {
+                        out.push(byte);
+                        byte = 0;
+                        used = 0;
+                    }
+                }
+                if used > 0 {
+                    out.push(byte);
+                }
+            }
+        },
+        _ => {
+            out.push(0);
+            for &color in rows.iter().flat_map(|row| row.iter()) {
+                format.push(color, &bytes, out);
+            }
+        },
+    }
+}
@@ -0,0 +1,332 @@
+//! Window on Windows, presenting with `StretchDIBits`.
+
+use std::{
+    cell::{Cell, RefCell},
+    ffi::CString,
+};
+use winapi::{
+    ctypes::c_int,
+    shared::{
+        minwindef::{HIWORD, LOWORD, LPARAM, LRESULT, UINT, WPARAM},
+        windef::{HDC, HWND, RECT},
+        winerror::ERROR_CLASS_ALREADY_EXISTS,
+    },
+    um::{
+        errhandlingapi::GetLastError,
+        libloaderapi::GetModuleHandleA,
+        wingdi::{BI_RGB, BITMAPINFO, BITMAPINFOHEADER, DIB_RGB_COLORS, SRCCOPY, StretchDIBits},
+        winuser::*,
+    },
+};
+use crate::{
+    canvas::Canvas,
+    input::{Event, Key, MouseButton},
+    Error,
+};
+use super::Backend;
+
+thread_local! {
+    /// Events translated by `window_procedure`, which Windows calls while messages are dispatched.
+    static EVENTS: RefCell<Vec<Event>> = const { RefCell::new(Vec::new()) };
+    /// First half of a character outside the Basic Multilingual Plane, sent in two `WM_CHAR`s.
+    static HIGH_SURROGATE: Cell<Option<u16>> = const { Cell::new(None) };
+}
+
+/// Window with a client area a canvas is shown in.
+///
+/// Events are collected per thread, so there should be one window per thread.
+pub struct Window {
+    hwnd: HWND,
+    device_context: HDC,
+    size: (usize, usize),
+}
+
+impl Window {
+    /// Shows a window titled `title` with a client area of `width` by `height` pixels.
+    pub fn new(title: &str, width: usize, height: usize) -> Result<Self, Error> {
+        // gets current .exe module handle. Should pass module name to use in .dll
+        let instance_handle = unsafe { GetModuleHandleA(std::ptr::null()) };
+        if instance_handle.is_null() {
+            return Err(Error::last_os_error("GetModuleHandleA"));
+        }
+
+        let window_class_name = b"gfx\0";
+
+        // TODO: use WNDCLASSEX for small icon
+        let window_class = WNDCLASSA {
+            style: 0,
+            lpfnWndProc: Some(window_procedure),
+
+            // TODO: number of extra bytes to allocate following the class struct. What is this for?
+            cbClsExtra: 0,
+
+            // TODO: number of extra bytes to allocate following the window instance. What is this for?
+            cbWndExtra: 0,
+            hInstance: instance_handle,
+
+            // TODO: these are handles to icon/cursor resources. Use a resource or is there an another way?
+            hIcon: std::ptr::null_mut(),
+            hCursor: unsafe { LoadCursorW(std::ptr::null_mut(), IDC_ARROW) },
+
+            // some brush stuff. We draw background ourselves
+            hbrBackground: std::ptr::null_mut(),
+
+            // no menu resource
+            lpszMenuName: std::ptr::null_mut(),
+
+            lpszClassName: window_class_name.as_ptr() as *const _,
+        };
+
+        // the class stays registered for the process, later windows reuse it
+        if unsafe { RegisterClassA(&window_class as *const _) } == 0
+            && unsafe { GetLastError() } != ERROR_CLASS_ALREADY_EXISTS
+        {
+            return Err(Error::last_os_error("RegisterClassA"));
+        }
+
+        // interior nul bytes would cut the title short anyway
+        let window_caption = CString::new(title.replace('\0', "")).unwrap();
+
+        // resizable, with minimize and maximize buttons
+        let window_style = WS_OVERLAPPEDWINDOW | WS_VISIBLE;
+
+        // get window size for desired client area size
+        let (window_width, window_height) = {
+            let mut rect = RECT { left: 0, top: 0, right: width as c_int, bottom: height as c_int };
+            if unsafe { AdjustWindowRectEx(&mut rect, window_style, 0, 0) } == 0 {
+                return Err(Error::last_os_error("AdjustWindowRectEx"));
+            }
+            (rect.right - rect.left, rect.bottom - rect.top)
+        };
+
+        let hwnd = unsafe { CreateWindowExA(
+            0, // TODO: check extended styles
+            window_class_name.as_ptr() as *const _,
+            window_caption.as_ptr(),
+            window_style,
+            CW_USEDEFAULT, // x
+            CW_USEDEFAULT, // y
+            window_width,
+            window_height,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            instance_handle,
+            std::ptr::null_mut()
+        ) };
+        if hwnd.is_null() {
+            return Err(Error::last_os_error("CreateWindowExA"));
+        }
+
+        let device_context = unsafe { GetDC(hwnd) };
+        if device_context.is_null() {
+            // GetDC does not set the last error
+            unsafe { DestroyWindow(hwnd) };
+            let message = "no device context for the window".to_owned();
+            return Err(Error::Platform { call: "GetDC", message });
+        }
+
+        Ok(Self { hwnd, device_context, size: (width, height) })
+    }
+}
+
+impl Backend for Window {
+    fn poll_events(&mut self, events: &mut Vec<Event>) {
+        loop {
+            let msg = unsafe {
+                let mut msg = std::mem::MaybeUninit::uninit();
+                if PeekMessageA(msg.as_mut_ptr(), std::ptr::null_mut(), 0, 0, PM_REMOVE) != 0 {
+                    Some(msg.assume_init())
+                } else {
+                    None
+                }
+            };
+
+            match msg {
+                None => break,
+                Some(msg) if msg.message == WM_QUIT => EVENTS.with(|queue| queue.borrow_mut().push(Event::Close)),
+                Some(msg) => unsafe {
+                    TranslateMessage(&msg);
+                    DispatchMessageA(&msg);
+                },
+            }
+        }
+
+        EVENTS.with(|queue| {
+            for event in queue.borrow_mut().drain(..) {
+                if let Event::Resize { width, height } = event {
+                    self.size = (width, height);
+                }
+                events.push(event);
+            }
+        });
+    }
+
+    fn present(&mut self, canvas: &Canvas) -> Result<(), Error> {
+        // minimized
+        if self.size.0 == 0 || self.size.1 == 0 || canvas.width() == 0 || canvas.height() == 0 {
+            return Ok(());
+        }
+        let bitmap_info = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: canvas.width() as c_int,
+                biHeight: -(canvas.height() as c_int), // negative means that bitmap is top-down
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB,
+                ..unsafe { std::mem::zeroed() }
+            },
+            ..unsafe { std::mem::zeroed() }
+        };
+        let (width, height) = (self.size.0 as c_int, self.size.1 as c_int);
+
+        let lines = unsafe { StretchDIBits(
+            self.device_context,
+            0,
+            0,
+            width,
+            height,
+            0,
+            0,
+            canvas.width() as _,
+            canvas.height() as _,
+            canvas.data() as *mut _,
+            &bitmap_info,
+            DIB_RGB_COLORS,
+            SRCCOPY,
+        ) };
+        if lines == 0 {
+            return Err(Error::last_os_error("StretchDIBits"));
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        unsafe {
+            ReleaseDC(self.hwnd, self.device_context);
+            DestroyWindow(self.hwnd);
+        }
+    }
+}
+
+unsafe extern "system" fn window_procedure(hwnd: HWND, u_msg: UINT, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+    let push = |event| EVENTS.with(|queue| queue.borrow_mut().push(event));
+    // signed, coordinates are negative left of and above the client area while captured
+    let x = LOWORD(l_param as u32) as i16 as isize;
+    let y = HIWORD(l_param as u32) as i16 as isize;
+
+    match u_msg {
+        WM_KEYDOWN | WM_SYSKEYDOWN => {
+            // bit 30 is the previous key state
+            push(Event::KeyDown { key: key(w_param), repeat: l_param & (1 << 30) != 0 });
+            // Alt+F4 and other system keys still work
+            if u_msg == WM_SYSKEYDOWN {
+                return DefWindowProcA(hwnd, u_msg, w_param, l_param);
+            }
+        },
+        WM_KEYUP | WM_SYSKEYUP => {
+            push(Event::KeyUp { key: key(w_param) });
+            if u_msg == WM_SYSKEYUP {
+                return DefWindowProcA(hwnd, u_msg, w_param, l_param);
+            }
+        },
+        WM_CHAR => {
+            let unit = w_param as u16;
+            let c = match unit {
+                0xd800..=0xdbff => {
+                    HIGH_SURROGATE.with(|high| high.set(Some(unit)));
+                    None
+                },
+                0xdc00..=0xdfff => HIGH_SURROGATE
+                    .with(|high| high.take())
+                    .and_then(|high| std::char::decode_utf16([high, unit].iter().copied()).next())
+                    .and_then(Result::ok),
+                _ => std::char::from_u32(unit as u32),
+            };
+            if let Some(c) = c.filter(|c| !c.is_control()) {
+                push(Event::Text(c));
+            }
+        },
+        WM_MOUSEMOVE => push(Event::MouseMove { x, y }),
+        WM_LBUTTONDOWN | WM_RBUTTONDOWN | WM_MBUTTONDOWN | WM_XBUTTONDOWN => {
+            // keeps getting mouse messages while dragged outside the window
+            SetCapture(hwnd);
+            push(Event::MouseDown { button: button(u_msg, w_param) });
+        },
+        WM_LBUTTONUP | WM_RBUTTONUP | WM_MBUTTONUP | WM_XBUTTONUP => {
+            if (w_param & (MK_LBUTTON | MK_RBUTTON | MK_MBUTTON | MK_XBUTTON1 | MK_XBUTTON2)) == 0 {
+                ReleaseCapture();
+            }
+            push(Event::MouseUp { button: button(u_msg, w_param) });
+        },
+        WM_MOUSEWHEEL => {
+            push(Event::MouseWheel { x: 0.0, y: GET_WHEEL_DELTA_WPARAM(w_param) as f32 / WHEEL_DELTA as f32 });
+        },
+        WM_MOUSEHWHEEL => {
+            push(Event::MouseWheel { x: GET_WHEEL_DELTA_WPARAM(w_param) as f32 / WHEEL_DELTA as f32, y: 0.0 });
+        },
+        WM_SIZE => push(Event::Resize { width: x as u16 as usize, height: y as u16 as usize }),
+        WM_SETFOCUS => push(Event::Focus(true)),
+        WM_KILLFOCUS => push(Event::Focus(false)),
+        // the application decides when to close, `DefWindowProcA` would destroy the window
+        WM_CLOSE => push(Event::Close),
+        _ => return DefWindowProcA(hwnd, u_msg, w_param, l_param),
+    }
+    0
+}
+
+fn button(u_msg: UINT, w_param: WPARAM) -> MouseButton {
+    match u_msg {
+        WM_LBUTTONDOWN | WM_LBUTTONUP => MouseButton::Left,
+        WM_RBUTTONDOWN | WM_RBUTTONUP => MouseButton::Right,
+        WM_MBUTTONDOWN | WM_MBUTTONUP => MouseButton::Middle,
+        _ if GET_XBUTTON_WPARAM(w_param) == XBUTTON1 => MouseButton::X1,
+        _ => MouseButton::X2,
+    }
+}
+
+/// Key of a virtual-key code.
+fn key(w_param: WPARAM) -> Key {
+    let code = w_param as c_int;
+    match code {
+        0x30..=0x39 => Key::digit((code - 0x30) as u32).unwrap(),
+        0x41..=0x5a => Key::letter((code as u8) as char).unwrap(),
+        VK_F1..=VK_F12 => Key::function((code - VK_F1 + 1) as u32).unwrap(),
+        VK_ESCAPE => Key::Escape,
+        VK_RETURN => Key::Enter,
+        VK_TAB => Key::Tab,
+        VK_BACK => Key::Backspace,
+        VK_SPACE => Key::Space,
+        VK_INSERT => Key::Insert,
+        VK_DELETE => Key::Delete,
+        VK_HOME => Key::Home,
+        VK_END => Key::End,
+        VK_PRIOR => Key::PageUp,
+        VK_NEXT => Key::PageDown,
+        VK_LEFT => Key::Left,
+        VK_RIGHT => Key::Right,
+        VK_UP => Key::Up,
+        VK_DOWN => Key::Down,
+        VK_SHIFT => Key::Shift,
+        VK_CONTROL => Key::Control,
+        VK_MENU => Key::Alt,
+        VK_OEM_MINUS => Key::Minus,
+        VK_OEM_PLUS => Key::Equal,
+        VK_OEM_4 => Key::LeftBracket,
+        VK_OEM_6 => Key::RightBracket,
+        VK_OEM_5 => Key::Backslash,
+        VK_OEM_1 => Key::Semicolon,
+        VK_OEM_7 => Key::Quote,
+        VK_OEM_3 => Key::Grave,
+        VK_OEM_COMMA => Key::Comma,
+        VK_OEM_PERIOD => Key::Period,
+        VK_OEM_2 => Key::Slash,
+        code => Key::Other(code as u32),
+    }
+}
@@ -0,0 +1,466 @@
+//! Window on X11 through Xlib.
+//!
+//! Frames go through a shared memory image when the X server runs on the same
+//! machine and supports MIT-SHM, and are sent with `XPutImage` otherwise.
+//! Text input goes through the X input method when one can be opened,
+//! falling back to Latin-1 characters.
+
+mod ffi;
+
+use std::{
+    ffi::CString,
+    os::raw::{c_char, c_int, c_uint, c_void},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use crate::{
+    canvas::Canvas,
+    input::{Event, MouseButton},
+    Error,
+};
+use super::{Backend, keysym};
+
+/// Set by `on_shm_error` while attaching a shared memory segment.
+static SHM_ERROR: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" fn on_shm_error(_display: *mut ffi::Display, _event: *mut c_void) -> c_int {
+    SHM_ERROR.store(true, Ordering::Relaxed);
+    0
+}
+
+/// Image in a shared memory segment the X server reads from.
+struct ShmImage {
+    image: *mut ffi::XImage,
+    /// Boxed, the image keeps a pointer to it.
+    info: Box<ffi::XShmSegmentInfo>,
+    size: (usize, usize),
+}
+
+pub struct Window {
+    display: *mut ffi::Display,
+    window: ffi::Window,
+    gc: ffi::Gc,
+    visual: *mut ffi::Visual,
+    depth: c_int,
+    delete_window: ffi::Atom,
+    /// Input method and context, null if the input method could not be opened.
+    im: ffi::Xim,
+    ic: ffi::Xic,
+    use_shm: bool,
+    shm: Option<ShmImage>,
+    /// Key codes held, to tell auto-repeats from presses.
+    held: Vec<c_uint>,
+    size: (usize, usize),
+}
+
+impl Window {
+    /// Opens a window titled `title` with a drawable area of `width` by `height` pixels
+    /// on the display named by `DISPLAY`.
+    pub fn new(title: &str, width: usize, height: usize) -> Result<Self, Error> {
+        unsafe {
+            let display = ffi::XOpenDisplay(std::ptr::null());
+            if display.is_null() {
+                let message = match std::env::var("DISPLAY") {
+                    Ok(name) => format!("cannot connect to X display {:?}", name),
+                    Err(_) => "DISPLAY is not set".to_owned(),
+                };
+                return Err(Error::Platform { call: "XOpenDisplay", message });
+            }
+
+            let screen = ffi::XDefaultScreen(display);
+            let visual = ffi::XDefaultVisual(display, screen);
+            let depth = ffi::XDefaultDepth(display, screen);
+            // canvas pixels are 0xAARRGGBB in LSB first order, sent as they are
+            let supported = (*visual).class == ffi::TrueColor
+                && (depth == 24 || depth == 32)
+                && (*visual).red_mask == 0xff_0000
+                && (*visual).green_mask == 0xff00
+                && (*visual).blue_mask == 0xff
+                && bits_per_pixel(display, visual, depth) == 32;
+            if !supported {
+                ffi::XCloseDisplay(display);
+                let message = format!("default visual of depth {} is not 24-bit true color with 32 bits per pixel", depth);
+                return Err(Error::Platform { call: "XDefaultVisual", message });
+            }
+
+            let black = ffi::XBlackPixel(display, screen);
+            let window = ffi::XCreateSimpleWindow(
+                display,
+                ffi::XRootWindow(display, screen),
+                0,
+                0,
+                width.max(1) as c_uint,
+                height.max(1) as c_uint,
+                0,
+                black,
+                black,
+            );
+            // interior nul bytes would cut the title short anyway
+            let title = CString::new(title.replace('\0', "")).unwrap();
+            ffi::XStoreName(display, window, title.as_ptr());
+            ffi::XSelectInput(
+                display,
+                window,
+                ffi::KeyPressMask | ffi::KeyReleaseMask
+                    | ffi::ButtonPressMask | ffi::ButtonReleaseMask | ffi::PointerMotionMask
+                    | ffi::ExposureMask | ffi::StructureNotifyMask | ffi::FocusChangeMask,
+            );
+
+            // the window manager asks instead of killing the connection
+            let mut delete_window = ffi::XInternAtom(display, b"WM_DELETE_WINDOW\0".as_ptr() as *const c_char, ffi::False);
+            ffi::XSetWMProtocols(display, window, &mut delete_window, 1);
+
+            let gc = ffi::XCreateGC(display, window, 0, std::ptr::null_mut());
+            // held keys repeat presses without releases in between
+            ffi::XkbSetDetectableAutoRepeat(display, ffi::True, std::ptr::null_mut());
+
+            ffi::XSetLocaleModifiers(b"\0".as_ptr() as *const c_char);
+            let im = ffi::XOpenIM(display, std::ptr::null_mut(), std::ptr::null_mut(), std::ptr::null_mut());
+            let ic = if im.is_null() {
+                std::ptr::null_mut()
+            } else {
+                ffi::XCreateIC(
+                    im,
+                    ffi::XNInputStyle.as_ptr() as *const c_char,
+                    ffi::XIMPreeditNothing | ffi::XIMStatusNothing,
+                    ffi::XNClientWindow.as_ptr() as *const c_char,
+                    window,
+                    std::ptr::null::<c_void>(),
+                )
+            };
+
+            ffi::XMapWindow(display, window);
+            ffi::XFlush(display);
+
+            Ok(Self {
+                display,
+                window,
+                gc,
+                visual,
+                depth,
+                delete_window,
+                im,
+                ic,
+                // the server reads shared memory in its own byte order
+                use_shm: ffi::XShmQueryExtension(display) != 0 && ffi::XImageByteOrder(display) == ffi::LSBFirst,
+                shm: None,
+                held: Vec::new(),
+                size: (width, height),
+            })
+        }
+    }
+
+    /// Whether frames are presented through shared memory.
+    /// Turns off by itself if the X server cannot attach the memory.
+    pub fn uses_shm(&self) -> bool {
+        self.use_shm
+    }
+
+    /// Presents with `XPutImage` from now on.
+    pub fn disable_shm(&mut self) {
+        self.use_shm = false;
+        self.destroy_shm_image();
+    }
+
+    fn create_shm_image(&self, (width, height): (usize, usize)) -> Option<ShmImage> {
+        unsafe {
+            let mut info = Box::new(ffi::XShmSegmentInfo {
+                shmseg: 0,
+                shmid: -1,
+                shmaddr: std::ptr::null_mut(),
+                readOnly: ffi::False,
+            });
+            let image = ffi::XShmCreateImage(
+                self.display,
+                self.visual,
+                self.depth as c_uint,
+                ffi::ZPixmap,
+                std::ptr::null_mut(),
+                &mut *info,
+                width as c_uint,
+                height as c_uint,
+            );
+            if image.is_null() {
+                return None;
+            }
+
+            let bytes = (*image).bytes_per_line as usize * height;
+            info.shmid = ffi::shmget(ffi::IPC_PRIVATE, bytes, ffi::IPC_CREAT | 0o600);
+            if info.shmid < 0 {
+                ffi::XFree(image as *mut c_void);
+                return None;
+            }
+            let address = ffi::shmat(info.shmid, std::ptr::null(), 0);
+            if address as isize == -1 {
+                ffi::shmctl(info.shmid, ffi::IPC_RMID, std::ptr::null_mut());
+                ffi::XFree(image as *mut c_void);
+                return None;
+            }
+            info.shmaddr = address as *mut c_char;
+            (*image).data = info.shmaddr;
+
+            // a server on another machine fails to attach, and reports it asynchronously
+            SHM_ERROR.store(false, Ordering::Relaxed);
+            let previous = ffi::XSetErrorHandler(Some(on_shm_error));
+            let attached = ffi::XShmAttach(self.display, &mut *info) != 0;
+            ffi::XSync(self.display, ffi::False);
+            ffi::XSetErrorHandler(previous);
+            // removed once the server detaches too
+            ffi::shmctl(info.shmid, ffi::IPC_RMID, std::ptr::null_mut());
+
+            if !attached || SHM_ERROR.load(Ordering::Relaxed) {
+                ffi::shmdt(address);
+                ffi::XFree(image as *mut c_void);
+                return None;
+            }
+            Some(ShmImage { image, info, size: (width, height) })
+        }
+    }
+
+    fn destroy_shm_image(&mut self) {
+        if let Some(mut shm) = self.shm.take() {
+            unsafe {
+                ffi::XShmDetach(self.display, &mut *shm.info);
+                ffi::XSync(self.display, ffi::False);
+                ffi::shmdt(shm.info.shmaddr as *const c_void);
+                // the memory is not Xlib's to free
+                (*shm.image).data = std::ptr::null_mut();
+                ffi::XFree(shm.image as *mut c_void);
+            }
+        }
+    }
+
+    fn present_shm(&mut self, canvas: &Canvas) -> bool {
+        let size = (canvas.width(), canvas.height());
+        if self.shm.as_ref().map(|shm| shm.size) != Some(size) {
+            self.destroy_shm_image();
+            self.shm = self.create_shm_image(size);
+        }
+        let shm = match &self.shm {
+            Some(shm) => shm,
+            None => return false,
+        };
+
+        unsafe {
+            let row_bytes = size.0 * std::mem::size_of::<crate::canvas::Color>();
+            let stride = (*shm.image).bytes_per_line as usize;
+            let pixels = canvas.pixels().as_ptr() as *const u8;
+            for y in 0..size.1 {
+                std::ptr::copy_nonoverlapping(pixels.add(y * row_bytes), (shm.info.shmaddr as *mut u8).add(y * stride), row_bytes);
+            }
+            ffi::XShmPutImage(
+                self.display,
+                self.window,
+                self.gc,
+                shm.image,
+                0,
+                0,
+                0,
+                0,
+                size.0 as c_uint,
+                size.1 as c_uint,
+                ffi::False,
+            );
+            // the next frame must not be written while the server still reads this one
+            ffi::XSync(self.display, ffi::False);
+        }
+        true
+    }
+
+    fn present_put_image(&mut self, canvas: &Canvas) -> Result<(), Error> {
+        unsafe {
+            let image = ffi::XCreateImage(
+                self.display,
+                self.visual,
+                self.depth as c_uint,
+                ffi::ZPixmap,
+                0,
+                canvas.data() as *mut c_char,
+                canvas.width() as c_uint,
+                canvas.height() as c_uint,
+                32,
+                (canvas.width() * std::mem::size_of::<crate::canvas::Color>()) as c_int,
+            );
+            if image.is_null() {
+                let message = format!("no image of {}x{} pixels", canvas.width(), canvas.height());
+                return Err(Error::Platform { call: "XCreateImage", message });
+            }
+            // Xlib converts to the server's byte order while sending
+            (*image).byte_order = ffi::LSBFirst;
+            ffi::XPutImage(
+                self.display,
+                self.window,
+                self.gc,
+                image,
+                0,
+                0,
+                0,
+                0,
+                canvas.width() as c_uint,
+                canvas.height() as c_uint,
+            );
+            // the pixels belong to the canvas
+            (*image).data = std::ptr::null_mut();
+            ffi::XFree(image as *mut c_void);
+            ffi::XFlush(self.display);
+        }
+        Ok(())
+    }
+
+    /// Characters typed with a key press.
+    fn text(&self, event: &mut ffi::XKeyEvent) -> String {
+        let mut buffer = [0u8; 64];
+        unsafe {
+            if self.ic.is_null() {
+                let length = ffi::XLookupString(
+                    event,
+                    buffer.as_mut_ptr() as *mut c_char,
+                    buffer.len() as c_int,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                );
+                // Latin-1 is the first 256 code points
+                return buffer[..length.max(0) as usize].iter().map(|&b| b as char).collect();
+            }
+
+            let mut status = 0;
+            let length = ffi::Xutf8LookupString(
+                self.ic,
+                event,
+                buffer.as_mut_ptr() as *mut c_char,
+                buffer.len() as c_int,
+                std::ptr::null_mut(),
+                &mut status,
+            );
+            if status == ffi::XLookupChars || status == ffi::XLookupBoth {
+                String::from_utf8_lossy(&buffer[..length.max(0) as usize]).into_owned()
+            } else {
+                String::new()
+            }
+        }
+    }
+}
+
+impl Backend for Window {
+    fn poll_events(&mut self, events: &mut Vec<Event>) {
+        unsafe {
+            while ffi::XPending(self.display) > 0 {
+                let mut event: ffi::XEvent = std::mem::zeroed();
+                ffi::XNextEvent(self.display, &mut event);
+                // the input method takes keys that are parts of composed characters
+                if ffi::XFilterEvent(&mut event, 0) != 0 {
+                    continue;
+                }
+
+                match event.type_ {
+                    ffi::KeyPress => {
+                        let keycode = event.key.keycode;
+                        let repeat = self.held.contains(&keycode);
+                        if !repeat {
+                            self.held.push(keycode);
+                        }
+                        let keysym = ffi::XLookupKeysym(&mut event.key, 0);
+                        events.push(Event::KeyDown { key: keysym::key(keysym as u32), repeat });
+                        let text = self.text(&mut event.key);
+                        events.extend(text.chars().filter(|c| !c.is_control()).map(Event::Text));
+                    },
+                    ffi::KeyRelease => {
+                        let keycode = event.key.keycode;
+                        self.held.retain(|&held| held != keycode);
+                        events.push(Event::KeyUp { key: keysym::key(ffi::XLookupKeysym(&mut event.key, 0) as u32) });
+                    },
+                    ffi::ButtonPress | ffi::ButtonRelease => {
+                        let pressed = event.type_ == ffi::ButtonPress;
+                        let button = match event.button.button {
+                            1 => MouseButton::Left,
+                            2 => MouseButton::Middle,
+                            3 => MouseButton::Right,
+                            8 => MouseButton::X1,
+                            9 => MouseButton::X2,
+                            // wheel notches come as presses of buttons 4 to 7
+                            wheel @ 4..=7 => {
+                                if pressed {
+                                    let (x, y) = [(0.0, 1.0), (0.0, -1.0), (-1.0, 0.0), (1.0, 0.0)][wheel as usize - 4];
+                                    events.push(Event::MouseWheel { x, y });
+                                }
+                                continue;
+                            },
+                            _ => continue,
+                        };
+                        events.push(if pressed { Event::MouseDown { button } } else { Event::MouseUp { button } });
+                    },
+                    ffi::MotionNotify => {
+                        events.push(Event::MouseMove { x: event.motion.x as isize, y: event.motion.y as isize });
+                    },
+                    ffi::FocusIn | ffi::FocusOut => {
+                        // grabs by the window manager, focus does not really change
+                        if event.focus.mode == ffi::NotifyGrab || event.focus.mode == ffi::NotifyUngrab {
+                            continue;
+                        }
+                        let focused = event.type_ == ffi::FocusIn;
+                        if !focused {
+                            self.held.clear();
+                        }
+                        events.push(Event::Focus(focused));
+                    },
+                    ffi::ConfigureNotify => {
+                        let size = (event.configure.width.max(0) as usize, event.configure.height.max(0) as usize);
+                        // also sent when the window only moves
+                        if size != self.size {
+                            self.size = size;
+                            events.push(Event::Resize { width: size.0, height: size.1 });
+                        }
+                    },
+                    ffi::ClientMessage if event.client.data[0] as ffi::Atom == self.delete_window => {
+                        events.push(Event::Close);
+                    },
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    fn present(&mut self, canvas: &Canvas) -> Result<(), Error> {
+        if canvas.width() == 0 || canvas.height() == 0 {
+            return Ok(());
+        }
+        if self.use_shm {
+            if self.present_shm(canvas) {
+                return Ok(());
+            }
+            self.use_shm = false;
+        }
+        self.present_put_image(canvas)
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        self.destroy_shm_image();
+        unsafe {
+            if !self.ic.is_null() {
+                ffi::XDestroyIC(self.ic);
+            }
+            if !self.im.is_null() {
+                ffi::XCloseIM(self.im);
+            }
+            ffi::XFreeGC(self.display, self.gc);
+            ffi::XDestroyWindow(self.display, self.window);
+            ffi::XCloseDisplay(self.display);
+        }
+    }
+}
+
+/// Bits per pixel of images of `depth`, by creating a 1x1 one.
+unsafe fn bits_per_pixel(display: *mut ffi::Display, visual: *mut ffi::Visual, depth: c_int) -> c_int {
+    let image = ffi::XCreateImage(display, visual, depth as c_uint, ffi::ZPixmap, 0, std::ptr::null_mut(), 1, 1, 32, 0);
+    if image.is_null() {
+        return 0;
+    }
+    let bits = (*image).bits_per_pixel;
+    ffi::XFree(image as *mut c_void);
+    bits
+}
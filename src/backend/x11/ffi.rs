@@ -0,0 +1,346 @@
+//! The parts of Xlib, its shared memory extension and System V shared memory the backend uses.
+
+#![allow(non_snake_case, non_upper_case_globals)]
+
+use std::os::raw::{c_char, c_int, c_long, c_uint, c_ulong, c_void};
+
+pub enum Display {}
+
+pub type Xid = c_ulong;
+pub type Window = Xid;
+pub type Drawable = Xid;
+pub type Atom = c_ulong;
+pub type KeySym = c_ulong;
+pub type Time = c_ulong;
+pub type Bool = c_int;
+pub type Status = c_int;
+pub type Gc = *mut c_void;
+pub type Xim = *mut c_void;
+pub type Xic = *mut c_void;
+
+pub const False: Bool = 0;
+pub const True: Bool = 1;
+
+pub const KeyPressMask: c_long = 1 << 0;
+pub const KeyReleaseMask: c_long = 1 << 1;
+pub const ButtonPressMask: c_long = 1 << 2;
+pub const ButtonReleaseMask: c_long = 1 << 3;
+pub const PointerMotionMask: c_long = 1 << 6;
+pub const ExposureMask: c_long = 1 << 15;
+pub const StructureNotifyMask: c_long = 1 << 17;
+pub const FocusChangeMask: c_long = 1 << 21;
+
+pub const KeyPress: c_int = 2;
+pub const KeyRelease: c_int = 3;
+pub const ButtonPress: c_int = 4;
+pub const ButtonRelease: c_int = 5;
+pub const MotionNotify: c_int = 6;
+pub const FocusIn: c_int = 9;
+pub const FocusOut: c_int = 10;
+pub const ConfigureNotify: c_int = 22;
+pub const ClientMessage: c_int = 33;
+
+pub const NotifyGrab: c_int = 1;
+pub const NotifyUngrab: c_int = 2;
+
+pub const ZPixmap: c_int = 2;
+pub const LSBFirst: c_int = 0;
+pub const TrueColor: c_int = 4;
+
+pub const XIMPreeditNothing: c_ulong = 0x0008;
+pub const XIMStatusNothing: c_ulong = 0x0400;
+pub const XNInputStyle: &[u8] = b"inputStyle\0";
+pub const XNClientWindow: &[u8] = b"clientWindow\0";
+pub const XLookupChars: Status = 2;
+pub const XLookupBoth: Status = 4;
+
+pub const IPC_PRIVATE: c_int = 0;
+pub const IPC_CREAT: c_int = 0o1000;
+pub const IPC_RMID: c_int = 0;
+
+#[repr(C)]
+pub struct Visual {
+    pub ext_data: *mut c_void,
+    pub visualid: Xid,
+    pub class: c_int,
+    pub red_mask: c_ulong,
+    pub green_mask: c_ulong,
+    pub blue_mask: c_ulong,
+    pub bits_per_rgb: c_int,
+    pub map_entries: c_int,
+}
+
+#[repr(C)]
+pub struct XImage {
+    pub width: c_int,
+    pub height: c_int,
+    pub xoffset: c_int,
+    pub format: c_int,
+    pub data: *mut c_char,
+    pub byte_order: c_int,
+    pub bitmap_unit: c_int,
+    pub bitmap_bit_order: c_int,
+    pub bitmap_pad: c_int,
+    pub depth: c_int,
+    pub bytes_per_line: c_int,
+    pub bits_per_pixel: c_int,
+    pub red_mask: c_ulong,
+    pub green_mask: c_ulong,
+    pub blue_mask: c_ulong,
+    pub obdata: *mut c_char,
+    /// create_image, destroy_image, get_pixel, put_pixel, sub_image, add_pixel
+    pub f: [Option<unsafe extern "C" fn()>; 6],
+}
+
+#[repr(C)]
+pub struct XShmSegmentInfo {
+    pub shmseg: Xid,
+    pub shmid: c_int,
+    pub shmaddr: *mut c_char,
+    pub readOnly: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XKeyEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: Time,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub keycode: c_uint,
+    pub same_screen: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XButtonEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: Time,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub button: c_uint,
+    pub same_screen: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XMotionEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: Time,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub is_hint: c_char,
+    pub same_screen: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XFocusChangeEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub mode: c_int,
+    pub detail: c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XConfigureEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub event: Window,
+    pub window: Window,
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_int,
+    pub height: c_int,
+    pub border_width: c_int,
+    pub above: Window,
+    pub override_redirect: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XClientMessageEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub message_type: Atom,
+    pub format: c_int,
+    /// As longs, the other views of the data are not needed.
+    pub data: [c_long; 5],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union XEvent {
+    pub type_: c_int,
+    pub key: XKeyEvent,
+    pub button: XButtonEvent,
+    pub motion: XMotionEvent,
+    pub focus: XFocusChangeEvent,
+    pub configure: XConfigureEvent,
+    pub client: XClientMessageEvent,
+    pub pad: [c_long; 24],
+}
+
+pub type XErrorHandler = Option<unsafe extern "C" fn(*mut Display, *mut c_void) -> c_int>;
+
+#[link(name = "X11")]
+extern "C" {
+    pub fn XOpenDisplay(name: *const c_char) -> *mut Display;
+    pub fn XCloseDisplay(display: *mut Display) -> c_int;
+    pub fn XDefaultScreen(display: *mut Display) -> c_int;
+    pub fn XRootWindow(display: *mut Display, screen: c_int) -> Window;
+    pub fn XDefaultVisual(display: *mut Display, screen: c_int) -> *mut Visual;
+    pub fn XDefaultDepth(display: *mut Display, screen: c_int) -> c_int;
+    pub fn XBlackPixel(display: *mut Display, screen: c_int) -> c_ulong;
+    pub fn XImageByteOrder(display: *mut Display) -> c_int;
+
+    pub fn XCreateSimpleWindow(
+        display: *mut Display,
+        parent: Window,
+        x: c_int,
+        y: c_int,
+        width: c_uint,
+        height: c_uint,
+        border_width: c_uint,
+        border: c_ulong,
+        background: c_ulong,
+    ) -> Window;
+    pub fn XDestroyWindow(display: *mut Display, window: Window) -> c_int;
+    pub fn XStoreName(display: *mut Display, window: Window, name: *const c_char) -> c_int;
+    pub fn XSelectInput(display: *mut Display, window: Window, mask: c_long) -> c_int;
+    pub fn XMapWindow(display: *mut Display, window: Window) -> c_int;
+    pub fn XInternAtom(display: *mut Display, name: *const c_char, only_if_exists: Bool) -> Atom;
+    pub fn XSetWMProtocols(display: *mut Display, window: Window, protocols: *mut Atom, count: c_int) -> Status;
+
+    pub fn XCreateGC(display: *mut Display, drawable: Drawable, mask: c_ulong, values: *mut c_void) -> Gc;
+    pub fn XFreeGC(display: *mut Display, gc: Gc) -> c_int;
+    pub fn XCreateImage(
+        display: *mut Display,
+        visual: *mut Visual,
+        depth: c_uint,
+        format: c_int,
+        offset: c_int,
+        data: *mut c_char,
+        width: c_uint,
+        height: c_uint,
+        bitmap_pad: c_int,
+        bytes_per_line: c_int,
+    ) -> *mut XImage;
+    pub fn XPutImage(
+        display: *mut Display,
+        drawable: Drawable,
+        gc: Gc,
+        image: *mut XImage,
+        src_x: c_int,
+        src_y: c_int,
+        dest_x: c_int,
+        dest_y: c_int,
+        width: c_uint,
+        height: c_uint,
+    ) -> c_int;
+    pub fn XFree(data: *mut c_void) -> c_int;
+
+    pub fn XPending(display: *mut Display) -> c_int;
+    pub fn XNextEvent(display: *mut Display, event: *mut XEvent) -> c_int;
+    pub fn XFilterEvent(event: *mut XEvent, window: Window) -> Bool;
+    pub fn XLookupKeysym(event: *mut XKeyEvent, index: c_int) -> KeySym;
+    pub fn XLookupString(
+        event: *mut XKeyEvent,
+        buffer: *mut c_char,
+        bytes: c_int,
+        keysym: *mut KeySym,
+        status: *mut c_void,
+    ) -> c_int;
+    pub fn XkbSetDetectableAutoRepeat(display: *mut Display, detectable: Bool, supported: *mut Bool) -> Bool;
+
+    pub fn XSetLocaleModifiers(modifiers: *const c_char) -> *mut c_char;
+    pub fn XOpenIM(display: *mut Display, db: *mut c_void, name: *mut c_char, class: *mut c_char) -> Xim;
+    pub fn XCloseIM(im: Xim) -> Status;
+    pub fn XCreateIC(im: Xim, ...) -> Xic;
+    pub fn XDestroyIC(ic: Xic);
+    pub fn Xutf8LookupString(
+        ic: Xic,
+        event: *mut XKeyEvent,
+        buffer: *mut c_char,
+        bytes: c_int,
+        keysym: *mut KeySym,
+        status: *mut Status,
+    ) -> c_int;
+
+    pub fn XSync(display: *mut Display, discard: Bool) -> c_int;
+    pub fn XFlush(display: *mut Display) -> c_int;
+    pub fn XSetErrorHandler(handler: XErrorHandler) -> XErrorHandler;
+}
+
+#[link(name = "Xext")]
+extern "C" {
+    pub fn XShmQueryExtension(display: *mut Display) -> Bool;
+    pub fn XShmCreateImage(
+        display: *mut Display,
+        visual: *mut Visual,
+        depth: c_uint,
+        format: c_int,
+        data: *mut c_char,
+        info: *mut XShmSegmentInfo,
+        width: c_uint,
+        height: c_uint,
+    ) -> *mut XImage;
+    pub fn XShmAttach(display: *mut Display, info: *mut XShmSegmentInfo) -> Bool;
+    pub fn XShmDetach(display: *mut Display, info: *mut XShmSegmentInfo) -> Bool;
+    pub fn XShmPutImage(
+        display: *mut Display,
+        drawable: Drawable,
+        gc: Gc,
+        image: *mut XImage,
+        src_x: c_int,
+        src_y: c_int,
+        dest_x: c_int,
+        dest_y: c_int,
+        width: c_uint,
+        height: c_uint,
+        send_event: Bool,
+    ) -> Bool;
+}
+
+extern "C" {
+    pub fn shmget(key: c_int, size: usize, flags: c_int) -> c_int;
+    pub fn shmat(id: c_int, address: *const c_void, flags: c_int) -> *mut c_void;
+    pub fn shmdt(address: *const c_void) -> c_int;
+    pub fn shmctl(id: c_int, command: c_int, buffer: *mut c_void) -> c_int;
+}
@@ -0,0 +1,286 @@
+//! Where the ray tracer looks from, and controllers moving it with the keyboard and mouse.
+//!
+//! The scene's `x` points right, `y` up and `z` forward. A `Camera` at rest looks
+//! along `z`; `yaw` turns it right, `pitch` up and `roll` around the view direction.
+//! `Camera::view` fixes it for a canvas size, and gives the primary ray of any point
+//! on the canvas:
+//!
+//! ```
+//! # use gfx::{camera::Camera, math::V3};
+//! let camera = Camera::look_at([0, 2, -5].into(), [0, 0, 0].into());
+//! let view = camera.view((1280, 720));
+//! // through the center of the top left pixel
+//! let ray = view.ray((0.5, 0.5));
+//! ```
+
+use std::{f64::consts::FRAC_PI_2, time::Duration};
+use crate::{
+    input::{InputState, Key, MouseButton},
+    math::{Num, V3, cross, dot, len, normalize},
+};
+
+/// How far from straight up or down the pitch is kept, so the view never flips over.
+const PITCH_MARGIN: Num = 0.001;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// Rays from the camera's position. `fov_y` is the angle between the top and
+    /// bottom edges of the view in radians, the horizontal one follows from the aspect.
+    Perspective { fov_y: Num },
+    /// Parallel rays along the view direction from a `height` units tall rectangle
+    /// around the camera's position.
+    Orthographic { height: Num },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub position: V3,
+    /// Radians, positive turns right.
+    pub yaw: Num,
+    /// Radians, positive looks up. Kept within straight up and down by the controllers.
+    pub pitch: Num,
+    /// Radians, positive tilts the camera's up to the left.
+    pub roll: Num,
+    pub projection: Projection,
+    /// Distances along the view direction that geometry is seen between.
+    pub near: Num,
+    pub far: Num,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Camera {
+    /// At the origin looking along `z`, with a vertical field of view of 60 degrees.
+    pub fn new() -> Self {
+        Self {
+            position: [0, 0, 0].into(),
+            yaw: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            projection: Projection::Perspective { fov_y: Num::to_radians(60.0) },
+            near: 0.01,
+            far: Num::INFINITY,
+        }
+    }
+
+    /// At `position` looking at `target`, upright.
+    pub fn look_at(position: V3, target: V3) -> Self {
+        let mut camera = Self { position, ..Self::new() };
+        camera.look_along(target - position);
+        camera
+    }
+
+    /// Turns the camera to look along `direction`, upright.
+    pub fn look_along(&mut self, direction: V3) {
+        let horizontal = (direction.x * direction.x + direction.z * direction.z).sqrt();
+        self.yaw = direction.x.atan2(direction.z);
+        self.pitch = direction.y.atan2(horizontal);
+        self.roll = 0.0;
+    }
+
+    /// Unit vectors of the view direction, right and up.
+    pub fn basis(&self) -> (V3, V3, V3) {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let forward = V3::from([sin_yaw * cos_pitch, sin_pitch, cos_yaw * cos_pitch]);
+        let right = V3::from([cos_yaw, 0.0, -sin_yaw]);
+        let up = cross(forward, right);
+
+        let (sin_roll, cos_roll) = self.roll.sin_cos();
+        (forward, cos_roll * right + sin_roll * up, cos_roll * up - sin_roll * right)
+    }
+
+    pub fn forward(&self) -> V3 {
+        self.basis().0
+    }
+
+    /// Camera fixed for a canvas of `(width, height)` pixels, its aspect ratio included.
+    pub fn view(&self, (width, height): (usize, usize)) -> View {
+        let (forward, right, up) = self.basis();
+        let aspect = width.max(1) as Num / height.max(1) as Num;
+        let half_height = match self.projection {
+            Projection::Perspective { fov_y } => (fov_y / 2.0).tan(),
+            Projection::Orthographic { height } => height / 2.0,
+        };
+        let half_width = half_height * aspect;
+        View {
+            position: self.position,
+            forward,
+            right: half_width * right,
+            up: half_height * up,
+            size: (width.max(1) as Num, height.max(1) as Num),
+            orthographic: matches!(self.projection, Projection::Orthographic { .. }),
+            near: self.near,
+            far: self.far,
+        }
+    }
+}
+
+/// Ray from `origin` along `direction`, `origin + t * direction` for `t` in `t_min..t_max`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: V3,
+    /// Its component along the view direction is 1, so `t` is the distance along it.
+    pub direction: V3,
+    pub t_min: Num,
+    pub t_max: Num,
+}
+
+impl Ray {
+    pub fn at(&self, t: Num) -> V3 {
+        self.origin + t * self.direction
+    }
+}
+
+/// Camera fixed for a canvas size, see `Camera::view`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    position: V3,
+    forward: V3,
+    /// Half of the view's width and height at a distance of 1, or of its size for orthographic views.
+    right: V3,
+    up: V3,
+    size: (Num, Num),
+    orthographic: bool,
+    near: Num,
+    far: Num,
+}
+
+impl View {
+    /// Primary ray through `(x, y)` in pixels from the top left corner of the canvas,
+    /// the center of pixel `(0, 0)` is at `(0.5, 0.5)`.
+    pub fn ray(&self, (x, y): (Num, Num)) -> Ray {
+        let u = 2.0 * x / self.size.0 - 1.0;
+        let v = 1.0 - 2.0 * y / self.size.1;
+        let offset = u * self.right + v * self.up;
+        let (origin, direction) = if self.orthographic {
+            (self.position + offset, self.forward)
+        } else {
+            (self.position, self.forward + offset)
+        };
+        Ray { origin, direction, t_min: self.near, t_max: self.far }
+    }
+
+    /// Point of the canvas `point` projects to, in pixels as in `ray`, `None` behind the camera.
+    pub fn project(&self, point: V3) -> Option<(Num, Num)> {
+        let relative = point - self.position;
+        let depth = dot(relative, self.forward);
+        let scale = if self.orthographic {
+            1.0
+        } else if depth > 0.0 {
+            depth
+        } else {
+            return None;
+        };
+        let u = dot(relative, self.right) / (dot(self.right, self.right) * scale);
+        let v = dot(relative, self.up) / (dot(self.up, self.up) * scale);
+        Some(((u + 1.0) * self.size.0 / 2.0, (1.0 - v) * self.size.1 / 2.0))
+    }
+}
+
+/// Free flight: W, A, S, D move along the view, E and Q up and down, Shift speeds up,
+/// and moving the mouse with the right button held looks around.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlyController {
+    /// Units per second.
+    pub speed: Num,
+    /// Multiplier of `speed` while Shift is held.
+    pub boost: Num,
+    /// Radians per pixel of mouse movement.
+    pub sensitivity: Num,
+}
+
+impl Default for FlyController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlyController {
+    pub fn new() -> Self {
+        Self { speed: 2.0, boost: 4.0, sensitivity: 0.005 }
+    }
+
+    /// Moves `camera` by the input of a frame that lasted `dt`.
+    /// Returns whether it moved, so accumulated images can be reset.
+    pub fn update(&self, camera: &mut Camera, input: &InputState, dt: Duration) -> bool {
+        let mut moved = false;
+        if input.button_down(MouseButton::Right) && input.mouse_delta != (0, 0) {
+            camera.yaw += input.mouse_delta.0 as Num * self.sensitivity;
+            camera.pitch -= input.mouse_delta.1 as Num * self.sensitivity;
+            camera.pitch = camera.pitch.clamp(-FRAC_PI_2 + PITCH_MARGIN, FRAC_PI_2 - PITCH_MARGIN);
+            moved = true;
+        }
+
+        let (forward, right, _) = camera.basis();
+        let axis = |positive, negative| {
+            (input.is_down(positive) as i32 - input.is_down(negative) as i32) as Num
+        };
+        let direction = axis(Key::W, Key::S) * forward
+            + axis(Key::D, Key::A) * right
+            + axis(Key::E, Key::Q) * V3::from([0, 1, 0]);
+        if len(direction) > 0.0 {
+            let speed = if input.is_down(Key::Shift) { self.speed * self.boost } else { self.speed };
+            camera.position = camera.position + speed * dt.as_secs_f64() * normalize(direction);
+            moved = true;
+        }
+        moved
+    }
+}
+
+/// Circles around `target`: moving the mouse with the left button held or the arrow
+/// keys turn around it, the wheel and Page Up and Down move closer and further.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrbitController {
+    pub target: V3,
+    pub distance: Num,
+    /// Radians per pixel of mouse movement.
+    pub sensitivity: Num,
+    /// Radians per second with the arrow keys.
+    pub turn_speed: Num,
+    /// Fraction the distance shrinks by per wheel notch, and per second of Page Up.
+    pub zoom: Num,
+}
+
+impl OrbitController {
+    /// Orbit around `target` at the camera's current distance from it.
+    pub fn new(camera: &Camera, target: V3) -> Self {
+        Self {
+            target,
+            distance: len(camera.position - target),
+            sensitivity: 0.005,
+            turn_speed: 1.5,
+            zoom: 0.1,
+        }
+    }
+
+    /// Moves `camera` by the input of a frame that lasted `dt`, it keeps looking at `target`.
+    /// Returns whether it moved.
+    pub fn update(&mut self, camera: &mut Camera, input: &InputState, dt: Duration) -> bool {
+        let dt = dt.as_secs_f64();
+        let mut turn = (0.0, 0.0);
+        if input.button_down(MouseButton::Left) {
+            turn.0 += input.mouse_delta.0 as Num * self.sensitivity;
+            turn.1 -= input.mouse_delta.1 as Num * self.sensitivity;
+        }
+        let axis = |positive, negative| {
+            (input.is_down(positive) as i32 - input.is_down(negative) as i32) as Num
+        };
+        turn.0 += axis(Key::Right, Key::Left) * self.turn_speed * dt;
+        turn.1 += axis(Key::Up, Key::Down) * self.turn_speed * dt;
+
+        let zoom = input.wheel.1 as Num + axis(Key::PageUp, Key::PageDown) * dt;
+        let distance = self.distance * (1.0 - self.zoom).powf(zoom);
+
+        let before = (*camera, self.distance);
+        camera.yaw += turn.0;
+        camera.pitch = (camera.pitch + turn.1).clamp(-FRAC_PI_2 + PITCH_MARGIN, FRAC_PI_2 - PITCH_MARGIN);
+        self.distance = distance.max(camera.near);
+        camera.position = self.target - self.distance * camera.forward();
+        (*camera, self.distance) != before
+    }
+}
@@ -1,7 +1,7 @@
-use crate::math::Num;
+use crate::{error::Error, math::Num};
 
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Color {
     pub b: u8,
     pub g: u8,
@@ -38,6 +38,102 @@ pub fn set_intensity(c: Color, i: Num) -> Color {
     }
 }
 
+/// Draws `src` over `dst`, using alpha of `src` as opacity.
+pub fn blend(dst: Color, src: Color) -> Color {
+    match (src.a, dst.a) {
+        (255, _) => src,
+        (0, _) => dst,
+        // opaque destination is the common case and needs no division by alpha
+        (_, 255) => {
+            let sa = src.a as u32;
+            let channel = |s: u8, d: u8| ((s as u32 * sa + d as u32 * (255 - sa) + 127) / 255) as u8;
+
+            Color {
+                b: channel(src.b, dst.b),
+                g: channel(src.g, dst.g),
+                r: channel(src.r, dst.r),
+                a: 255,
+            }
+        },
+        _ => {
+            let sa = src.a as u32;
+            let da = dst.a as u32 * (255 - sa) / 255;
+            let a = sa + da;
+            let channel = |s: u8, d: u8| ((s as u32 * sa + d as u32 * da + a / 2) / a) as u8;
+
+            Color {
+                b: channel(src.b, dst.b),
+                g: channel(src.g, dst.g),
+                r: channel(src.r, dst.r),
+                a: a as u8,
+            }
+        },
+    }
+}
+
+/// Rectangle of pixels. `x` and `y` are of its top left corner, and may be
+/// negative, as things are allowed to be partially outside of a canvas.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub x: isize,
+    pub y: isize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new((x, y): (isize, isize), (width, height): (usize, usize)) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// Rectangle from `min` inclusive to `max` exclusive. Empty if `max` is not past `min`.
+    pub fn from_corners((x0, y0): (isize, isize), (x1, y1): (isize, isize)) -> Self {
+        Self {
+            x: x0,
+            y: y0,
+            width: if x1 > x0 { (x1 - x0) as usize } else { 0 },
+            height: if y1 > y0 { (y1 - y0) as usize } else { 0 },
+        }
+    }
+
+    pub fn right(&self) -> isize {
+        self.x + self.width as isize
+    }
+
+    pub fn bottom(&self) -> isize {
+        self.y + self.height as isize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn contains(&self, (x, y): (isize, isize)) -> bool {
+        (self.x..self.right()).contains(&x) && (self.y..self.bottom()).contains(&y)
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        Rect::from_corners(
+            (self.x.max(other.x), self.y.max(other.y)),
+            (self.right().min(other.right()), self.bottom().min(other.bottom())),
+        )
+    }
+
+    /// Smallest rectangle containing both. Empty rectangles are ignored.
+    pub fn union(&self, other: &Rect) -> Rect {
+        if self.is_empty() {
+            *other
+        } else if other.is_empty() {
+            *self
+        } else {
+            Rect::from_corners(
+                (self.x.min(other.x), self.y.min(other.y)),
+                (self.right().max(other.right()), self.bottom().max(other.bottom())),
+            )
+        }
+    }
+}
+
 pub struct Canvas {
     width: usize,
     height: usize,
@@ -48,6 +144,10 @@ impl Drop for Canvas {
     fn drop(&mut self) {
         use std::alloc::{Layout, dealloc};
 
+        // empty canvases own no allocation
+        if self.width * self.height == 0 {
+            return;
+        }
         unsafe {
             dealloc(
                 self.data as *mut _,
@@ -61,14 +161,23 @@ impl Drop for Canvas {
 }
 
 impl Canvas {
-    pub fn new(width: usize, height: usize) -> Result<Self, std::alloc::LayoutErr> {
+    /// Canvas of transparent black pixels.
+    pub fn new(width: usize, height: usize) -> Result<Self, Error> {
         use std::alloc::{Layout, alloc_zeroed};
 
-        Ok(Self {
-            width,
-            height,
-            data: unsafe { alloc_zeroed(Layout::array::<Color>(width * height)?) } as *mut _,
-        })
+        let layout = width.checked_mul(height)
+            .and_then(|pixels| Layout::array::<Color>(pixels).ok())
+            .ok_or(Error::Alloc { width, height })?;
+        // allocating zero bytes is undefined behavior
+        let data = if layout.size() == 0 {
+            std::ptr::NonNull::dangling().as_ptr()
+        } else {
+            unsafe { alloc_zeroed(layout) as *mut Color }
+        };
+        if data.is_null() {
+            return Err(Error::Alloc { width, height });
+        }
+        Ok(Self { width, height, data })
     }
 
     pub fn width(&self) -> usize {
@@ -79,10 +188,24 @@ impl Canvas {
         self.height
     }
 
+    /// # Safety
+    /// Pointer is valid for `width * height` pixels for as long as the canvas lives.
     pub unsafe fn data(&self) -> *mut Color {
         self.data
     }
 
+    /// All pixels, row by row, starting from the top-left corner.
+    pub fn pixels(&self) -> &[Color] {
+        unsafe { std::slice::from_raw_parts(self.data, self.width * self.height) }
+    }
+
+    pub fn get(&self, (x, y): (usize, usize)) -> Color {
+        debug_assert!(x < self.width, "Canvas::get. x: {} >= self.width: {}", x, self.width);
+        debug_assert!(y < self.height, "Canvas::get. y: {} >= self.height: {}", y, self.height);
+
+        unsafe { *self.data.add(x + self.width * y) }
+    }
+
     pub fn set(&mut self, (x, y): (usize, usize), pxl: Color) {
         debug_assert!(x < self.width, "Canvas::set. x: {} >= self.width: {}", x, self.width);
         debug_assert!(y < self.height, "Canvas::set. y: {} >= self.height: {}", y, self.height);
@@ -91,4 +214,15 @@ impl Canvas {
             *self.data.add(x + self.width * y) = pxl;
         }
     }
-}
\ No newline at end of file
+
+    /// Blends `pxl` over the pixel, see `blend`.
+    pub fn blend(&mut self, (x, y): (usize, usize), pxl: Color) {
+        let dst = self.get((x, y));
+        self.set((x, y), blend(dst, pxl));
+    }
+
+    /// Whole canvas as a rectangle.
+    pub fn rect(&self) -> Rect {
+        Rect::new((0, 0), (self.width, self.height))
+    }
+}
@@ -0,0 +1,118 @@
+//! Lines and rectangles, blended over the canvas and clipped to it.
+
+use crate::canvas::{Canvas, Color, Rect};
+
+/// One pixel wide line from `from` to `to`, both ends included.
+pub fn draw_line(canvas: &mut Canvas, from: (isize, isize), to: (isize, isize), color: Color) {
+    let (x0, y0, x1, y1) = match clip_line(from, to, canvas.rect()) {
+        Some(clipped) => clipped,
+        None => return,
+    };
+
+    // Bresenham
+    let dx = (x1 - x0).abs();
+    let dy = -(y1 - y0).abs();
+    let step_x = if x0 < x1 { 1 } else { -1 };
+    let step_y = if y0 < y1 { 1 } else { -1 };
+    let (mut x, mut y) = (x0, y0);
+    let mut error = dx + dy;
+    loop {
+        canvas.blend((x as usize, y as usize), color);
+        if x == x1 && y == y1 {
+            break;
+        }
+        let e2 = 2 * error;
+        if e2 >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if e2 <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+/// Horizontal line from `x0` to `x1`, both ends included.
+pub fn draw_hline(canvas: &mut Canvas, (x0, x1): (isize, isize), y: isize, color: Color) {
+    fill_rect(canvas, Rect::from_corners((x0.min(x1), y), (x0.max(x1) + 1, y + 1)), color);
+}
+
+/// Vertical line from `y0` to `y1`, both ends included.
+pub fn draw_vline(canvas: &mut Canvas, x: isize, (y0, y1): (isize, isize), color: Color) {
+    fill_rect(canvas, Rect::from_corners((x, y0.min(y1)), (x + 1, y0.max(y1) + 1)), color);
+}
+
+pub fn fill_rect(canvas: &mut Canvas, rect: Rect, color: Color) {
+    let clipped = rect.intersection(&canvas.rect());
+    for y in clipped.y..clipped.bottom() {
+        for x in clipped.x..clipped.right() {
+            canvas.blend((x as usize, y as usize), color);
+        }
+    }
+}
+
+/// One pixel wide border along the inside of `rect`.
+pub fn draw_rect(canvas: &mut Canvas, rect: Rect, color: Color) {
+    if rect.is_empty() {
+        return;
+    }
+
+    let (right, bottom) = (rect.right() - 1, rect.bottom() - 1);
+    draw_hline(canvas, (rect.x, right), rect.y, color);
+    if bottom > rect.y {
+        draw_hline(canvas, (rect.x, right), bottom, color);
+    }
+    if bottom - rect.y > 1 {
+        draw_vline(canvas, rect.x, (rect.y + 1, bottom - 1), color);
+        if right > rect.x {
+            draw_vline(canvas, right, (rect.y + 1, bottom - 1), color);
+        }
+    }
+}
+
+/// Part of the line inside `rect`, by Liang-Barsky.
+fn clip_line((x0, y0): (isize, isize), (x1, y1): (isize, isize), rect: Rect) -> Option<(isize, isize, isize, isize)> {
+    if rect.is_empty() {
+        return None;
+    }
+
+    let (fx0, fy0) = (x0 as f64, y0 as f64);
+    let (dx, dy) = ((x1 - x0) as f64, (y1 - y0) as f64);
+    let (min_x, min_y) = (rect.x as f64, rect.y as f64);
+    let (max_x, max_y) = ((rect.right() - 1) as f64, (rect.bottom() - 1) as f64);
+
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for &(p, q) in &[(-dx, fx0 - min_x), (dx, max_x - fx0), (-dy, fy0 - min_y), (dy, max_y - fy0)] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+
+    let point_at = |t: f64| -> (isize, isize) {
+        (
+            ((fx0 + t * dx).round() as isize).clamp(rect.x, rect.right() - 1),
+            ((fy0 + t * dy).round() as isize).clamp(rect.y, rect.bottom() - 1),
+        )
+    };
+    // ends inside keep their exact position, so lines sharing an end meet
+    let start = if t0 == 0.0 { (x0, y0) } else { point_at(t0) };
+    let end = if t1 == 1.0 { (x1, y1) } else { point_at(t1) };
+    // lines only touching a corner of `rect` can end up just outside with rounding errors
+    if !rect.contains(start) || !rect.contains(end) {
+        return None;
+    }
+    Some((start.0, start.1, end.0, end.1))
+}
@@ -0,0 +1,74 @@
+//! Errors of the crate as one type, for code that does not care which part failed.
+
+use std::{fmt, io};
+use crate::image::PngError;
+#[cfg(feature = "truetype")]
+use crate::text::FontError;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Not enough memory for a canvas of this size, or its size in bytes overflows.
+    Alloc { width: usize, height: usize },
+    /// Call to the operating system or the windowing system failed.
+    Platform {
+        call: &'static str,
+        /// What went wrong, in words, with the OS error code where there is one.
+        message: String,
+    },
+    Io(io::Error),
+    Decode(PngError),
+    #[cfg(feature = "truetype")]
+    Font(FontError),
+}
+
+impl Error {
+    /// Platform error with the message of the last OS error of the thread,
+    /// `GetLastError` on Windows and `errno` elsewhere.
+    pub fn last_os_error(call: &'static str) -> Self {
+        Error::Platform { call, message: io::Error::last_os_error().to_string() }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Alloc { width, height } => write!(f, "cannot allocate a {}x{} canvas", width, height),
+            Error::Platform { call, message } => write!(f, "{} failed: {}", call, message),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Decode(e) => write!(f, "{}", e),
+            #[cfg(feature = "truetype")]
+            Error::Font(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Decode(e) => Some(e),
+            #[cfg(feature = "truetype")]
+            Error::Font(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<PngError> for Error {
+    fn from(e: PngError) -> Self {
+        Error::Decode(e)
+    }
+}
+
+#[cfg(feature = "truetype")]
+impl From<FontError> for Error {
+    fn from(e: FontError) -> Self {
+        Error::Font(e)
+    }
+}
@@ -0,0 +1,184 @@
+//! Golden-image regression testing.
+//!
+//! A test renders into a `Canvas` and compares it against a reference PNG.
+//! When they differ too much, the test fails and leaves `<name>.actual.png`
+//! and `<name>.diff.png` next to the reference. Running tests with
+//! `GFX_BLESS=1` writes renders as new references instead of comparing.
+
+use std::{
+    fmt,
+    io,
+    path::PathBuf,
+};
+use crate::{
+    canvas::{Canvas, Color},
+    image::PngError,
+};
+
+pub const BLESS_VAR: &str = "GFX_BLESS";
+
+/// Where reference images live and how much a render may differ from them.
+pub struct Golden {
+    pub references: PathBuf,
+    /// Pixels differ if any of their channels differ by more than this.
+    pub channel_tolerance: u8,
+    /// Number of differing pixels tolerated before the comparison fails.
+    pub max_differing_pixels: usize,
+    /// Write renders as new references instead of comparing.
+    pub bless: bool,
+}
+
+#[derive(Debug)]
+pub enum GoldenError {
+    MissingReference(PathBuf),
+    SizeMismatch {
+        expected: (usize, usize),
+        actual: (usize, usize),
+    },
+    Mismatch {
+        differing_pixels: usize,
+        max_differing_pixels: usize,
+        diff: PathBuf,
+    },
+    Io(io::Error),
+    Png(PngError),
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GoldenError::MissingReference(path) => write!(
+                f,
+                "reference image {} does not exist. Run with {}=1 to create it",
+                path.display(),
+                BLESS_VAR,
+            ),
+            GoldenError::SizeMismatch { expected, actual } => write!(
+                f,
+                "image size {}x{} does not match reference size {}x{}",
+                actual.0, actual.1, expected.0, expected.1,
+            ),
+            GoldenError::Mismatch { differing_pixels, max_differing_pixels, diff } => write!(
+                f,
+                "{} pixels differ from reference, {} allowed. See {}",
+                differing_pixels,
+                max_differing_pixels,
+                diff.display(),
+            ),
+            GoldenError::Io(e) => write!(f, "{}", e),
+            GoldenError::Png(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GoldenError::Io(e) => Some(e),
+            GoldenError::Png(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GoldenError {
+    fn from(e: io::Error) -> Self {
+        GoldenError::Io(e)
+    }
+}
+
+impl From<PngError> for GoldenError {
+    fn from(e: PngError) -> Self {
+        GoldenError::Png(e)
+    }
+}
+
+impl Golden {
+    /// Exact comparison against references in `references` directory.
+    /// Blesses if `GFX_BLESS` is set to anything but `0`.
+    pub fn new(references: impl Into<PathBuf>) -> Self {
+        Self {
+            references: references.into(),
+            channel_tolerance: 0,
+            max_differing_pixels: 0,
+            bless: std::env::var_os(BLESS_VAR).is_some_and(|v| !v.is_empty() && v != "0"),
+        }
+    }
+
+    /// Compares `canvas` against reference image `name`, or replaces the reference if blessing.
+    pub fn check(&self, name: &str, canvas: &Canvas) -> Result<(), GoldenError> {
+        let reference_path = self.path(name, "png");
+
+        if self.bless {
+            std::fs::create_dir_all(&self.references)?;
+            canvas.save_png(&reference_path)?;
+            return Ok(());
+        }
+
+        if !reference_path.exists() {
+            return Err(GoldenError::MissingReference(reference_path));
+        }
+        let reference = Canvas::load_png(&reference_path)?;
+
+        let expected = (reference.width(), reference.height());
+        let actual = (canvas.width(), canvas.height());
+        if expected != actual {
+            canvas.save_png(self.path(name, "actual.png"))?;
+            return Err(GoldenError::SizeMismatch { expected, actual });
+        }
+
+        let mut diff = Canvas::new(canvas.width(), canvas.height()).expect("Canvas::new(width, height) failed");
+        let mut differing_pixels = 0;
+        for y in 0..canvas.height() {
+            for x in 0..canvas.width() {
+                let r = reference.get((x, y));
+                let c = canvas.get((x, y));
+                let differs = channels(r).iter()
+                    .zip(&channels(c))
+                    .any(|(&a, &b)| a.abs_diff(b) > self.channel_tolerance);
+
+                if differs {
+                    differing_pixels += 1;
+                    diff.set((x, y), Color { r: 255, g: 0, b: 255, a: 255 });
+                } else {
+                    // dimmed reference gives context to the highlighted pixels
+                    let v = ((r.r as u16 + r.g as u16 + r.b as u16) / 3 / 4) as u8;
+                    diff.set((x, y), Color { r: v, g: v, b: v, a: 255 });
+                }
+            }
+        }
+
+        let actual_path = self.path(name, "actual.png");
+        let diff_path = self.path(name, "diff.png");
+        if differing_pixels > self.max_differing_pixels {
+            canvas.save_png(&actual_path)?;
+            diff.save_png(&diff_path)?;
+            Err(GoldenError::Mismatch {
+                differing_pixels,
+                max_differing_pixels: self.max_differing_pixels,
+                diff: diff_path,
+            })
+        } else {
+            // leftovers from a previous failed run would only confuse
+            let _ = std::fs::remove_file(actual_path);
+            let _ = std::fs::remove_file(diff_path);
+            Ok(())
+        }
+    }
+
+    /// Same as `check`, but panics with a readable message on failure.
+    #[track_caller]
+    pub fn assert(&self, name: &str, canvas: &Canvas) {
+        if let Err(e) = self.check(name, canvas) {
+            panic!("golden image {:?}: {}", name, e);
+        }
+    }
+
+    fn path(&self, name: &str, extension: &str) -> PathBuf {
+        self.references.join(format!("{}.{}", name, extension))
+    }
+}
+
+fn channels(c: Color) -> [u8; 4] {
+    [c.r, c.g, c.b, c.a]
+}
@@ -0,0 +1,316 @@
+//! Frame time statistics and an on-canvas graph of them.
+//!
+//! ```no_run
+//! # use std::time::Instant;
+//! # use gfx::{canvas::{Canvas, Rect}, hud::{FrameStats, FrameTimeGraph}};
+//! # let mut canvas = Canvas::new(1280, 720).unwrap();
+//! let mut stats = FrameStats::new(500);
+//! let graph = FrameTimeGraph::new(Rect::new((0, 20), (300, 160)));
+//! let mut instant = Instant::now();
+//! loop {
+//!     stats.push(instant.elapsed());
+//!     instant = Instant::now();
+//!     // render the frame
+//!     graph.draw(&mut canvas, &stats);
+//! }
+//! ```
+
+use std::time::Duration;
+use crate::{
+    canvas::{Canvas, Color, Rect},
+    debug_text::{GLYPH_HEIGHT, GLYPH_WIDTH, draw_debug_text, measure_debug_text},
+    draw::{draw_hline, draw_line, draw_rect, fill_rect},
+};
+
+/// Frame times of the last `capacity` frames.
+///
+/// Statistics other than `last` sort a copy of the samples, which is
+/// cheap for the few hundred frames a HUD usually keeps.
+pub struct FrameStats {
+    samples: Vec<Duration>,
+    capacity: usize,
+    /// Index of the oldest sample once the buffer is full.
+    oldest: usize,
+}
+
+impl FrameStats {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "FrameStats::new. capacity must not be 0");
+        Self {
+            samples: Vec::with_capacity(capacity),
+            capacity,
+            oldest: 0,
+        }
+    }
+
+    /// Adds time of a frame, dropping the oldest one if full.
+    pub fn push(&mut self, frame_time: Duration) {
+        if self.samples.len() < self.capacity {
+            self.samples.push(frame_time);
+        } else {
+            self.samples[self.oldest] = frame_time;
+            self.oldest = (self.oldest + 1) % self.capacity;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.oldest = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Samples from the oldest to the newest.
+    pub fn samples(&self) -> impl Iterator<Item = Duration> + '_ {
+        let (newer, older) = self.samples.split_at(self.oldest);
+        older.iter().chain(newer).copied()
+    }
+
+    /// The newest sample.
+    pub fn last(&self) -> Option<Duration> {
+        self.samples().last()
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.samples.iter().min().copied()
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.samples.iter().max().copied()
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples.iter().sum::<Duration>() / self.samples.len() as u32)
+    }
+
+    /// Frame time `percent` of frames are at most as slow as, by nearest rank.
+    /// `percentile(50.0)` is the median, `percentile(99.0)` only 1% of frames are slower than.
+    pub fn percentile(&self, percent: f64) -> Option<Duration> {
+        let sorted = self.sorted();
+        if sorted.is_empty() {
+            return None;
+        }
+        let rank = (percent.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.clamp(1, sorted.len()) - 1])
+    }
+
+    /// Mean time of the slowest `fraction` of frames, at least one.
+    pub fn low(&self, fraction: f64) -> Option<Duration> {
+        let sorted = self.sorted();
+        if sorted.is_empty() {
+            return None;
+        }
+        let count = ((sorted.len() as f64 * fraction.clamp(0.0, 1.0)).ceil() as usize).max(1);
+        Some(sorted[sorted.len() - count..].iter().sum::<Duration>() / count as u32)
+    }
+
+    /// Mean time of the slowest 1% of frames.
+    pub fn one_percent_low(&self) -> Option<Duration> {
+        self.low(0.01)
+    }
+
+    /// Mean time of the slowest 0.1% of frames.
+    pub fn point_one_percent_low(&self) -> Option<Duration> {
+        self.low(0.001)
+    }
+
+    fn sorted(&self) -> Vec<Duration> {
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+        sorted
+    }
+}
+
+/// Horizontal reference line of a `FrameTimeGraph`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Target {
+    pub frame_time: Duration,
+    /// Color of the line, and of the graph where frames are slower than it.
+    pub color: Color,
+}
+
+impl Target {
+    /// Frame time of `fps` frames per second. Panics if `fps` is not positive.
+    pub fn fps(fps: f64, color: Color) -> Self {
+        assert!(fps > 0.0, "Target::fps. fps: {} is not positive", fps);
+        Self { frame_time: Duration::from_secs_f64(1.0 / fps), color }
+    }
+}
+
+/// Graph of frame times, newest at the right, with statistics above it.
+///
+/// The vertical axis starts at zero. Parts of the graph slower than
+/// a target are drawn in the color of the slowest target they exceed.
+pub struct FrameTimeGraph {
+    /// Whole widget, labels included.
+    pub rect: Rect,
+    pub targets: Vec<Target>,
+    /// Top of the vertical axis. Fits the samples and targets if `None`,
+    /// rounded up to 1, 2 or 5 times a power of ten, so it does not jump every frame.
+    pub max: Option<Duration>,
+    /// Statistics line and axis labels.
+    pub labels: bool,
+    pub background: Color,
+    pub border: Color,
+    pub line: Color,
+    pub text: Color,
+}
+
+impl FrameTimeGraph {
+    /// 60 fps target in yellow and 30 fps in red, over a translucent black background.
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            targets: vec![
+                Target::fps(60.0, Color { r: 255, g: 200, b: 0, a: 255 }),
+                Target::fps(30.0, Color { r: 255, g: 40, b: 40, a: 255 }),
+            ],
+            max: None,
+            labels: true,
+            background: Color { r: 0, g: 0, b: 0, a: 160 },
+            border: Color { r: 128, g: 128, b: 128, a: 255 },
+            line: Color { r: 80, g: 255, b: 80, a: 255 },
+            text: Color { r: 255, g: 255, b: 255, a: 255 },
+        }
+    }
+
+    /// Area the samples are plotted in.
+    pub fn plot_rect(&self) -> Rect {
+        if !self.labels {
+            return self.rect;
+        }
+        // statistics line above, widest axis label to the left
+        let left = measure_debug_text(format_args!("99.9")).0 as isize + 2;
+        let top = GLYPH_HEIGHT as isize + 2;
+        Rect::from_corners((self.rect.x + left, self.rect.y + top), (self.rect.right(), self.rect.bottom()))
+    }
+
+    /// Top of the vertical axis for these samples.
+    pub fn axis_max(&self, stats: &FrameStats) -> Duration {
+        if let Some(max) = self.max {
+            return max.max(Duration::from_nanos(1));
+        }
+
+        let slowest = self.targets
+            .iter()
+            .map(|target| target.frame_time)
+            .chain(stats.max())
+            .max()
+            .unwrap_or_default();
+        nice_ceil(slowest.as_secs_f64() * 1.1)
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas, stats: &FrameStats) {
+        fill_rect(canvas, self.rect, self.background);
+
+        let plot = self.plot_rect();
+        if plot.width < 2 || plot.height < 2 {
+            return;
+        }
+        draw_rect(canvas, plot, self.border);
+
+        let max = self.axis_max(stats).as_secs_f64();
+        let (bottom, height) = (plot.bottom() - 1, (plot.height - 1) as f64);
+        let y_of = |t: Duration| bottom - ((t.as_secs_f64() / max).min(1.0) * height).round() as isize;
+
+        let mut targets = self.targets.clone();
+        targets.sort_by_key(|target| target.frame_time);
+        for target in &targets {
+            let y = y_of(target.frame_time);
+            draw_hline(canvas, (plot.x + 1, plot.right() - 2), y, Color { a: target.color.a / 2, ..target.color });
+        }
+
+        let step = (plot.width - 1) as f64 / (stats.capacity().max(2) - 1) as f64;
+        let x_of = |age: usize| plot.right() - 1 - (age as f64 * step).round() as isize;
+        let color_of = |t: Duration| {
+            targets
+                .iter()
+                .rev()
+                .find(|target| t > target.frame_time)
+                .map_or(self.line, |target| target.color)
+        };
+
+        let mut newer: Option<((isize, isize), Duration)> = None;
+        for (age, sample) in stats.samples().collect::<Vec<_>>().into_iter().rev().enumerate() {
+            let point = (x_of(age), y_of(sample));
+            match newer {
+                // segment takes the color of the slower end, so single spikes stand out
+                Some((newer_point, newer_sample)) => {
+                    draw_line(canvas, point, newer_point, color_of(sample.max(newer_sample)));
+                },
+                None => draw_line(canvas, point, point, color_of(sample)),
+            }
+            newer = Some((point, sample));
+        }
+
+        if !self.labels {
+            return;
+        }
+
+        let label_x = |text_width: usize| plot.x - 2 - text_width as isize;
+        let top_label = ms(Duration::from_secs_f64(max));
+        let (top_width, _) = measure_debug_text(format_args!("{}", top_label));
+        draw_debug_text(canvas, (label_x(top_width), plot.y), self.text, format_args!("{}", top_label));
+        draw_debug_text(canvas, (label_x(GLYPH_WIDTH), bottom - GLYPH_HEIGHT as isize + 1), self.text, format_args!("0"));
+        for target in &targets {
+            let y = y_of(target.frame_time);
+            // skip labels that would overlap the ones at the ends of the axis
+            if y - (GLYPH_HEIGHT as isize) / 2 > plot.y + GLYPH_HEIGHT as isize && y + (GLYPH_HEIGHT as isize) / 2 < bottom - GLYPH_HEIGHT as isize {
+                let label = ms(target.frame_time);
+                let (width, _) = measure_debug_text(format_args!("{}", label));
+                draw_debug_text(canvas, (label_x(width), y - GLYPH_HEIGHT as isize / 2), target.color, format_args!("{}", label));
+            }
+        }
+
+        if let (Some(last), Some(mean), Some(low), Some(lower)) =
+            (stats.last(), stats.mean(), stats.one_percent_low(), stats.point_one_percent_low())
+        {
+            // as many statistics as fit
+            let mut line = format!("{}ms", ms(last));
+            for part in &[format!(" avg {}", ms(mean)), format!(" 1% {}", ms(low)), format!(" .1% {}", ms(lower))] {
+                if (line.len() + part.len()) * GLYPH_WIDTH > self.rect.width {
+                    break;
+                }
+                line.push_str(part);
+            }
+            if line.len() * GLYPH_WIDTH <= self.rect.width {
+                draw_debug_text(canvas, (self.rect.x, self.rect.y), self.text, format_args!("{}", line));
+            }
+        }
+    }
+}
+
+/// Milliseconds with one decimal, or none from 100ms up to keep labels short.
+fn ms(t: Duration) -> String {
+    let ms = t.as_secs_f64() * 1000.0;
+    if ms < 100.0 {
+        format!("{:.1}", ms)
+    } else {
+        format!("{:.0}", ms)
+    }
+}
+
+/// Smallest of 1, 2 and 5 times a power of ten, in milliseconds, that is at least `seconds`.
+fn nice_ceil(seconds: f64) -> Duration {
+    let ms = (seconds * 1000.0).max(0.001);
+    let power = 10f64.powf(ms.log10().floor());
+    let nice = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * power)
+        .find(|&v| v >= ms * (1.0 - 1e-9))
+        .unwrap_or(10.0 * power);
+    Duration::from_secs_f64(nice / 1000.0)
+}
@@ -0,0 +1,27 @@
+//! Getting pixels out of a `Canvas` and into image files.
+//!
+//! Every format has a `Canvas::write_*` method that writes to any `io::Write`
+//! and a `Canvas::save_*` shortcut that writes to a file.
+
+mod bmp;
+mod png;
+mod ppm;
+mod tga;
+
+pub use png::PngError;
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+fn save(path: &Path, write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write(&mut file)?;
+    file.flush()
+}
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
@@ -0,0 +1,71 @@
+use std::{convert::TryFrom, io::{self, Write}, path::Path};
+use crate::canvas::Canvas;
+
+const FILE_HEADER_SIZE: u32 = 14;
+const V4_HEADER_SIZE: u32 = 108;
+
+const BI_BITFIELDS: u32 = 3;
+const LCS_SRGB: u32 = 0x7352_4742; // 'sRGB'
+
+impl Canvas {
+    /// Writes 32-bit top-down BMP with a BITMAPV4HEADER.
+    ///
+    /// Channel masks are set explicitly, so viewers that understand BMP alpha
+    /// get it, and the rest show the same picture as `StretchDIBits`.
+    /// `Color` is already laid out as BMP expects (BGRA), so rows are written as is.
+    pub fn write_bmp(&self, mut w: impl Write) -> io::Result<()> {
+        let too_big = || super::invalid_input(format!(
+            "Canvas::write_bmp. {}x{} canvas is too big for BMP",
+            self.width(),
+            self.height(),
+        ));
+
+        let width = i32::try_from(self.width()).map_err(|_| too_big())?;
+        let height = i32::try_from(self.height()).map_err(|_| too_big())?;
+        let image_size = u32::try_from(self.pixels().len() * 4).map_err(|_| too_big())?;
+        let data_offset = FILE_HEADER_SIZE + V4_HEADER_SIZE;
+        let file_size = data_offset.checked_add(image_size).ok_or_else(too_big)?;
+
+        // BITMAPFILEHEADER
+        w.write_all(b"BM")?;
+        w.write_all(&file_size.to_le_bytes())?;
+        w.write_all(&[0; 4])?; // reserved
+        w.write_all(&data_offset.to_le_bytes())?;
+
+        // BITMAPV4HEADER
+        w.write_all(&V4_HEADER_SIZE.to_le_bytes())?;
+        w.write_all(&width.to_le_bytes())?;
+        w.write_all(&(-height).to_le_bytes())?; // negative means that bitmap is top-down
+        w.write_all(&1u16.to_le_bytes())?; // planes
+        w.write_all(&32u16.to_le_bytes())?; // bits per pixel
+        w.write_all(&BI_BITFIELDS.to_le_bytes())?;
+        w.write_all(&image_size.to_le_bytes())?;
+        w.write_all(&2835i32.to_le_bytes())?; // 72 DPI horizontally
+        w.write_all(&2835i32.to_le_bytes())?; // and vertically
+        w.write_all(&0u32.to_le_bytes())?; // colors used
+        w.write_all(&0u32.to_le_bytes())?; // important colors
+        w.write_all(&0x00ff_0000u32.to_le_bytes())?; // red mask
+        w.write_all(&0x0000_ff00u32.to_le_bytes())?; // green mask
+        w.write_all(&0x0000_00ffu32.to_le_bytes())?; // blue mask
+        w.write_all(&0xff00_0000u32.to_le_bytes())?; // alpha mask
+        w.write_all(&LCS_SRGB.to_le_bytes())?;
+        w.write_all(&[0; 36])?; // endpoints, unused for sRGB
+        w.write_all(&[0; 12])?; // gamma, unused for sRGB
+
+        // 32-bit rows are always 4-byte aligned, so there is no padding
+        let mut row = Vec::with_capacity(self.width() * 4);
+        for pixels in self.pixels().chunks(self.width().max(1)) {
+            row.clear();
+            for c in pixels {
+                row.extend_from_slice(&[c.b, c.g, c.r, c.a]);
+            }
+            w.write_all(&row)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save_bmp(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        super::save(path.as_ref(), |w| self.write_bmp(w))
+    }
+}
@@ -0,0 +1,573 @@
+use std::{
+    convert::TryFrom,
+    fmt,
+    fs::File,
+    io::{self, BufReader, Read, Write},
+    path::Path,
+};
+use crate::canvas::{Canvas, Color};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+#[derive(Debug)]
+pub enum PngError {
+    Io(io::Error),
+    /// File is not a PNG or is damaged: bad signature, CRC mismatch,
+    /// malformed chunks, undecompressable or truncated image data.
+    Corrupt(String),
+    /// File is a valid PNG, but uses a feature that is not supported.
+    Unsupported(String),
+    /// Image dimensions are too big for a `Canvas`.
+    TooBig { width: u32, height: u32 },
+}
+
+impl fmt::Display for PngError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PngError::Io(e) => write!(f, "PNG I/O error: {}", e),
+            PngError::Corrupt(msg) => write!(f, "corrupt PNG: {}", msg),
+            PngError::Unsupported(msg) => write!(f, "unsupported PNG: {}", msg),
+            PngError::TooBig { width, height } => write!(f, "PNG is too big: {}x{}", width, height),
+        }
+    }
+}
+
+impl std::error::Error for PngError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PngError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PngError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            PngError::Corrupt("unexpected end of file".to_owned())
+        } else {
+            PngError::Io(e)
+        }
+    }
+}
+
+fn corrupt<T>(msg: impl Into<String>) -> Result<T, PngError> {
+    Err(PngError::Corrupt(msg.into()))
+}
+
+fn unsupported<T>(msg: impl Into<String>) -> Result<T, PngError> {
+    Err(PngError::Unsupported(msg.into()))
+}
+
+impl Canvas {
+    /// Writes 8-bit RGBA PNG.
+    pub fn write_png(&self, mut w: impl Write) -> io::Result<()> {
+        let too_big = || super::invalid_input(format!(
+            "Canvas::write_png. {}x{} canvas is too big for PNG",
+            self.width(),
+            self.height(),
+        ));
+
+        let width = u32::try_from(self.width()).map_err(|_| too_big())?;
+        let height = u32::try_from(self.height()).map_err(|_| too_big())?;
+
+        w.write_all(&SIGNATURE)?;
+
+        let mut ihdr = Vec::with_capacity(13);
+        ihdr.extend_from_slice(&width.to_be_bytes());
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[
+            8, // bit depth
+            ColorType::Rgba as u8,
+            0, // compression: deflate
+            0, // filter method: adaptive
+            0, // no interlace
+        ]);
+        write_chunk(&mut w, b"IHDR", &ihdr)?;
+
+        let stride = self.width() * 4;
+        let mut filtered = Vec::with_capacity((stride + 1) * self.height());
+        let mut prev = vec![0; stride];
+        let mut row = Vec::with_capacity(stride);
+        for pixels in self.pixels().chunks(self.width().max(1)) {
+            row.clear();
+            for c in pixels {
+                row.extend_from_slice(&[c.r, c.g, c.b, c.a]);
+            }
+            filter_row(&mut filtered, &row, &prev, 4);
+            std::mem::swap(&mut row, &mut prev);
+        }
+        write_chunk(&mut w, b"IDAT", &miniz_oxide::deflate::compress_to_vec_zlib(&filtered, 6))?;
+
+        write_chunk(&mut w, b"IEND", &[])
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        super::save(path.as_ref(), |w| self.write_png(w))
+    }
+
+    /// Reads PNG of any standard color type and bit depth, interlaced or not.
+    ///
+    /// 16-bit samples are truncated to 8 bits. Transparency from `tRNS`
+    /// is applied; gamma, color profiles and other ancillary chunks are ignored.
+    pub fn read_png(mut r: impl Read) -> Result<Canvas, PngError> {
+        let mut signature = [0; 8];
+        r.read_exact(&mut signature)?;
+        if signature != SIGNATURE {
+            return corrupt("invalid signature");
+        }
+
+        let (chunk_type, data) = read_chunk(&mut r)?;
+        if &chunk_type != b"IHDR" {
+            return corrupt("first chunk is not IHDR");
+        }
+        let header = Header::parse(&data)?;
+
+        let mut palette = Vec::new();
+        let mut transparency = None;
+        let mut idat = Vec::new();
+        loop {
+            let (chunk_type, data) = read_chunk(&mut r)?;
+            match &chunk_type {
+                b"IEND" => break,
+                b"IDAT" => idat.extend_from_slice(&data),
+                b"PLTE" => {
+                    if data.is_empty() || data.len() % 3 != 0 || data.len() / 3 > 256 {
+                        return corrupt(format!("PLTE chunk length is {}", data.len()));
+                    }
+                    palette = data
+                        .chunks(3)
+                        .map(|rgb| Color { r: rgb[0], g: rgb[1], b: rgb[2], a: 255 })
+                        .collect();
+                },
+                b"tRNS" => transparency = Some(data),
+                b"IHDR" => return corrupt("multiple IHDR chunks"),
+                // bit 5 of the first byte is set for ancillary chunks, which are safe to skip
+                _ if chunk_type[0] & 0x20 != 0 => {},
+                _ => return unsupported(format!(
+                    "unknown critical chunk {}",
+                    String::from_utf8_lossy(&chunk_type),
+                )),
+            }
+        }
+
+        if header.color_type == ColorType::Indexed {
+            if palette.is_empty() {
+                return corrupt("indexed image without PLTE chunk");
+            }
+            if let Some(alpha) = &transparency {
+                if alpha.len() > palette.len() {
+                    return corrupt("tRNS chunk is longer than palette");
+                }
+                for (c, &a) in palette.iter_mut().zip(alpha) {
+                    c.a = a;
+                }
+            }
+        }
+
+        let color_key = match (header.color_type, &transparency) {
+            (ColorType::Gray, Some(t)) if t.len() == 2 => Some([be_u16(t, 0), 0, 0]),
+            (ColorType::Rgb, Some(t)) if t.len() == 6 => Some([be_u16(t, 0), be_u16(t, 2), be_u16(t, 4)]),
+            (ColorType::Gray, Some(_)) | (ColorType::Rgb, Some(_)) => return corrupt("invalid tRNS chunk length"),
+            (ColorType::GrayAlpha, Some(_)) | (ColorType::Rgba, Some(_)) => return corrupt("tRNS chunk in image with alpha"),
+            _ => None,
+        };
+
+        let too_big = || PngError::TooBig { width: header.width, height: header.height };
+        let width = usize::try_from(header.width).map_err(|_| too_big())?;
+        let height = usize::try_from(header.height).map_err(|_| too_big())?;
+
+        let passes = header.passes();
+        let expected_len = passes
+            .iter()
+            .try_fold(0usize, |len, &(_, w, h)| {
+                if w == 0 || h == 0 {
+                    Some(len)
+                } else {
+                    header.stride(w)?.checked_add(1)?.checked_mul(h)?.checked_add(len)
+                }
+            })
+            .ok_or_else(too_big)?;
+
+        let data = miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(&idat, expected_len)
+            .map_err(|e| PngError::Corrupt(format!("image data does not decompress: {:?}", e.status)))?;
+        if data.len() != expected_len {
+            return corrupt(format!("image data is {} bytes, expected {}", data.len(), expected_len));
+        }
+        // allocated only once the data fills it, the header alone may ask for gigabytes
+        let mut canvas = Canvas::new(width, height).map_err(|_| too_big())?;
+
+        let bpp = header.bytes_per_pixel();
+        let mut data = &data[..];
+        let mut samples = Vec::new();
+        for &(pass, pass_width, pass_height) in &passes {
+            if pass_width == 0 || pass_height == 0 {
+                continue;
+            }
+
+            let stride = header.stride(pass_width).unwrap();
+            let mut prev = vec![0; stride];
+            let mut row = vec![0; stride];
+            for y in 0..pass_height {
+                let (filter, rest) = data.split_first().unwrap();
+                row.copy_from_slice(&rest[..stride]);
+                data = &rest[stride..];
+                unfilter_row(*filter, &mut row, &prev, bpp)?;
+
+                for x in 0..pass_width {
+                    header.samples(&row, x, &mut samples);
+                    let color = header.color(&samples, &palette, color_key)?;
+                    canvas.set(pass.position((x, y)), color);
+                }
+
+                std::mem::swap(&mut row, &mut prev);
+            }
+        }
+
+        Ok(canvas)
+    }
+
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Canvas, PngError> {
+        Canvas::read_png(BufReader::new(File::open(path)?))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ColorType {
+    Gray = 0,
+    Rgb = 2,
+    Indexed = 3,
+    GrayAlpha = 4,
+    Rgba = 6,
+}
+
+impl ColorType {
+    fn channels(self) -> usize {
+        match self {
+            ColorType::Gray | ColorType::Indexed => 1,
+            ColorType::GrayAlpha => 2,
+            ColorType::Rgb => 3,
+            ColorType::Rgba => 4,
+        }
+    }
+}
+
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: ColorType,
+    interlaced: bool,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> Result<Self, PngError> {
+        if data.len() != 13 {
+            return corrupt(format!("IHDR chunk length is {}", data.len()));
+        }
+
+        let width = be_u32(data, 0);
+        let height = be_u32(data, 4);
+        if width == 0 || height == 0 {
+            return corrupt(format!("image dimensions are {}x{}", width, height));
+        }
+
+        let bit_depth = data[8];
+        let color_type = match data[9] {
+            0 => ColorType::Gray,
+            2 => ColorType::Rgb,
+            3 => ColorType::Indexed,
+            4 => ColorType::GrayAlpha,
+            6 => ColorType::Rgba,
+            t => return corrupt(format!("unknown color type {}", t)),
+        };
+        let valid_bit_depth = match color_type {
+            ColorType::Gray => [1, 2, 4, 8, 16].contains(&bit_depth),
+            ColorType::Indexed => [1, 2, 4, 8].contains(&bit_depth),
+            _ => [8, 16].contains(&bit_depth),
+        };
+        if !valid_bit_depth {
+            return corrupt(format!("bit depth {} is invalid for color type {}", bit_depth, data[9]));
+        }
+
+        if data[10] != 0 {
+            return unsupported(format!("compression method {}", data[10]));
+        }
+        if data[11] != 0 {
+            return unsupported(format!("filter method {}", data[11]));
+        }
+        let interlaced = match data[12] {
+            0 => false,
+            1 => true,
+            m => return unsupported(format!("interlace method {}", m)),
+        };
+
+        Ok(Self { width, height, bit_depth, color_type, interlaced })
+    }
+
+    fn bits_per_pixel(&self) -> usize {
+        self.color_type.channels() * self.bit_depth as usize
+    }
+
+    /// Distance in bytes to the corresponding byte of the previous pixel,
+    /// as used by filters. Rounded up to 1 for bit depths below 8.
+    fn bytes_per_pixel(&self) -> usize {
+        (self.bits_per_pixel() / 8).max(1)
+    }
+
+    /// Length in bytes of a scanline `width` pixels wide, without the filter byte.
+    fn stride(&self, width: usize) -> Option<usize> {
+        Some(width.checked_mul(self.bits_per_pixel())?.checked_add(7)? / 8)
+    }
+
+    /// Returns passes together with their width and height in pixels.
+    fn passes(&self) -> Vec<(Pass, usize, usize)> {
+        let width = self.width as usize;
+        let height = self.height as usize;
+
+        let passes: &[Pass] = if self.interlaced { &ADAM7 } else { &[Pass { x0: 0, y0: 0, dx: 1, dy: 1 }] };
+        passes
+            .iter()
+            .map(|&p| {
+                let (w, h) = p.size((width, height));
+                (p, w, h)
+            })
+            .collect()
+    }
+
+    /// Reads raw samples of pixel `x` of an unfiltered scanline.
+    fn samples(&self, row: &[u8], x: usize, samples: &mut Vec<u16>) {
+        samples.clear();
+        let channels = self.color_type.channels();
+        match self.bit_depth {
+            16 => samples.extend((0..channels).map(|c| be_u16(row, (x * channels + c) * 2))),
+            8 => samples.extend(row[x * channels..][..channels].iter().map(|&s| s as u16)),
+            depth => {
+                // only single-channel images have sub-byte samples
+                let bit = x * depth as usize;
+                let shift = 8 - depth as usize - bit % 8;
+                let mask = (1 << depth) - 1;
+                samples.push((row[bit / 8] >> shift) as u16 & mask);
+            },
+        }
+    }
+
+    fn color(&self, samples: &[u16], palette: &[Color], color_key: Option<[u16; 3]>) -> Result<Color, PngError> {
+        let to_u8 = |s: u16| -> u8 {
+            match self.bit_depth {
+                16 => (s >> 8) as u8,
+                8 => s as u8,
+                depth => (s as u32 * 255 / ((1 << depth) - 1)) as u8,
+            }
+        };
+
+        Ok(match self.color_type {
+            ColorType::Indexed => match palette.get(samples[0] as usize) {
+                Some(&c) => c,
+                None => return corrupt(format!("palette index {} is out of range", samples[0])),
+            },
+            ColorType::Gray => {
+                let v = to_u8(samples[0]);
+                let a = if color_key == Some([samples[0], 0, 0]) { 0 } else { 255 };
+                Color { r: v, g: v, b: v, a }
+            },
+            ColorType::GrayAlpha => {
+                let v = to_u8(samples[0]);
+                Color { r: v, g: v, b: v, a: to_u8(samples[1]) }
+            },
+            ColorType::Rgb => {
+                let a = if color_key == Some([samples[0], samples[1], samples[2]]) { 0 } else { 255 };
+                Color { r: to_u8(samples[0]), g: to_u8(samples[1]), b: to_u8(samples[2]), a }
+            },
+            ColorType::Rgba => Color {
+                r: to_u8(samples[0]),
+                g: to_u8(samples[1]),
+                b: to_u8(samples[2]),
+                a: to_u8(samples[3]),
+            },
+        })
+    }
+}
+
+/// Interlacing pass: pixels of the pass start at `(x0, y0)` and are `(dx, dy)` apart.
+#[derive(Clone, Copy)]
+struct Pass {
+    x0: usize,
+    y0: usize,
+    dx: usize,
+    dy: usize,
+}
+
+impl Pass {
+    fn size(self, (width, height): (usize, usize)) -> (usize, usize) {
+        fn len(image_len: usize, start: usize, step: usize) -> usize {
+            if image_len > start {
+                (image_len - start).div_ceil(step)
+            } else {
+                0
+            }
+        }
+
+        (len(width, self.x0, self.dx), len(height, self.y0, self.dy))
+    }
+
+    fn position(self, (x, y): (usize, usize)) -> (usize, usize) {
+        (self.x0 + x * self.dx, self.y0 + y * self.dy)
+    }
+}
+
+const ADAM7: [Pass; 7] = [
+    Pass { x0: 0, y0: 0, dx: 8, dy: 8 },
+    Pass { x0: 4, y0: 0, dx: 8, dy: 8 },
+    Pass { x0: 0, y0: 4, dx: 4, dy: 8 },
+    Pass { x0: 2, y0: 0, dx: 4, dy: 4 },
+    Pass { x0: 0, y0: 2, dx: 2, dy: 4 },
+    Pass { x0: 1, y0: 0, dx: 2, dy: 2 },
+    Pass { x0: 0, y0: 1, dx: 1, dy: 2 },
+];
+
+const FILTER_NONE: u8 = 0;
+const FILTER_SUB: u8 = 1;
+const FILTER_UP: u8 = 2;
+const FILTER_AVERAGE: u8 = 3;
+const FILTER_PAETH: u8 = 4;
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn unfilter_row(filter: u8, row: &mut [u8], prev: &[u8], bpp: usize) -> Result<(), PngError> {
+    match filter {
+        FILTER_NONE => {},
+        FILTER_SUB => for i in bpp..row.len() {
+            row[i] = row[i].wrapping_add(row[i - bpp]);
+        },
+        FILTER_UP => for i in 0..row.len() {
+            row[i] = row[i].wrapping_add(prev[i]);
+        },
+        FILTER_AVERAGE => for i in 0..row.len() {
+            let left = if i >= bpp { row[i - bpp] } else { 0 };
+            row[i] = row[i].wrapping_add(((left as u16 + prev[i] as u16) / 2) as u8);
+        },
+        FILTER_PAETH => for i in 0..row.len() {
+            let (left, up_left) = if i >= bpp { (row[i - bpp], prev[i - bpp]) } else { (0, 0) };
+            row[i] = row[i].wrapping_add(paeth(left, prev[i], up_left));
+        },
+        f => return corrupt(format!("unknown filter type {}", f)),
+    }
+    Ok(())
+}
+
+/// Appends filter byte and filtered `row` to `out`, choosing the filter
+/// with the minimum sum of absolute differences, as recommended by the spec.
+fn filter_row(out: &mut Vec<u8>, row: &[u8], prev: &[u8], bpp: usize) {
+    let filtered = |filter: u8, i: usize| -> u8 {
+        let left = if i >= bpp { row[i - bpp] } else { 0 };
+        let up_left = if i >= bpp { prev[i - bpp] } else { 0 };
+        let up = prev[i];
+        match filter {
+            FILTER_SUB => row[i].wrapping_sub(left),
+            FILTER_UP => row[i].wrapping_sub(up),
+            FILTER_AVERAGE => row[i].wrapping_sub(((left as u16 + up as u16) / 2) as u8),
+            FILTER_PAETH => row[i].wrapping_sub(paeth(left, up, up_left)),
+            _ => row[i],
+        }
+    };
+
+    let best = (FILTER_NONE..=FILTER_PAETH)
+        .min_by_key(|&filter| {
+            (0..row.len())
+                .map(|i| (filtered(filter, i) as i8).unsigned_abs() as u64)
+                .sum::<u64>()
+        })
+        .unwrap();
+
+    out.push(best);
+    out.extend((0..row.len()).map(|i| filtered(best, i)));
+}
+
+fn be_u16(data: &[u8], i: usize) -> u16 {
+    u16::from_be_bytes([data[i], data[i + 1]])
+}
+
+fn be_u32(data: &[u8], i: usize) -> u32 {
+    u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]])
+}
+
+fn read_chunk(r: &mut impl Read) -> Result<([u8; 4], Vec<u8>), PngError> {
+    let mut header = [0; 8];
+    r.read_exact(&mut header)?;
+    let len = be_u32(&header, 0);
+    let chunk_type = [header[4], header[5], header[6], header[7]];
+
+    if len > i32::MAX as u32 {
+        return corrupt(format!("chunk length {} is too big", len));
+    }
+    if !chunk_type.iter().all(u8::is_ascii_alphabetic) {
+        return corrupt(format!("invalid chunk type {:?}", chunk_type));
+    }
+
+    let mut data = Vec::new();
+    r.take(len as u64).read_to_end(&mut data)?;
+    if data.len() != len as usize {
+        return corrupt("unexpected end of file");
+    }
+
+    let mut crc = [0; 4];
+    r.read_exact(&mut crc)?;
+    if u32::from_be_bytes(crc) != crc32(&[&chunk_type, &data]) {
+        return corrupt(format!("CRC mismatch in {} chunk", String::from_utf8_lossy(&chunk_type)));
+    }
+
+    Ok((chunk_type, data))
+}
+
+fn write_chunk(w: &mut impl Write, chunk_type: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(data.len())
+        .ok()
+        .filter(|&len| len <= i32::MAX as u32)
+        .ok_or_else(|| super::invalid_input(format!("PNG chunk of {} bytes is too big", data.len())))?;
+
+    w.write_all(&len.to_be_bytes())?;
+    w.write_all(chunk_type)?;
+    w.write_all(data)?;
+    w.write_all(&crc32(&[chunk_type, data]).to_be_bytes())
+}
+
+/// CRC-32 of concatenated `parts`, as used by PNG and zlib.
+fn crc32(parts: &[&[u8]]) -> u32 {
+    const fn table() -> [u32; 256] {
+        let mut table = [0; 256];
+        let mut n = 0;
+        while n < 256 {
+            let mut c = n as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+                k += 1;
+            }
+            table[n] = c;
+            n += 1;
+        }
+        table
+    }
+    const TABLE: [u32; 256] = table();
+
+    let mut crc = 0xffff_ffff;
+    for &part in parts {
+        for &byte in part {
+            crc = TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+        }
+    }
+    crc ^ 0xffff_ffff
+}
@@ -0,0 +1,27 @@
+use std::{io::{self, Write}, path::Path};
+use crate::canvas::Canvas;
+
+impl Canvas {
+    /// Writes binary (P6) PPM.
+    ///
+    /// PPM has no alpha channel, so alpha is dropped. This matches what
+    /// `StretchDIBits` shows on screen.
+    pub fn write_ppm(&self, mut w: impl Write) -> io::Result<()> {
+        write!(w, "P6\n{} {}\n255\n", self.width(), self.height())?;
+
+        let mut row = Vec::with_capacity(self.width() * 3);
+        for pixels in self.pixels().chunks(self.width().max(1)) {
+            row.clear();
+            for c in pixels {
+                row.extend_from_slice(&[c.r, c.g, c.b]);
+            }
+            w.write_all(&row)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save_ppm(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        super::save(path.as_ref(), |w| self.write_ppm(w))
+    }
+}
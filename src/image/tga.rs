@@ -0,0 +1,52 @@
+use std::{convert::TryFrom, io::{self, Write}, path::Path};
+use crate::canvas::Canvas;
+
+const UNCOMPRESSED_TRUE_COLOR: u8 = 2;
+const ALPHA_BITS: u8 = 8;
+const TOP_LEFT_ORIGIN: u8 = 0x20;
+
+impl Canvas {
+    /// Writes uncompressed 32-bit top-down TGA with an 8-bit alpha channel.
+    ///
+    /// TGA stores pixels as BGRA, same as `Color`.
+    pub fn write_tga(&self, mut w: impl Write) -> io::Result<()> {
+        let too_big = || super::invalid_input(format!(
+            "Canvas::write_tga. {}x{} canvas is too big for TGA, max is 65535x65535",
+            self.width(),
+            self.height(),
+        ));
+
+        let width = u16::try_from(self.width()).map_err(|_| too_big())?;
+        let height = u16::try_from(self.height()).map_err(|_| too_big())?;
+
+        w.write_all(&[
+            0, // no image id
+            0, // no color map
+            UNCOMPRESSED_TRUE_COLOR,
+        ])?;
+        w.write_all(&[0; 5])?; // color map specification
+        w.write_all(&0u16.to_le_bytes())?; // x origin
+        w.write_all(&0u16.to_le_bytes())?; // y origin
+        w.write_all(&width.to_le_bytes())?;
+        w.write_all(&height.to_le_bytes())?;
+        w.write_all(&[32, ALPHA_BITS | TOP_LEFT_ORIGIN])?;
+
+        let mut row = Vec::with_capacity(self.width() * 4);
+        for pixels in self.pixels().chunks(self.width().max(1)) {
+            row.clear();
+            for c in pixels {
+                row.extend_from_slice(&[c.b, c.g, c.r, c.a]);
+            }
+            w.write_all(&row)?;
+        }
+
+        // TGA 2.0 footer without extension and developer areas
+        w.write_all(&0u32.to_le_bytes())?;
+        w.write_all(&0u32.to_le_bytes())?;
+        w.write_all(b"TRUEVISION-XFILE.\0")
+    }
+
+    pub fn save_tga(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        super::save(path.as_ref(), |w| self.write_tga(w))
+    }
+}
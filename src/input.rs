@@ -0,0 +1,275 @@
+//! Keyboard, mouse and window events, and the input state they add up to.
+//!
+//! Backends translate whatever their platform reports into `Event`s, see
+//! `gfx::backend`. Applications either react to the events themselves or feed
+//! them to an `InputState` once per frame and ask it what is held and what changed:
+//!
+//! ```no_run
+//! # use gfx::{backend::{Backend, scripted::Scripted}, input::{InputState, Key}};
+//! # let mut backend = Scripted::new((1280, 720), Vec::new());
+//! let mut input = InputState::new();
+//! let mut events = Vec::new();
+//! loop {
+//!     events.clear();
+//!     backend.poll_events(&mut events);
+//!     input.update(&events);
+//!     if input.close_requested || input.pressed(Key::Escape) {
+//!         break;
+//!     }
+//!     // update and render the frame
+//! }
+//! ```
+
+/// Key on the keyboard, by position on a US layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    A, B, C, D, E, F, G, H, I, J, K, L, M,
+    N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Digit0, Digit1, Digit2, Digit3, Digit4,
+    Digit5, Digit6, Digit7, Digit8, Digit9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Escape,
+    Enter,
+    Tab,
+    Backspace,
+    Space,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Left,
+    Right,
+    Up,
+    Down,
+    Shift,
+    Control,
+    Alt,
+    Minus,
+    Equal,
+    LeftBracket,
+    RightBracket,
+    Backslash,
+    Semicolon,
+    Quote,
+    Grave,
+    Comma,
+    Period,
+    Slash,
+    /// Key without a variant, by the backend's own key code.
+    Other(u32),
+}
+
+impl Key {
+    /// Letter key `A` to `Z`, case-insensitive.
+    pub fn letter(c: char) -> Option<Self> {
+        use Key::*;
+        const LETTERS: [Key; 26] = [A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z];
+        let c = c.to_ascii_uppercase();
+        if c.is_ascii_uppercase() {
+            Some(LETTERS[(c as u8 - b'A') as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Digit key `0` to `9` of the main keyboard.
+    pub fn digit(digit: u32) -> Option<Self> {
+        use Key::*;
+        const DIGITS: [Key; 10] = [Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9];
+        DIGITS.get(digit as usize).copied()
+    }
+
+    /// Function key `F1` to `F12`.
+    pub fn function(n: u32) -> Option<Self> {
+        use Key::*;
+        const FUNCTION: [Key; 12] = [F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12];
+        FUNCTION.get((n as usize).wrapping_sub(1)).copied()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    /// Side buttons, usually back and forward.
+    X1,
+    X2,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// `repeat` is set for presses generated by holding the key down.
+    KeyDown { key: Key, repeat: bool },
+    KeyUp { key: Key },
+    /// Character typed, after the keyboard layout and modifiers are applied.
+    /// Control characters are not reported.
+    Text(char),
+    /// Position of the cursor in pixels of the drawable area, may lie outside while a button is held.
+    /// These are canvas pixels unless the canvas is scaled, see `gfx::resize::Presenter::map_events`.
+    MouseMove { x: isize, y: isize },
+    MouseDown { button: MouseButton },
+    MouseUp { button: MouseButton },
+    /// Wheel rotation in notches, positive away from the user and to the right.
+    MouseWheel { x: f32, y: f32 },
+    /// New size of the drawable area in pixels.
+    Resize { width: usize, height: usize },
+    /// Window gained (`true`) or lost (`false`) keyboard focus.
+    Focus(bool),
+    /// User asked to close the window. The window stays open until the application exits.
+    Close,
+}
+
+/// Input at the end of a frame: what is held, and what changed during the frame.
+#[derive(Clone, Debug)]
+pub struct InputState {
+    /// Cursor position in canvas pixels.
+    pub mouse: (isize, isize),
+    /// Cursor movement during the frame.
+    pub mouse_delta: (isize, isize),
+    /// Wheel rotation during the frame in notches, see `Event::MouseWheel`.
+    pub wheel: (f32, f32),
+    /// Characters typed during the frame.
+    pub text: String,
+    pub focused: bool,
+    /// Last size the window was resized to during the frame.
+    pub resized: Option<(usize, usize)>,
+    pub close_requested: bool,
+    keys_down: Vec<Key>,
+    /// In the order they were pressed, auto-repeats included.
+    key_presses: Vec<(Key, bool)>,
+    keys_released: Vec<Key>,
+    buttons_down: Vec<MouseButton>,
+    buttons_pressed: Vec<MouseButton>,
+    buttons_released: Vec<MouseButton>,
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputState {
+    /// Nothing held, with focus.
+    pub fn new() -> Self {
+        Self {
+            mouse: (0, 0),
+            mouse_delta: (0, 0),
+            wheel: (0.0, 0.0),
+            text: String::new(),
+            focused: true,
+            resized: None,
+            close_requested: false,
+            keys_down: Vec::new(),
+            key_presses: Vec::new(),
+            keys_released: Vec::new(),
+            buttons_down: Vec::new(),
+            buttons_pressed: Vec::new(),
+            buttons_released: Vec::new(),
+        }
+    }
+
+    /// Starts a new frame with the events that arrived since the previous one.
+    pub fn update(&mut self, events: &[Event]) {
+        self.begin_frame();
+        for event in events {
+            self.handle(event);
+        }
+    }
+
+    /// Forgets what changed during the previous frame, keeping what is held.
+    pub fn begin_frame(&mut self) {
+        self.mouse_delta = (0, 0);
+        self.wheel = (0.0, 0.0);
+        self.text.clear();
+        self.resized = None;
+        self.close_requested = false;
+        self.key_presses.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+    }
+
+    pub fn handle(&mut self, event: &Event) {
+        match *event {
+            Event::KeyDown { key, repeat } => {
+                if !self.keys_down.contains(&key) {
+                    self.keys_down.push(key);
+                }
+                self.key_presses.push((key, repeat));
+            },
+            Event::KeyUp { key } => {
+                self.keys_down.retain(|&down| down != key);
+                self.keys_released.push(key);
+            },
+            Event::Text(c) => self.text.push(c),
+            Event::MouseMove { x, y } => {
+                self.mouse_delta.0 += x - self.mouse.0;
+                self.mouse_delta.1 += y - self.mouse.1;
+                self.mouse = (x, y);
+            },
+            Event::MouseDown { button } => {
+                if !self.buttons_down.contains(&button) {
+                    self.buttons_down.push(button);
+                }
+                self.buttons_pressed.push(button);
+            },
+            Event::MouseUp { button } => {
+                self.buttons_down.retain(|&down| down != button);
+                self.buttons_released.push(button);
+            },
+            Event::MouseWheel { x, y } => {
+                self.wheel.0 += x;
+                self.wheel.1 += y;
+            },
+            Event::Resize { width, height } => self.resized = Some((width, height)),
+            Event::Focus(focused) => {
+                self.focused = focused;
+                // releases go to the window with focus, so nothing stays held
+                if !focused {
+                    self.keys_released.append(&mut self.keys_down);
+                    self.buttons_released.append(&mut self.buttons_down);
+                }
+            },
+            Event::Close => self.close_requested = true,
+        }
+    }
+
+    pub fn is_down(&self, key: Key) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    /// Whether `key` went down during the frame, auto-repeats not included.
+    pub fn pressed(&self, key: Key) -> bool {
+        self.key_presses.iter().any(|&(pressed, repeat)| pressed == key && !repeat)
+    }
+
+    pub fn released(&self, key: Key) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    /// Keys pressed during the frame in order, auto-repeats included,
+    /// for things like moving through a list by holding an arrow.
+    pub fn key_presses(&self) -> impl Iterator<Item = Key> + '_ {
+        self.key_presses.iter().map(|&(key, _)| key)
+    }
+
+    pub fn keys_down(&self) -> &[Key] {
+        &self.keys_down
+    }
+
+    pub fn button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+}
@@ -1,3 +1,25 @@
+pub mod app;
+pub mod backend;
+pub mod camera;
 pub mod canvas;
+pub mod debug_text;
+pub mod draw;
+pub mod error;
+pub mod golden;
+pub mod hud;
+pub mod image;
+pub mod input;
 pub mod math;
-pub mod win_except;
\ No newline at end of file
+pub mod metrics;
+pub mod pathtracer;
+pub mod profile;
+pub mod progressive;
+pub mod raytracer;
+pub mod record;
+pub mod resize;
+pub mod sampling;
+#[cfg(feature = "truetype")]
+pub mod text;
+pub mod ui;
+
+pub use error::Error;
@@ -1 +1,83 @@
-pub type Num = f64;
\ No newline at end of file
+pub type Num = f64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct V3 {
+    pub x: Num,
+    pub y: Num,
+    pub z: Num,
+}
+
+impl From<[Num; 3]> for V3 {
+    fn from([x, y, z]: [Num; 3]) -> Self {
+        V3 { x, y, z }
+    }
+}
+
+impl From<[isize; 3]> for V3 {
+    fn from([x, y, z]: [isize; 3]) -> Self {
+        V3 { x: x as Num, y: y as Num, z: z as Num }
+    }
+}
+
+impl std::ops::Mul<V3> for Num {
+    type Output = V3;
+    fn mul(self, rhs: V3) -> Self::Output {
+        [self * rhs.x, self * rhs.y, self * rhs.z].into()
+    }
+}
+
+impl std::ops::Mul<Num> for V3 {
+    type Output = V3;
+    fn mul(self, rhs: Num) -> Self::Output {
+        rhs * self
+    }
+}
+
+impl std::ops::Div<Num> for V3 {
+    type Output = V3;
+    fn div(self, rhs: Num) -> Self::Output {
+        [self.x / rhs, self.y / rhs, self.z / rhs].into()
+    }
+}
+
+impl std::ops::Add for V3 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        [self.x + rhs.x, self.y + rhs.y, self.z + rhs.z].into()
+    }
+}
+
+impl std::ops::Sub for V3 {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self + (-rhs)
+    }
+}
+
+impl std::ops::Neg for V3 {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        [-self.x, -self.y, -self.z].into()
+    }
+}
+
+pub fn dot(lhs: V3, rhs: V3) -> Num {
+    lhs.x * rhs.x + lhs.y * rhs.y + lhs.z * rhs.z
+}
+
+pub fn len(v: V3) -> Num {
+    dot(v, v).sqrt()
+}
+
+/// Perpendicular to both, `cross(x, y) == z` for the axes of the scene.
+pub fn cross(lhs: V3, rhs: V3) -> V3 {
+    [
+        lhs.y * rhs.z - lhs.z * rhs.y,
+        lhs.z * rhs.x - lhs.x * rhs.z,
+        lhs.x * rhs.y - lhs.y * rhs.x,
+    ].into()
+}
+
+pub fn normalize(v: V3) -> V3 {
+    v / len(v)
+}
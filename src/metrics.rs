@@ -0,0 +1,193 @@
+//! Image quality metrics between two canvases of the same size.
+//!
+//! All metrics look at color channels only, alpha is ignored.
+//! Every function panics if canvas sizes differ.
+
+use crate::canvas::{Canvas, Color};
+
+pub struct Metrics {
+    pub mse: f64,
+    pub psnr: f64,
+    pub ssim: f64,
+    pub max_abs_diff: u8,
+}
+
+pub fn compare(a: &Canvas, b: &Canvas) -> Metrics {
+    let mse = mse(a, b);
+    Metrics {
+        mse,
+        psnr: psnr_from_mse(mse),
+        ssim: ssim(a, b),
+        max_abs_diff: max_abs_diff(a, b),
+    }
+}
+
+/// Mean squared error over all color channels, in `0.0..=65025.0`. `0.0` for empty canvases.
+pub fn mse(a: &Canvas, b: &Canvas) -> f64 {
+    assert_same_size(a, b);
+    if a.pixels().is_empty() {
+        return 0.0;
+    }
+
+    let sum: u64 = a.pixels()
+        .iter()
+        .zip(b.pixels())
+        .flat_map(|(&a, &b)| channels(a).zip(channels(b)))
+        .map(|(a, b)| (a as i64 - b as i64).pow(2) as u64)
+        .sum();
+
+    sum as f64 / (a.pixels().len() * 3) as f64
+}
+
+/// Peak signal-to-noise ratio in decibels. Infinite for identical images.
+pub fn psnr(a: &Canvas, b: &Canvas) -> f64 {
+    psnr_from_mse(mse(a, b))
+}
+
+fn psnr_from_mse(mse: f64) -> f64 {
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0 * 255.0 / mse).log10()
+    }
+}
+
+/// Largest difference of a single channel.
+pub fn max_abs_diff(a: &Canvas, b: &Canvas) -> u8 {
+    assert_same_size(a, b);
+
+    a.pixels()
+        .iter()
+        .zip(b.pixels())
+        .map(|(&a, &b)| pixel_diff(a, b))
+        .max()
+        .unwrap_or(0)
+}
+
+const SSIM_WINDOW: usize = 8;
+const SSIM_STEP: usize = 4;
+
+/// Mean structural similarity of luma, in `-1.0..=1.0`, where `1.0` means identical.
+///
+/// Computed over 8x8 windows placed 4 pixels apart, plus windows along the right
+/// and bottom edges so every pixel is covered. Images smaller than a window are
+/// compared as a single window.
+pub fn ssim(a: &Canvas, b: &Canvas) -> f64 {
+    assert_same_size(a, b);
+
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let width = a.width();
+    let height = a.height();
+    if width == 0 || height == 0 {
+        return 1.0;
+    }
+
+    let luma_a: Vec<f64> = a.pixels().iter().map(|&c| luma(c)).collect();
+    let luma_b: Vec<f64> = b.pixels().iter().map(|&c| luma(c)).collect();
+
+    let window_w = SSIM_WINDOW.min(width);
+    let window_h = SSIM_WINDOW.min(height);
+
+    let mut sum = 0.0;
+    let mut windows = 0;
+    for y0 in window_starts(height, window_h) {
+        for x0 in window_starts(width, window_w) {
+            let n = (window_w * window_h) as f64;
+            let (mut sum_a, mut sum_b) = (0.0, 0.0);
+            let (mut sum_aa, mut sum_bb, mut sum_ab) = (0.0, 0.0, 0.0);
+            for y in y0..y0 + window_h {
+                for x in x0..x0 + window_w {
+                    let a = luma_a[x + y * width];
+                    let b = luma_b[x + y * width];
+                    sum_a += a;
+                    sum_b += b;
+                    sum_aa += a * a;
+                    sum_bb += b * b;
+                    sum_ab += a * b;
+                }
+            }
+
+            let mean_a = sum_a / n;
+            let mean_b = sum_b / n;
+            let var_a = sum_aa / n - mean_a * mean_a;
+            let var_b = sum_bb / n - mean_b * mean_b;
+            let covar = sum_ab / n - mean_a * mean_b;
+
+            sum += ((2.0 * mean_a * mean_b + C1) * (2.0 * covar + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+
+    sum / windows as f64
+}
+
+/// Offsets of windows along a side of `len` pixels, `SSIM_STEP` apart, the last one flush with the end.
+fn window_starts(len: usize, window: usize) -> impl Iterator<Item = usize> {
+    let last = len - window;
+    (0..last).step_by(SSIM_STEP).chain(std::iter::once(last))
+}
+
+/// Visualizes the largest channel difference of every pixel,
+/// from black for equal pixels through blue, red and yellow to white.
+///
+/// Differences are scaled so the largest one is white, which makes even
+/// off-by-one errors visible. Identical images give a black canvas.
+pub fn diff_heatmap(a: &Canvas, b: &Canvas) -> Canvas {
+    assert_same_size(a, b);
+
+    let max = max_abs_diff(a, b);
+    let mut heatmap = Canvas::new(a.width(), a.height()).expect("Canvas::new(width, height) failed");
+    for y in 0..a.height() {
+        for x in 0..a.width() {
+            let diff = pixel_diff(a.get((x, y)), b.get((x, y)));
+            let t = if max == 0 { 0.0 } else { diff as f64 / max as f64 };
+            heatmap.set((x, y), heat(t));
+        }
+    }
+    heatmap
+}
+
+/// Maps `t` in `0.0..=1.0` onto the heatmap color ramp.
+fn heat(t: f64) -> Color {
+    const STOPS: [[f64; 3]; 5] = [
+        [0.0, 0.0, 0.0],
+        [0.0, 0.0, 255.0],
+        [255.0, 0.0, 0.0],
+        [255.0, 255.0, 0.0],
+        [255.0, 255.0, 255.0],
+    ];
+
+    let t = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let i = (t as usize).min(STOPS.len() - 2);
+    let f = t - i as f64;
+    let lerp = |c: usize| (STOPS[i][c] + (STOPS[i + 1][c] - STOPS[i][c]) * f).round() as u8;
+
+    Color { r: lerp(0), g: lerp(1), b: lerp(2), a: 255 }
+}
+
+fn channels(c: Color) -> impl Iterator<Item = u8> {
+    IntoIterator::into_iter([c.r, c.g, c.b])
+}
+
+fn pixel_diff(a: Color, b: Color) -> u8 {
+    channels(a).zip(channels(b)).map(|(a, b)| a.abs_diff(b)).max().unwrap()
+}
+
+/// Rec. 601 luma, as used by the reference SSIM implementation.
+fn luma(c: Color) -> f64 {
+    0.299 * c.r as f64 + 0.587 * c.g as f64 + 0.114 * c.b as f64
+}
+
+fn assert_same_size(a: &Canvas, b: &Canvas) {
+    assert!(
+        a.width() == b.width() && a.height() == b.height(),
+        "canvas sizes differ: {}x{} and {}x{}",
+        a.width(),
+        a.height(),
+        b.width(),
+        b.height(),
+    );
+}
@@ -0,0 +1,393 @@
+//! Monte Carlo path tracing, the physically based counterpart of `gfx::raytracer`.
+//!
+//! Every sample follows a random path from the camera, bouncing off surfaces by their
+//! `Material` until it leaves the scene, and adds up the light emitted along the way.
+//! Diffuse bounces go in cosine-weighted directions and sample the emissive spheres
+//! directly (next-event estimation), long paths end at random (Russian roulette).
+//! Averaging many samples per pixel converges to the lit image, either all at once
+//! with `render` or over frames with `render_progressive`. Random numbers come from
+//! `PathTracer::seed`, so the same seed renders the same image. Samples are averaged
+//! as they are, lights brighter than 1 included, and only the average is clamped.
+
+use std::f64::consts::PI;
+use crate::{
+    camera::{Camera, Projection, Ray},
+    canvas::{Canvas, Color},
+    math::{Num, V3, cross, dot, len, normalize},
+    progressive::Progressive,
+    sampling::{self, Filter, Pattern, Rng, Supersampling},
+};
+
+/// Distance secondary rays start from their surface, so they do not hit it again by rounding.
+const EPSILON: Num = 1e-3;
+
+/// Linear color, 1 is full brightness on a canvas. Light sources may be brighter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rgb {
+    pub r: Num,
+    pub g: Num,
+    pub b: Num,
+}
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb { r: 0.0, g: 0.0, b: 0.0 };
+
+    pub fn new(r: Num, g: Num, b: Num) -> Self {
+        Self { r, g, b }
+    }
+
+    pub fn gray(value: Num) -> Self {
+        Self::new(value, value, value)
+    }
+
+    fn max(self) -> Num {
+        self.r.max(self.g).max(self.b)
+    }
+
+    /// Clamped to the canvas' range, without tone mapping.
+    pub fn to_color(self) -> Color {
+        let channel = |value: Num| (value * 255.0).round().clamp(0.0, 255.0) as u8;
+        Color { r: channel(self.r), g: channel(self.g), b: channel(self.b), a: 255 }
+    }
+}
+
+impl From<Rgb> for [Num; 3] {
+    fn from(rgb: Rgb) -> Self {
+        [rgb.r, rgb.g, rgb.b]
+    }
+}
+
+impl std::ops::Add for Rgb {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.r + rhs.r, self.g + rhs.g, self.b + rhs.b)
+    }
+}
+
+impl std::ops::AddAssign for Rgb {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl std::ops::Mul for Rgb {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::new(self.r * rhs.r, self.g * rhs.g, self.b * rhs.b)
+    }
+}
+
+impl std::ops::Mul<Num> for Rgb {
+    type Output = Self;
+    fn mul(self, rhs: Num) -> Self::Output {
+        Self::new(self.r * rhs, self.g * rhs, self.b * rhs)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Material {
+    /// Lambertian, scatters equally in all directions.
+    Diffuse { albedo: Rgb },
+    /// Mirror, blurred by up to `roughness` from 0 (polished) to 1.
+    Metal { albedo: Rgb, roughness: Num },
+    /// Glass and the like, refracting by the index of refraction `ior` and reflecting by Fresnel.
+    Dielectric { ior: Num },
+    /// Light source, emits `radiance` and reflects nothing.
+    Emissive { radiance: Rgb },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sphere {
+    pub center: V3,
+    pub radius: Num,
+    pub material: Material,
+}
+
+pub struct Scene {
+    /// Emissive ones are the lights.
+    pub spheres: Vec<Sphere>,
+    /// Radiance of rays that leave the scene.
+    pub sky: Rgb,
+    pub camera: Camera,
+}
+
+/// Closest intersection of a ray with a sphere of a scene.
+struct Hit {
+    point: V3,
+    /// Unit normal pointing out of the sphere.
+    normal: V3,
+    sphere: usize,
+}
+
+impl Scene {
+    /// Diffuse, metal and glass spheres on a floor, lit by a spherical light and a dim sky.
+    pub fn demo() -> Self {
+        let spheres = vec![
+            Sphere {
+                center: [0.0, -100.5, 2.0].into(),
+                radius: 100.0,
+                material: Material::Diffuse { albedo: Rgb::gray(0.6) },
+            },
+            Sphere {
+                center: [0.0, 0.0, 2.0].into(),
+                radius: 0.5,
+                material: Material::Diffuse { albedo: Rgb::new(0.7, 0.25, 0.2) },
+            },
+            Sphere {
+                center: [1.1, 0.0, 2.4].into(),
+                radius: 0.5,
+                material: Material::Metal { albedo: Rgb::gray(0.8), roughness: 0.05 },
+            },
+            Sphere {
+                center: [-1.1, 0.0, 2.4].into(),
+                radius: 0.5,
+                material: Material::Dielectric { ior: 1.5 },
+            },
+            Sphere {
+                center: [0.0, 2.5, 2.0].into(),
+                radius: 0.7,
+                material: Material::Emissive { radiance: Rgb::gray(12.0) },
+            },
+        ];
+        let camera = Camera {
+            projection: Projection::Perspective { fov_y: Num::to_radians(50.0) },
+            ..Camera::look_at([0.0, 0.6, -1.5].into(), [0.0, 0.0, 2.0].into())
+        };
+        Self { spheres, sky: Rgb::new(0.1, 0.12, 0.18), camera }
+    }
+
+    fn intersect(&self, origin: V3, direction: V3, t_min: Num, t_max: Num) -> Option<Hit> {
+        let mut closest: Option<(usize, Num)> = None;
+        let a = dot(direction, direction);
+        for (i, sphere) in self.spheres.iter().enumerate() {
+            // `t * t * a + 2 * t * h + c = 0`, see `gfx::raytracer` for the derivation
+            let oc = origin - sphere.center;
+            let h = dot(oc, direction);
+            let c = dot(oc, oc) - sphere.radius * sphere.radius;
+            let discriminant = h * h - a * c;
+            if discriminant < 0.0 {
+                continue;
+            }
+            let root = discriminant.sqrt();
+            let t_max = closest.map_or(t_max, |(_, t)| t);
+            for t in [(-h - root) / a, (-h + root) / a] {
+                if t > t_min && t < t_max {
+                    closest = Some((i, t));
+                    break;
+                }
+            }
+        }
+        closest.map(|(sphere, t)| {
+            let point = origin + t * direction;
+            Hit { point, normal: (point - self.spheres[sphere].center) / self.spheres[sphere].radius, sphere }
+        })
+    }
+}
+
+/// Settings of the integrator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathTracer {
+    /// Samples per pixel of `render`.
+    pub samples: usize,
+    /// Most surfaces a path bounces off.
+    pub max_depth: usize,
+    /// Bounces after which paths end at random, more likely the less light they carry.
+    pub roulette_depth: usize,
+    /// Whether diffuse bounces sample the lights directly. Converges to the same image,
+    /// with less noise where lights are small.
+    pub next_event: bool,
+    pub seed: u64,
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PathTracer {
+    /// 64 samples per pixel, up to 16 bounces, roulette after 3, with next-event estimation.
+    pub fn new() -> Self {
+        Self { samples: 64, max_depth: 16, roulette_depth: 3, next_event: true, seed: 0 }
+    }
+
+    /// Light arriving along `ray` towards its origin, estimated by one random path.
+    pub fn radiance(&self, scene: &Scene, ray: &Ray, rng: &mut Rng) -> Rgb {
+        let mut radiance = Rgb::BLACK;
+        let mut throughput = Rgb::gray(1.0);
+        let (mut origin, mut direction) = (ray.origin, ray.direction);
+        let (mut t_min, mut t_max) = (ray.t_min, ray.t_max);
+        // emission found by a diffuse bounce was already counted by next-event estimation
+        let mut count_emission = true;
+
+        for depth in 0..self.max_depth {
+            let hit = match scene.intersect(origin, direction, t_min, t_max) {
+                Some(hit) => hit,
+                None => {
+                    radiance += throughput * scene.sky;
+                    break;
+                },
+            };
+            let incoming = normalize(direction);
+            direction = match scene.spheres[hit.sphere].material {
+                Material::Emissive { radiance: emitted } => {
+                    if count_emission {
+                        radiance += throughput * emitted;
+                    }
+                    break;
+                },
+                Material::Diffuse { albedo } => {
+                    // facing the ray, for the inside of spheres
+                    let normal = if dot(incoming, hit.normal) > 0.0 { -hit.normal } else { hit.normal };
+                    if self.next_event {
+                        radiance += throughput * albedo * self.direct(scene, hit.point, normal, rng) * (1.0 / PI);
+                    }
+                    count_emission = !self.next_event;
+                    // cosine-weighted, so the cosine over the density cancels the 1 / pi of the BRDF
+                    throughput = throughput * albedo;
+                    cosine_direction(normal, rng)
+                },
+                Material::Metal { albedo, roughness } => {
+                    count_emission = true;
+                    let reflected = reflect(incoming, hit.normal) + roughness * in_unit_sphere(rng);
+                    if dot(reflected, hit.normal) <= 0.0 {
+                        break;
+                    }
+                    throughput = throughput * albedo;
+                    normalize(reflected)
+                },
+                Material::Dielectric { ior } => {
+                    count_emission = true;
+                    refract(incoming, hit.normal, ior, rng)
+                },
+            };
+            origin = hit.point;
+            t_min = EPSILON;
+            t_max = Num::INFINITY;
+
+            if depth + 1 >= self.roulette_depth {
+                let survival = throughput.max().min(0.95);
+                if rng.next_float() >= survival {
+                    break;
+                }
+                throughput = throughput * survival.recip();
+            }
+        }
+        radiance
+    }
+
+    /// Light from the emissive spheres arriving at `point` of a surface facing `normal`,
+    /// weighted by the cosine, one random point per light.
+    fn direct(&self, scene: &Scene, point: V3, normal: V3, rng: &mut Rng) -> Rgb {
+        let mut direct = Rgb::BLACK;
+        for (i, light) in scene.spheres.iter().enumerate() {
+            let emitted = match light.material {
+                Material::Emissive { radiance } => radiance,
+                _ => continue,
+            };
+            let to_center = light.center - point;
+            let distance = len(to_center);
+            if distance <= light.radius {
+                continue;
+            }
+
+            // uniformly in the cone the sphere covers as seen from `point`
+            let sin2_max = (light.radius / distance).powi(2);
+            let cos_max = (1.0 - sin2_max).sqrt();
+            let one_minus_cos_max = sin2_max / (1.0 + cos_max);
+            let cos_theta = 1.0 - rng.next_float() * one_minus_cos_max;
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = 2.0 * PI * rng.next_float();
+            let (tangent, bitangent) = orthonormal(to_center / distance);
+            let direction = (sin_theta * phi.cos()) * tangent
+                + (sin_theta * phi.sin()) * bitangent
+                + cos_theta * (to_center / distance);
+
+            let cosine = dot(normal, direction);
+            if cosine <= 0.0 {
+                continue;
+            }
+            match scene.intersect(point, direction, EPSILON, Num::INFINITY) {
+                Some(hit) if hit.sphere == i => {
+                    let density = 1.0 / (2.0 * PI * one_minus_cos_max);
+                    direct += emitted * (cosine / density);
+                },
+                _ => {},
+            }
+        }
+        direct
+    }
+}
+
+/// Two unit vectors perpendicular to unit `n` and to each other.
+fn orthonormal(n: V3) -> (V3, V3) {
+    let helper = if n.x.abs() > 0.9 { V3::from([0, 1, 0]) } else { V3::from([1, 0, 0]) };
+    let tangent = normalize(cross(helper, n));
+    (tangent, cross(n, tangent))
+}
+
+/// Random unit vector around `normal`, more likely the closer to it by the cosine.
+fn cosine_direction(normal: V3, rng: &mut Rng) -> V3 {
+    let u = rng.next_float();
+    let phi = 2.0 * PI * rng.next_float();
+    let r = u.sqrt();
+    let (tangent, bitangent) = orthonormal(normal);
+    (r * phi.cos()) * tangent + (r * phi.sin()) * bitangent + (1.0 - u).max(0.0).sqrt() * normal
+}
+
+fn in_unit_sphere(rng: &mut Rng) -> V3 {
+    loop {
+        let v = V3::from([rng.next_float(), rng.next_float(), rng.next_float()]) * 2.0 - V3::from([1, 1, 1]);
+        if dot(v, v) < 1.0 {
+            return v;
+        }
+    }
+}
+
+fn reflect(incoming: V3, normal: V3) -> V3 {
+    incoming - 2.0 * dot(incoming, normal) * normal
+}
+
+/// Refracted or reflected unit direction of `incoming` at a surface with outward `normal`,
+/// picked at random by Schlick's approximation of the Fresnel reflectance.
+fn refract(incoming: V3, normal: V3, ior: Num, rng: &mut Rng) -> V3 {
+    let cos_incoming = -dot(incoming, normal);
+    let (normal, eta, cos_incoming) = if cos_incoming > 0.0 {
+        (normal, 1.0 / ior, cos_incoming)
+    } else {
+        (-normal, ior, -cos_incoming)
+    };
+    let sin2_refracted = eta * eta * (1.0 - cos_incoming * cos_incoming);
+    let r0 = ((1.0 - ior) / (1.0 + ior)).powi(2);
+    let reflectance = r0 + (1.0 - r0) * (1.0 - cos_incoming).powi(5);
+    if sin2_refracted > 1.0 || rng.next_float() < reflectance {
+        return reflect(incoming, normal);
+    }
+    normalize(eta * incoming + (eta * cos_incoming - (1.0 - sin2_refracted).sqrt()) * normal)
+}
+
+/// Renders `tracer.samples` paths per pixel of `canvas`, jittered and box filtered.
+/// Returns the number of paths traced.
+pub fn render(canvas: &mut Canvas, scene: &Scene, tracer: &PathTracer) -> usize {
+    crate::profile_scope!("trace_paths");
+    let view = scene.camera.view((canvas.width(), canvas.height()));
+    let settings = Supersampling {
+        pattern: Pattern::Jittered,
+        samples: tracer.samples,
+        filter: Filter::BOX,
+        adaptive: None,
+        seed: tracer.seed,
+    };
+    let mut rng = Rng::new(tracer.seed);
+    sampling::render_rgb(canvas, &settings, |point| tracer.radiance(scene, &view.ray(point), &mut rng).into())
+}
+
+/// Renders the next frame of `progressive` onto `canvas`, a path per pixel once past the
+/// coarse frames. Returns the number of paths traced.
+pub fn render_progressive(canvas: &mut Canvas, scene: &Scene, tracer: &PathTracer, progressive: &mut Progressive) -> usize {
+    crate::profile_scope!("trace_paths");
+    let view = scene.camera.view((canvas.width(), canvas.height()));
+    // a different sequence every frame, the same for the same frame
+    let mut rng = Rng::new(tracer.seed ^ (progressive.samples() as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    progressive.render(canvas, |point| tracer.radiance(scene, &view.ray(point), &mut rng).into())
+}
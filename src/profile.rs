@@ -0,0 +1,396 @@
+//! Hierarchical CPU profiler.
+//!
+//! `profile_scope!("name")` times the rest of the enclosing block. Scopes nest,
+//! and every thread records its own. `finish_frame` collects everything recorded
+//! since the previous call into a `Frame`, which `FlameView` draws onto a canvas
+//! and `Capture` saves in Chrome's trace format, viewable offline in
+//! `chrome://tracing` or Perfetto.
+//!
+//! Profiling is off until `set_enabled(true)`, scopes cost next to nothing then.
+//!
+//! ```no_run
+//! # use gfx::{profile::{self, Capture}, profile_scope};
+//! profile::set_enabled(true);
+//! let mut capture = Capture::new();
+//! for _ in 0..100 {
+//!     {
+//!         profile_scope!("update");
+//!     }
+//!     {
+//!         profile_scope!("render");
+//!     }
+//!     capture.push(profile::finish_frame());
+//! }
+//! capture.save_chrome_trace("trace.json").unwrap();
+//! ```
+
+use std::{
+    cell::{Cell, RefCell},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{
+        Mutex,
+        OnceLock,
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use crate::{
+    canvas::{Canvas, Color, Rect},
+    debug_text::{GLYPH_HEIGHT, GLYPH_WIDTH, draw_debug_text},
+    draw::{draw_rect, fill_rect},
+};
+
+/// Times the rest of the enclosing block under `name`, a `&'static str`.
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::profile::Scope::new($name);
+    };
+}
+
+/// Timed scope, see `profile_scope!`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub name: &'static str,
+    /// Index of the thread, see `Frame::threads`.
+    pub thread: u32,
+    /// Number of scopes around this one on its thread.
+    pub depth: usize,
+    /// Since the profiler started.
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+impl Span {
+    pub fn end(&self) -> Duration {
+        self.start + self.duration
+    }
+}
+
+/// Spans finished between two `finish_frame` calls.
+#[derive(Clone, Debug, Default)]
+pub struct Frame {
+    /// Number of frames finished before this one.
+    pub index: u64,
+    /// Since the profiler started.
+    pub start: Duration,
+    pub end: Duration,
+    /// Sorted by thread, then by start.
+    pub spans: Vec<Span>,
+    /// Index and name of every thread with spans.
+    pub threads: Vec<(u32, String)>,
+    /// Spans that finished past `MAX_SPANS` and were not kept.
+    pub dropped: usize,
+}
+
+/// Most spans kept between two `finish_frame` calls, so memory stays bounded
+/// when frames are finished rarely or never. Later spans are only counted.
+pub const MAX_SPANS: usize = 1 << 16;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static NEXT_THREAD: AtomicU32 = AtomicU32::new(0);
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+static STATE: Mutex<State> = Mutex::new(State {
+    spans: Vec::new(),
+    threads: Vec::new(),
+    frame_index: 0,
+    frame_start: Duration::ZERO,
+});
+
+struct State {
+    /// Spans finished on any thread in the current frame.
+    spans: Vec<Span>,
+    threads: Vec<(u32, String)>,
+    frame_index: u64,
+    frame_start: Duration,
+}
+
+thread_local! {
+    static THREAD: u32 = register_thread();
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+    /// Spans of unfinished outermost scopes, flushed when they finish.
+    static LOCAL: RefCell<Vec<Span>> = const { RefCell::new(Vec::new()) };
+}
+
+fn epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+fn state() -> std::sync::MutexGuard<'static, State> {
+    // a panic inside a scope leaves nothing half done
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn register_thread() -> u32 {
+    let id = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+    let name = std::thread::current()
+        .name()
+        .map_or_else(|| format!("thread {}", id), str::to_owned);
+    state().threads.push((id, name));
+    id
+}
+
+pub fn set_enabled(enabled: bool) {
+    if enabled {
+        epoch();
+    }
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Guard recording a span when dropped. Created by `profile_scope!`.
+pub struct Scope {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+impl Scope {
+    pub fn new(name: &'static str) -> Self {
+        let start = if is_enabled() {
+            DEPTH.with(|depth| depth.set(depth.get() + 1));
+            Some(Instant::now())
+        } else {
+            None
+        };
+        Self { name, start }
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let start = match self.start {
+            Some(start) => start,
+            None => return,
+        };
+        let duration = start.elapsed();
+
+        let depth = DEPTH.with(|depth| {
+            let inner = depth.get() - 1;
+            depth.set(inner);
+            inner
+        });
+        let span = Span {
+            name: self.name,
+            thread: THREAD.with(|&thread| thread),
+            depth,
+            start: start.saturating_duration_since(epoch()),
+            duration,
+        };
+
+        LOCAL.with(|local| {
+            let mut local = local.borrow_mut();
+            if local.len() < MAX_SPANS {
+                local.push(span);
+            } else {
+                DROPPED.fetch_add(1, Ordering::Relaxed);
+            }
+            if depth == 0 {
+                let mut state = state();
+                let room = MAX_SPANS.saturating_sub(state.spans.len());
+                if local.len() > room {
+                    DROPPED.fetch_add(local.len() - room, Ordering::Relaxed);
+                    local.truncate(room);
+                }
+                state.spans.append(&mut local);
+            }
+        });
+    }
+}
+
+/// Ends the current frame and returns spans of scopes that finished during it.
+/// Scopes still running belong to the frame they finish in.
+pub fn finish_frame() -> Frame {
+    let end = epoch().elapsed();
+    let mut state = state();
+
+    let mut spans = std::mem::take(&mut state.spans);
+    spans.sort_by_key(|span| (span.thread, span.start, span.depth));
+    let mut threads: Vec<_> = state.threads
+        .iter()
+        .filter(|(id, _)| spans.iter().any(|span| span.thread == *id))
+        .cloned()
+        .collect();
+    threads.sort_by_key(|&(id, _)| id);
+
+    let frame = Frame {
+        index: state.frame_index,
+        start: state.frame_start,
+        end,
+        spans,
+        threads,
+        dropped: DROPPED.swap(0, Ordering::Relaxed),
+    };
+    state.frame_index += 1;
+    state.frame_start = end;
+    frame
+}
+
+/// Frames collected for saving.
+#[derive(Default)]
+pub struct Capture {
+    pub frames: Vec<Frame>,
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, frame: Frame) {
+        self.frames.push(frame);
+    }
+
+    /// Writes Chrome's trace event JSON: a complete event for every span
+    /// and an instant event at the start of every frame.
+    pub fn write_chrome_trace(&self, mut w: impl Write) -> io::Result<()> {
+        let micros = |t: Duration| t.as_nanos() as f64 / 1000.0;
+
+        w.write_all(b"{\"displayTimeUnit\":\"ms\",\"traceEvents\":[")?;
+        let mut first = true;
+        let mut separator = |w: &mut dyn Write| -> io::Result<()> {
+            if !std::mem::replace(&mut first, false) {
+                w.write_all(b",\n")?;
+            }
+            Ok(())
+        };
+
+        let mut named = Vec::new();
+        for frame in &self.frames {
+            for (id, name) in &frame.threads {
+                if !named.contains(id) {
+                    named.push(*id);
+                    separator(&mut w)?;
+                    write!(w, "{{\"ph\":\"M\",\"pid\":0,\"tid\":{},\"name\":\"thread_name\",\"args\":{{\"name\":", id)?;
+                    write_json_string(&mut w, name)?;
+                    w.write_all(b"}}")?;
+                }
+            }
+
+            separator(&mut w)?;
+            write!(
+                w,
+                "{{\"ph\":\"i\",\"s\":\"g\",\"pid\":0,\"tid\":0,\"ts\":{:.3},\"name\":\"frame {}\"}}",
+                micros(frame.start),
+                frame.index,
+            )?;
+
+            for span in &frame.spans {
+                separator(&mut w)?;
+                write!(
+                    w,
+                    "{{\"ph\":\"X\",\"pid\":0,\"tid\":{},\"ts\":{:.3},\"dur\":{:.3},\"name\":",
+                    span.thread,
+                    micros(span.start),
+                    micros(span.duration),
+                )?;
+                write_json_string(&mut w, span.name)?;
+                w.write_all(b"}")?;
+            }
+        }
+        w.write_all(b"]}\n")?;
+        w.flush()
+    }
+
+    pub fn save_chrome_trace(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_chrome_trace(BufWriter::new(File::create(path)?))
+    }
+}
+
+fn write_json_string(w: &mut impl Write, s: &str) -> io::Result<()> {
+    w.write_all(b"\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => w.write_all(b"\\\"")?,
+            '\\' => w.write_all(b"\\\\")?,
+            '\n' => w.write_all(b"\\n")?,
+            c if (c as u32) < 0x20 => write!(w, "\\u{:04x}", c as u32)?,
+            c => write!(w, "{}", c)?,
+        }
+    }
+    w.write_all(b"\"")
+}
+
+/// Timeline of a frame: time runs from left to right, every thread gets a lane
+/// with a row per nesting level, outermost scopes on top.
+pub struct FlameView {
+    pub rect: Rect,
+    /// Height of a span, labels are drawn in spans at least `GLYPH_HEIGHT` tall.
+    pub row_height: usize,
+    /// Time shown across the width. The frame's own duration if `None`.
+    pub duration: Option<Duration>,
+    pub background: Color,
+    pub text: Color,
+}
+
+impl FlameView {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            row_height: GLYPH_HEIGHT + 4,
+            duration: None,
+            background: Color { r: 0, g: 0, b: 0, a: 160 },
+            text: Color { r: 255, g: 255, b: 255, a: 255 },
+        }
+    }
+
+    /// Color of spans named `name`, the same every frame.
+    pub fn color(name: &str) -> Color {
+        // FNV-1a
+        let hash = name.bytes().fold(0x811c_9dc5u32, |hash, b| (hash ^ b as u32).wrapping_mul(0x0100_0193));
+        let channel = |shift: u32| 96 + (hash >> shift & 0x7f) as u8;
+        Color { r: channel(0), g: channel(8), b: channel(16), a: 255 }
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas, frame: &Frame) {
+        fill_rect(canvas, self.rect, self.background);
+
+        let duration = self.duration.unwrap_or(frame.end.saturating_sub(frame.start)).as_secs_f64();
+        if duration <= 0.0 || self.row_height == 0 {
+            return;
+        }
+        let width = self.rect.width as f64;
+        let x_of = |t: Duration| {
+            let t = t.saturating_sub(frame.start).as_secs_f64();
+            self.rect.x + (t / duration * width).round() as isize
+        };
+
+        let mut lane_top = self.rect.y;
+        for &(thread, _) in &frame.threads {
+            let spans = frame.spans.iter().filter(|span| span.thread == thread);
+            let depth = spans.clone().map(|span| span.depth).max().unwrap_or(0);
+
+            for span in spans {
+                let x0 = x_of(span.start);
+                // even the shortest spans stay visible
+                let x1 = x_of(span.end()).max(x0 + 1);
+                let y = lane_top + (span.depth * self.row_height) as isize;
+                let rect = Rect::from_corners((x0, y), (x1, y + self.row_height as isize - 1))
+                    .intersection(&self.rect);
+                if rect.is_empty() {
+                    continue;
+                }
+
+                fill_rect(canvas, rect, Self::color(span.name));
+                if rect.width > 2 && rect.height > 2 {
+                    draw_rect(canvas, rect, Color { r: 0, g: 0, b: 0, a: 64 });
+                }
+
+                let chars = rect.width.saturating_sub(4) / GLYPH_WIDTH;
+                if chars > 0 && rect.height >= GLYPH_HEIGHT {
+                    let label: String = span.name.chars().take(chars).collect();
+                    let label_y = rect.y + (rect.height - GLYPH_HEIGHT) as isize / 2;
+                    draw_debug_text(canvas, (rect.x + 2, label_y), self.text, format_args!("{}", label));
+                }
+            }
+
+            // gap between lanes
+            lane_top += ((depth + 1) * self.row_height) as isize + 2;
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Rendering that refines over frames while nothing changes.
+//!
+//! The first frames after a change trace one sample per block of pixels, halving the
+//! blocks every frame, so the view follows the camera quickly at a low resolution.
+//! After that every frame adds a sample to every pixel in a float buffer and shows the
+//! average, which converges to an antialiased image:
+//!
+//! ```no_run
+//! # use gfx::{canvas::Canvas, progressive::Progressive, raytracer::{self, Scene}};
+//! # let mut canvas = Canvas::new(1280, 720).unwrap();
+//! # let scene = Scene::demo();
+//! # let camera_moved = false;
+//! let mut progressive = Progressive::new();
+//! loop {
+//!     if camera_moved {
+//!         progressive.reset();
+//!     }
+//!     raytracer::render_progressive(&mut canvas, &scene, &mut progressive);
+//!     // present the canvas
+//! }
+//! ```
+
+use crate::{
+    canvas::Canvas,
+    math::Num,
+    sampling::{self, Pattern, PixelRng},
+};
+
+/// Samples accumulated over frames, see the module documentation.
+#[derive(Clone, Debug)]
+pub struct Progressive {
+    /// Where the samples of a pixel go, one per frame. Grids are sized for `max_samples`.
+    pub pattern: Pattern,
+    /// Side in pixels of the blocks of the first frame after a change, 1 to start accumulating right away.
+    pub first_block: usize,
+    /// Samples per pixel after which frames only show the image.
+    pub max_samples: usize,
+    pub seed: u64,
+    size: (usize, usize),
+    /// Sums of the red, green and blue of the samples of every pixel, unclamped.
+    sums: Vec<[Num; 3]>,
+    samples: usize,
+    /// Side of the blocks of the next coarse frame, 1 once accumulating.
+    block: usize,
+}
+
+impl Default for Progressive {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Progressive {
+    /// Starts with blocks of 8 by 8 pixels and stops at 1024 samples per pixel.
+    pub fn new() -> Self {
+        Self {
+            pattern: Pattern::Halton,
+            first_block: 8,
+            max_samples: 1024,
+            seed: 0,
+            size: (0, 0),
+            sums: Vec::new(),
+            samples: 0,
+            block: 8,
+        }
+    }
+
+    /// Forgets the samples, the next frame starts again with blocks of `first_block`.
+    /// Call it when anything in view changed. Resizing the canvas resets by itself.
+    pub fn reset(&mut self) {
+        self.sums.iter_mut().for_each(|sum| *sum = [0.0; 3]);
+        self.samples = 0;
+        self.block = self.first_block.max(1);
+    }
+
+    /// Samples per pixel accumulated, 0 during the coarse frames.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Whether `max_samples` are accumulated.
+    pub fn is_done(&self) -> bool {
+        self.samples >= self.max_samples
+    }
+
+    /// Renders the next frame onto `canvas` with the red, green and blue `sample` gives for
+    /// points of it, in pixels from the top left corner. Channels go from 0 to 1 on the canvas,
+    /// samples beyond that count fully in the average and only the result is clamped.
+    /// Every pixel is written, what was drawn over the previous frame is gone.
+    /// Returns the number of samples taken.
+    pub fn render(&mut self, canvas: &mut Canvas, mut sample: impl FnMut((Num, Num)) -> [Num; 3]) -> usize {
+        crate::profile_scope!("progressive");
+        let (width, height) = (canvas.width(), canvas.height());
+        if self.size != (width, height) {
+            self.size = (width, height);
+            self.sums = vec![[0.0; 3]; width * height];
+            self.reset();
+        }
+
+        if self.block > 1 {
+            let block = self.block;
+            self.block /= 2;
+            return self.render_blocks(canvas, block, sample);
+        }
+
+        let mut taken = 0;
+        if !self.is_done() {
+            let index = self.samples;
+            for y in 0..height {
+                for x in 0..width {
+                    let rng = PixelRng::new(self.seed, (x, y));
+                    let (dx, dy) = self.pattern.point(index, self.max_samples, &rng);
+                    let [r, g, b] = sample((x as Num + dx, y as Num + dy));
+                    let sum = &mut self.sums[y * width + x];
+                    sum[0] += r;
+                    sum[1] += g;
+                    sum[2] += b;
+                }
+            }
+            self.samples += 1;
+            taken = width * height;
+        }
+
+        let scale = (self.samples.max(1) as Num).recip();
+        for y in 0..height {
+            for x in 0..width {
+                let [r, g, b] = self.sums[y * width + x];
+                canvas.set((x, y), sampling::color([r * scale, g * scale, b * scale]));
+            }
+        }
+        taken
+    }
+
+    /// Fills every `block` by `block` square with the sample at its center.
+    fn render_blocks(&self, canvas: &mut Canvas, block: usize, mut sample: impl FnMut((Num, Num)) -> [Num; 3]) -> usize {
+        let (width, height) = (canvas.width(), canvas.height());
+        let mut taken = 0;
+        for top in (0..height).step_by(block) {
+            let bottom = (top + block).min(height);
+            for left in (0..width).step_by(block) {
+                let right = (left + block).min(width);
+                let color = sampling::color(sample(((left + right) as Num / 2.0, (top + bottom) as Num / 2.0)));
+                taken += 1;
+                for y in top..bottom {
+                    for x in left..right {
+                        canvas.set((x, y), color);
+                    }
+                }
+            }
+        }
+        taken
+    }
+}
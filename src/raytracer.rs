@@ -0,0 +1,187 @@
+use crate::{
+    camera::{Camera, Projection},
+    canvas::{Canvas, Color, set_intensity},
+    math::{Num, V3, dot, len},
+    progressive::Progressive,
+    sampling::{self, Supersampling},
+};
+
+pub struct Sphere {
+    pub center: V3,
+    pub radius: Num,
+    pub color: Color,
+}
+
+pub struct Light {
+    pub intensity: Num,
+    pub light_type: LightType
+}
+
+pub enum LightType {
+    Ambient,
+    Point { pos: V3 },
+    Directional { dir: V3 },
+}
+
+pub struct Scene {
+    pub lights: Vec<Light>,
+    pub spheres: Vec<Sphere>,
+    pub camera: Camera,
+}
+
+impl Scene {
+    /// Scene rendered by the demo.
+    pub fn demo() -> Self {
+        let lights = vec![
+            Light {
+                intensity: 0.2,
+                light_type: LightType::Ambient,
+            },
+            Light {
+                intensity: 0.6,
+                light_type: LightType::Point { pos: [4, 1, 0].into() },
+            },
+            Light {
+                intensity: 0.2,
+                light_type: LightType::Directional { dir: [1, 4, 4].into() },
+            },
+        ];
+
+        let spheres = vec![
+            Sphere { center: [0.0, 0.0, 2.0].into(), radius: 0.5, color: Color { r: 255, g: 255, b: 255, a: 255 } },
+            Sphere { center: [3, 1, 10].into(), radius: 2.0, color: Color { r: 255, g: 0, b: 0, a: 255 } },
+        ];
+
+        // the view is 1 unit tall at a distance of 1
+        let camera = Camera {
+            projection: Projection::Perspective { fov_y: 2.0 * Num::atan(0.5) },
+            near: 1.0,
+            ..Camera::new()
+        };
+
+        Self { lights, spheres, camera }
+    }
+}
+
+/// Traces a ray through the center of every pixel of `canvas`, matching where
+/// `render_supersampled` puts a single sample.
+pub fn render(canvas: &mut Canvas, scene: &Scene) {
+    crate::profile_scope!("trace_rays");
+    let view = scene.camera.view((canvas.width(), canvas.height()));
+    for y in 0..canvas.height() {
+        for x in 0..canvas.width() {
+            let ray = view.ray((x as Num + 0.5, y as Num + 0.5));
+            let col = trace_ray(ray.origin, ray.direction, ray.t_min, ray.t_max, scene);
+            canvas.set((x, y), col);
+        }
+    }
+}
+
+/// Traces `settings.samples` rays per pixel of `canvas`, or fewer with adaptive
+/// refinement, see `gfx::sampling`. Returns the number of rays traced.
+pub fn render_supersampled(canvas: &mut Canvas, scene: &Scene, settings: &Supersampling) -> usize {
+    crate::profile_scope!("trace_rays");
+    let view = scene.camera.view((canvas.width(), canvas.height()));
+    sampling::render(canvas, settings, |point| {
+        let ray = view.ray(point);
+        trace_ray(ray.origin, ray.direction, ray.t_min, ray.t_max, scene)
+    })
+}
+
+/// Renders the next frame of `progressive` onto `canvas`, see `gfx::progressive`.
+/// Returns the number of rays traced.
+pub fn render_progressive(canvas: &mut Canvas, scene: &Scene, progressive: &mut Progressive) -> usize {
+    crate::profile_scope!("trace_rays");
+    let view = scene.camera.view((canvas.width(), canvas.height()));
+    progressive.render(canvas, |point| {
+        let ray = view.ray(point);
+        sampling::rgb(trace_ray(ray.origin, ray.direction, ray.t_min, ray.t_max, scene))
+    })
+}
+
+fn in_range(n: Num, range: std::ops::Range<Num>) -> bool {
+    range.contains(&n)
+}
+
+pub fn trace_ray(o: V3, d: V3, t_min: Num, t_max: Num, scene: &Scene) -> Color {
+    fn get_t_or(default: Num, intersection: &Option<(&Sphere, Num)>) -> Num {
+        intersection.map_or(default, |(_, t)| t)
+    }
+
+    let mut closest_intesection: Option<(&Sphere, Num)> = None;
+    for sphere in &scene.spheres {
+        for &t in &intersect_ray_sphere(o, d, sphere) {
+            if in_range(t, t_min..t_max) && t < get_t_or(Num::INFINITY, &closest_intesection) {
+                closest_intesection = Some((sphere, t));
+            }
+        }
+    }
+
+    if let Some((sphere, t)) = closest_intesection {
+        let p = o + t * d;
+        let n = p - sphere.center;
+        let n = n / len(n);
+        set_intensity(sphere.color, get_light_intensity(p, n, scene))
+    } else {
+        Color { r: 0, g: 0, b: 0, a: 255 }
+    }
+}
+
+fn intersect_ray_sphere(o: V3, d: V3, sphere: &Sphere) -> [Num; 2] {
+    // result is all possible t for a ray intersecting a sphere
+    // ray: p^ = o^ + t * d^
+    // sphere: |p^ - c^| = r
+    //           => dot(p^ - c^, p^ - c^) = r * r
+    //
+    // substitute p^ in sphere equation with it's value in p^ equation
+    // dot(o^ + t * d^ - c^, o^ + t * d^ - c^) = r * r
+    //
+    // let oc^ = o^ - c^
+    // in dot(oc^ + t * d^, oc^ + t * d^) = r * r
+    // => dot(oc^, oc^) + 2 * dot(oc^, t * d^) + dot(t * d^, t * d^) = r * r
+    // => t * t * dot(d^, d^) + t * 2 * dot(oc^, d^) + dot(oc^, oc^) - r * r = 0
+    // This is quadratic equation
+
+    let c = sphere.center;
+    let r = sphere.radius;
+    let oc = o - c;
+
+    let a = dot(d, d);
+    let b = 2.0 * dot(oc, d);
+    let c = dot(oc, oc) - r * r;
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        [Num::INFINITY; 2]
+    } else {
+        let t1 = (-b + discriminant.sqrt()) / (2.0 * a);
+        let t2 = (-b - discriminant.sqrt()) / (2.0 * a);
+        [t1, t2]
+    }
+}
+
+fn get_light_intensity(p: V3, n: V3, scene: &Scene) -> Num {
+    let mut i = 0.0;
+    for light in &scene.lights {
+        match light.light_type {
+            LightType::Ambient => {
+                i += light.intensity;
+            },
+            LightType::Point { pos: dir }
+                | LightType::Directional { dir } =>
+            {
+                let l = if let LightType::Point { .. } = light.light_type {
+                    dir - p
+                } else {
+                    dir
+                };
+
+                let n_dot_l = dot(n, l);
+                if n_dot_l > 0.0 {
+                    i += light.intensity * n_dot_l / (len(n) * len(l))
+                }
+            },
+        }
+    }
+    i
+}
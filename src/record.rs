@@ -0,0 +1,57 @@
+//! Recording sequences of frames into animations.
+//!
+//! ```no_run
+//! # use std::{fs::File, time::Duration};
+//! # use gfx::{canvas::Canvas, record::{GifRecorder, Recorder}};
+//! # fn render(_: &mut Canvas) {}
+//! # let (width, height) = (320, 240);
+//! # let mut canvas = Canvas::new(width, height).unwrap();
+//! let mut recorder = GifRecorder::new(File::create("demo.gif")?, width, height)?;
+//! for _ in 0..60 {
+//!     render(&mut canvas);
+//!     recorder.frame(&canvas, Duration::from_millis(40))?;
+//! }
+//! recorder.finish()?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+mod gif;
+mod y4m;
+
+pub use self::{gif::GifRecorder, y4m::Y4mRecorder};
+
+use std::{io, time::Duration};
+use crate::canvas::Canvas;
+
+pub trait Recorder {
+    /// Appends `canvas` shown for `delay`, until the next frame.
+    ///
+    /// Canvas must be of the size the recorder was created with.
+    fn frame(&mut self, canvas: &Canvas, delay: Duration) -> io::Result<()>;
+
+    /// Writes whatever the format needs at the end of the stream and flushes it.
+    /// No frames may be added after this.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+fn check_size(canvas: &Canvas, (width, height): (usize, usize)) -> io::Result<()> {
+    if (canvas.width(), canvas.height()) == (width, height) {
+        Ok(())
+    } else {
+        Err(invalid_input(format!(
+            "frame size {}x{} does not match recording size {}x{}",
+            canvas.width(),
+            canvas.height(),
+            width,
+            height,
+        )))
+    }
+}
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+fn finished() -> io::Error {
+    io::Error::other("recording is already finished")
+}
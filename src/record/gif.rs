@@ -0,0 +1,345 @@
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    io::{self, Write},
+    time::Duration,
+};
+use crate::canvas::{Canvas, Color};
+use super::Recorder;
+
+/// Animated GIF that loops forever.
+///
+/// Every frame gets its own 256 color palette, chosen by median cut.
+/// Frames with at most 256 distinct colors are stored exactly.
+/// Alpha is ignored, frames are opaque.
+///
+/// GIF delays are in hundredths of a second; rounding errors do not accumulate
+/// over frames. Most viewers show delays under 0.02s as 0.1s.
+pub struct GifRecorder<W: Write> {
+    w: W,
+    width: usize,
+    height: usize,
+    elapsed: Duration,
+    elapsed_centis: u64,
+    finished: bool,
+}
+
+impl<W: Write> GifRecorder<W> {
+    pub fn new(mut w: W, width: usize, height: usize) -> io::Result<Self> {
+        let too_big = || super::invalid_input(format!(
+            "GifRecorder::new. {}x{} is too big for GIF, max is 65535x65535",
+            width,
+            height,
+        ));
+        let w16 = u16::try_from(width).map_err(|_| too_big())?;
+        let h16 = u16::try_from(height).map_err(|_| too_big())?;
+
+        w.write_all(b"GIF89a")?;
+
+        // logical screen descriptor
+        w.write_all(&w16.to_le_bytes())?;
+        w.write_all(&h16.to_le_bytes())?;
+        w.write_all(&[
+            0, // no global color table, every frame has its own
+            0, // background color index
+            0, // no aspect ratio
+        ])?;
+
+        // NETSCAPE2.0 application extension, makes animation loop
+        w.write_all(&[0x21, 0xff, 11])?;
+        w.write_all(b"NETSCAPE2.0")?;
+        w.write_all(&[3, 1])?;
+        w.write_all(&0u16.to_le_bytes())?; // loop forever
+        w.write_all(&[0])?;
+
+        Ok(Self {
+            w,
+            width,
+            height,
+            elapsed: Duration::default(),
+            elapsed_centis: 0,
+            finished: false,
+        })
+    }
+}
+
+impl<W: Write> Recorder for GifRecorder<W> {
+    fn frame(&mut self, canvas: &Canvas, delay: Duration) -> io::Result<()> {
+        if self.finished {
+            return Err(super::finished());
+        }
+        super::check_size(canvas, (self.width, self.height))?;
+
+        self.elapsed += delay;
+        let elapsed_centis = (self.elapsed.as_millis() as u64 + 5) / 10;
+        let delay_centis = u16::try_from(elapsed_centis - self.elapsed_centis).unwrap_or(u16::MAX);
+        self.elapsed_centis += delay_centis as u64;
+
+        let (palette, indices) = quantize(canvas);
+
+        // graphic control extension
+        self.w.write_all(&[0x21, 0xf9, 4])?;
+        self.w.write_all(&[
+            1 << 2, // disposal method: do not dispose, no transparency
+        ])?;
+        self.w.write_all(&delay_centis.to_le_bytes())?;
+        self.w.write_all(&[0, 0])?; // transparent color index, terminator
+
+        // image descriptor
+        self.w.write_all(&[0x2c])?;
+        self.w.write_all(&0u16.to_le_bytes())?; // left
+        self.w.write_all(&0u16.to_le_bytes())?; // top
+        self.w.write_all(&(self.width as u16).to_le_bytes())?;
+        self.w.write_all(&(self.height as u16).to_le_bytes())?;
+        self.w.write_all(&[
+            0x80 | 7, // local color table of 2^(7 + 1) colors
+        ])?;
+
+        let mut table = [0; 256 * 3];
+        for (entry, c) in table.chunks_mut(3).zip(&palette) {
+            entry.copy_from_slice(&[c.r, c.g, c.b]);
+        }
+        self.w.write_all(&table)?;
+
+        self.w.write_all(&[MIN_CODE_SIZE])?;
+        for block in lzw_encode(&indices).chunks(255) {
+            self.w.write_all(&[block.len() as u8])?;
+            self.w.write_all(block)?;
+        }
+        self.w.write_all(&[0])
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if !self.finished {
+            self.finished = true;
+            self.w.write_all(&[0x3b])?;
+        }
+        self.w.flush()
+    }
+}
+
+/// Returns palette of at most 256 colors and palette index of every pixel.
+fn quantize(canvas: &Canvas) -> (Vec<Color>, Vec<u8>) {
+    fn rgb(c: Color) -> u32 {
+        (c.r as u32) << 16 | (c.g as u32) << 8 | c.b as u32
+    }
+
+    let mut exact = HashMap::new();
+    for &c in canvas.pixels() {
+        let next = exact.len();
+        exact.entry(rgb(c)).or_insert(next);
+        if exact.len() > 256 {
+            break;
+        }
+    }
+
+    if exact.len() <= 256 {
+        let mut palette = vec![Color { r: 0, g: 0, b: 0, a: 255 }; exact.len()];
+        for (&rgb, &i) in &exact {
+            palette[i] = Color { r: (rgb >> 16) as u8, g: (rgb >> 8) as u8, b: rgb as u8, a: 255 };
+        }
+        let indices = canvas.pixels().iter().map(|&c| exact[&rgb(c)] as u8).collect();
+        return (palette, indices);
+    }
+
+    let palette = median_cut(canvas, 256);
+
+    // nearest palette color for every 5-bit-per-channel cell, filled on demand
+    let mut nearest = vec![u16::MAX; 1 << 15];
+    let indices = canvas.pixels()
+        .iter()
+        .map(|&c| {
+            let cell = cell(c);
+            if nearest[cell] == u16::MAX {
+                nearest[cell] = nearest_color(&palette, c) as u16;
+            }
+            nearest[cell] as u8
+        })
+        .collect();
+
+    (palette, indices)
+}
+
+/// Histogram cell index of a color with channels truncated to 5 bits.
+fn cell(c: Color) -> usize {
+    (c.r as usize >> 3) << 10 | (c.g as usize >> 3) << 5 | c.b as usize >> 3
+}
+
+struct ColorBox {
+    /// Histogram cells inside the box together with their pixel count.
+    cells: Vec<(usize, u32)>,
+}
+
+impl ColorBox {
+    fn channel(cell: usize, channel: usize) -> usize {
+        cell >> (10 - 5 * channel) & 0x1f
+    }
+
+    /// Longest channel and its length.
+    fn longest_channel(&self) -> (usize, usize) {
+        (0..3)
+            .map(|ch| {
+                let min = self.cells.iter().map(|&(cell, _)| Self::channel(cell, ch)).min().unwrap();
+                let max = self.cells.iter().map(|&(cell, _)| Self::channel(cell, ch)).max().unwrap();
+                (ch, max - min)
+            })
+            .max_by_key(|&(_, len)| len)
+            .unwrap()
+    }
+
+    /// Splits along the longest channel so both halves cover about the same number of pixels.
+    fn split(mut self) -> (ColorBox, ColorBox) {
+        let (ch, _) = self.longest_channel();
+        self.cells.sort_unstable_by_key(|&(cell, _)| Self::channel(cell, ch));
+
+        let total: u64 = self.cells.iter().map(|&(_, count)| count as u64).sum();
+        let mut acc = 0;
+        let mut median = self.cells.len() - 1;
+        for (i, &(_, count)) in self.cells.iter().enumerate() {
+            acc += count as u64;
+            if acc * 2 >= total {
+                median = i;
+                break;
+            }
+        }
+        let at = (median + 1).clamp(1, self.cells.len() - 1);
+
+        let rest = self.cells.split_off(at);
+        (self, ColorBox { cells: rest })
+    }
+}
+
+fn median_cut(canvas: &Canvas, colors: usize) -> Vec<Color> {
+    let mut counts = vec![0u32; 1 << 15];
+    let mut sums = vec![[0u64; 3]; 1 << 15];
+    for &c in canvas.pixels() {
+        let cell = cell(c);
+        counts[cell] += 1;
+        sums[cell][0] += c.r as u64;
+        sums[cell][1] += c.g as u64;
+        sums[cell][2] += c.b as u64;
+    }
+
+    let cells = counts.iter().enumerate().filter(|&(_, &n)| n > 0).map(|(cell, &n)| (cell, n)).collect();
+    let mut boxes = vec![ColorBox { cells }];
+    while boxes.len() < colors {
+        let splittable = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.cells.len() > 1)
+            .max_by_key(|(_, b)| b.longest_channel().1 as u64 * b.cells.iter().map(|&(_, n)| n as u64).sum::<u64>())
+            .map(|(i, _)| i);
+
+        match splittable {
+            Some(i) => {
+                let (a, b) = boxes.swap_remove(i).split();
+                boxes.push(a);
+                boxes.push(b);
+            },
+            None => break,
+        }
+    }
+
+    boxes
+        .iter()
+        .map(|b| {
+            let n: u64 = b.cells.iter().map(|&(cell, _)| counts[cell] as u64).sum();
+            let mean = |ch: usize| (b.cells.iter().map(|&(cell, _)| sums[cell][ch]).sum::<u64>() / n) as u8;
+            Color { r: mean(0), g: mean(1), b: mean(2), a: 255 }
+        })
+        .collect()
+}
+
+fn nearest_color(palette: &[Color], c: Color) -> usize {
+    let distance = |p: &Color| {
+        let dr = p.r as i32 - c.r as i32;
+        let dg = p.g as i32 - c.g as i32;
+        let db = p.b as i32 - c.b as i32;
+        dr * dr + dg * dg + db * db
+    };
+
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|&(_, p)| distance(p))
+        .map(|(i, _)| i)
+        .unwrap()
+}
+
+const MIN_CODE_SIZE: u8 = 8;
+const MAX_CODE_SIZE: u8 = 12;
+
+/// Variable code size LZW, as GIF wants it.
+fn lzw_encode(indices: &[u8]) -> Vec<u8> {
+    let clear = 1u16 << MIN_CODE_SIZE;
+    let end = clear + 1;
+
+    let mut out = BitWriter::default();
+    let mut dictionary = HashMap::<(u16, u8), u16>::new();
+    let mut code_size = MIN_CODE_SIZE + 1;
+    let mut next = end + 1;
+
+    out.write(clear, code_size);
+
+    let mut iter = indices.iter();
+    let mut prefix = match iter.next() {
+        Some(&first) => first as u16,
+        None => {
+            out.write(end, code_size);
+            return out.finish();
+        },
+    };
+
+    for &index in iter {
+        if let Some(&code) = dictionary.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+
+        out.write(prefix, code_size);
+        if next < 1 << MAX_CODE_SIZE {
+            dictionary.insert((prefix, index), next);
+            if next == 1 << code_size {
+                code_size += 1;
+            }
+            next += 1;
+        } else {
+            out.write(clear, code_size);
+            dictionary.clear();
+            code_size = MIN_CODE_SIZE + 1;
+            next = end + 1;
+        }
+        prefix = index as u16;
+    }
+
+    out.write(prefix, code_size);
+    out.write(end, code_size);
+    out.finish()
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u32,
+    bits: u8,
+}
+
+impl BitWriter {
+    /// Appends lowest `size` bits of `code`, least significant bit first.
+    fn write(&mut self, code: u16, size: u8) {
+        self.acc |= (code as u32) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.acc as u8);
+            self.acc >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.acc as u8);
+        }
+        self.bytes
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+use crate::canvas::{Canvas, Color};
+use super::Recorder;
+
+/// Uncompressed YUV4MPEG2 stream with 4:4:4 BT.601 limited range color,
+/// which ffmpeg reads directly:
+///
+/// `ffmpeg -i demo.y4m -pix_fmt yuv420p demo.mp4`
+///
+/// Y4M has a constant frame rate, so a frame is repeated as many times as
+/// its delay lasts, and dropped if it does not last until the next frame
+/// is due. Alpha is ignored.
+pub struct Y4mRecorder<W: Write> {
+    w: W,
+    width: usize,
+    height: usize,
+    fps: u32,
+    elapsed: Duration,
+    frames_written: u64,
+    planes: Vec<u8>,
+    finished: bool,
+}
+
+impl<W: Write> Y4mRecorder<W> {
+    pub fn new(mut w: W, width: usize, height: usize, fps: u32) -> io::Result<Self> {
+        if width == 0 || height == 0 || fps == 0 {
+            return Err(super::invalid_input(format!(
+                "Y4mRecorder::new. Invalid size {}x{} or frame rate {}",
+                width,
+                height,
+                fps,
+            )));
+        }
+
+        writeln!(w, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444 XCOLORRANGE=LIMITED", width, height, fps)?;
+
+        Ok(Self {
+            w,
+            width,
+            height,
+            fps,
+            elapsed: Duration::default(),
+            frames_written: 0,
+            planes: Vec::with_capacity(width * height * 3),
+            finished: false,
+        })
+    }
+}
+
+impl<W: Write> Recorder for Y4mRecorder<W> {
+    fn frame(&mut self, canvas: &Canvas, delay: Duration) -> io::Result<()> {
+        if self.finished {
+            return Err(super::finished());
+        }
+        super::check_size(canvas, (self.width, self.height))?;
+
+        self.elapsed += delay;
+        let due = (self.elapsed.as_secs_f64() * self.fps as f64).round() as u64;
+        if due <= self.frames_written {
+            return Ok(());
+        }
+
+        self.planes.clear();
+        let pixels = canvas.pixels();
+        // 8-bit fixed point BT.601
+        let (r, g, b) = (|c: &Color| c.r as i32, |c: &Color| c.g as i32, |c: &Color| c.b as i32);
+        self.planes.extend(pixels.iter().map(|c| {
+            (((66 * r(c) + 129 * g(c) + 25 * b(c) + 128) >> 8) + 16) as u8
+        }));
+        self.planes.extend(pixels.iter().map(|c| {
+            (((-38 * r(c) - 74 * g(c) + 112 * b(c) + 128) >> 8) + 128) as u8
+        }));
+        self.planes.extend(pixels.iter().map(|c| {
+            (((112 * r(c) - 94 * g(c) - 18 * b(c) + 128) >> 8) + 128) as u8
+        }));
+
+        while self.frames_written < due {
+            self.w.write_all(b"FRAME\n")?;
+            self.w.write_all(&self.planes)?;
+            self.frames_written += 1;
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.finished = true;
+        self.w.flush()
+    }
+}
@@ -0,0 +1,197 @@
+//! What to do with the canvas when the window is resized.
+//!
+//! `ResizePolicy` decides the size of the canvas and where it is shown in the window,
+//! `Presenter` applies that on any backend: it scales the canvas into a frame of the
+//! window's size and maps cursor positions back into canvas pixels.
+
+use crate::{
+    backend::Backend,
+    canvas::{Canvas, Color, Rect},
+    input::Event,
+    Error,
+};
+
+/// Color of the bars around a canvas that does not fill the window.
+const BACKGROUND: Color = Color { r: 0, g: 0, b: 0, a: 255 };
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResizePolicy {
+    /// Canvas is reallocated at the size of the window and shown unscaled.
+    Reallocate,
+    /// Canvas keeps its size and is scaled by the largest whole factor that fits,
+    /// for pixel art. Centered, cropped if the window is smaller than the canvas.
+    IntegerScale,
+    /// Canvas keeps its size and is scaled to fit the window, keeping its aspect ratio.
+    Letterbox,
+}
+
+impl ResizePolicy {
+    /// Size the canvas should have in a window of `window`, given it is `canvas` now.
+    /// Minimized windows report a size of zero, the canvas is kept then.
+    pub fn canvas_size(self, canvas: (usize, usize), window: (usize, usize)) -> (usize, usize) {
+        match self {
+            Self::Reallocate if window.0 > 0 && window.1 > 0 => window,
+            _ => canvas,
+        }
+    }
+
+    /// Where a canvas of `canvas` size is shown in a window of `window` size.
+    pub fn viewport(self, canvas: (usize, usize), window: (usize, usize)) -> Viewport {
+        let (canvas_width, canvas_height) = canvas;
+        let (window_width, window_height) = window;
+        if canvas_width == 0 || canvas_height == 0 || window_width == 0 || window_height == 0 {
+            return Viewport { rect: Rect::default(), canvas };
+        }
+
+        let size = match self {
+            Self::Reallocate => canvas,
+            Self::IntegerScale => {
+                let factor = (window_width / canvas_width).min(window_height / canvas_height).max(1);
+                (canvas_width * factor, canvas_height * factor)
+            },
+            // compares the aspect ratios without dividing, the size along the other axis is rounded
+            Self::Letterbox if window_width * canvas_height <= window_height * canvas_width => {
+                (window_width, ((window_width * canvas_height + canvas_width / 2) / canvas_width).max(1))
+            },
+            Self::Letterbox => {
+                (((window_height * canvas_width + canvas_height / 2) / canvas_height).max(1), window_height)
+            },
+        };
+        let position = match self {
+            Self::Reallocate => (0, 0),
+            _ => (
+                (window_width as isize - size.0 as isize) / 2,
+                (window_height as isize - size.1 as isize) / 2,
+            ),
+        };
+        Viewport { rect: Rect::new(position, size), canvas }
+    }
+}
+
+/// Placement of a canvas in a window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    /// Area of the window the canvas is scaled to, may reach outside of it.
+    pub rect: Rect,
+    /// Size of the canvas.
+    pub canvas: (usize, usize),
+}
+
+impl Viewport {
+    /// Canvas pixel under a point of the window, outside the canvas for points outside `rect`.
+    pub fn to_canvas(&self, (x, y): (isize, isize)) -> (isize, isize) {
+        if self.rect.is_empty() {
+            return (x, y);
+        }
+        (
+            ((x - self.rect.x) * self.canvas.0 as isize).div_euclid(self.rect.width as isize),
+            ((y - self.rect.y) * self.canvas.1 as isize).div_euclid(self.rect.height as isize),
+        )
+    }
+
+    /// Top left corner of a canvas pixel in the window.
+    pub fn to_window(&self, (x, y): (isize, isize)) -> (isize, isize) {
+        if self.rect.is_empty() {
+            return (x, y);
+        }
+        (
+            self.rect.x + (x * self.rect.width as isize).div_euclid(self.canvas.0 as isize),
+            self.rect.y + (y * self.rect.height as isize).div_euclid(self.canvas.1 as isize),
+        )
+    }
+}
+
+/// Draws `canvas` scaled to `viewport.rect` of `target` by nearest neighbour,
+/// and fills the rest of `target` with black.
+pub fn blit(canvas: &Canvas, viewport: &Viewport, target: &mut Canvas) {
+    let rect = viewport.rect;
+    // source column of every target column, `None` outside the canvas
+    let columns: Vec<Option<usize>> = (0..target.width() as isize)
+        .map(|x| {
+            if x < rect.x || x >= rect.right() {
+                return None;
+            }
+            Some(((x - rect.x) as usize * canvas.width() / rect.width).min(canvas.width() - 1))
+        })
+        .collect();
+
+    for y in 0..target.height() {
+        let row = y as isize;
+        let source_row = if row < rect.y || row >= rect.bottom() {
+            None
+        } else {
+            Some(((row - rect.y) as usize * canvas.height() / rect.height).min(canvas.height() - 1))
+        };
+        for (x, column) in columns.iter().enumerate() {
+            let color = match (column, source_row) {
+                (Some(column), Some(source_row)) => canvas.get((*column, source_row)),
+                _ => BACKGROUND,
+            };
+            target.set((x, y), color);
+        }
+    }
+}
+
+/// Presents canvases on a backend according to a `ResizePolicy`.
+pub struct Presenter {
+    pub policy: ResizePolicy,
+    /// Window sized frame the canvas is scaled into, when it does not fill the window as is.
+    frame: Option<Canvas>,
+    viewport: Viewport,
+}
+
+impl Presenter {
+    pub fn new(policy: ResizePolicy) -> Self {
+        Self {
+            policy,
+            frame: None,
+            viewport: Viewport { rect: Rect::default(), canvas: (0, 0) },
+        }
+    }
+
+    /// Reallocates `canvas` if the policy wants another size for a window of `window`.
+    /// Returns whether it did, the new canvas is black.
+    pub fn fit(&self, canvas: &mut Canvas, window: (usize, usize)) -> Result<bool, Error> {
+        let size = self.policy.canvas_size((canvas.width(), canvas.height()), window);
+        if size == (canvas.width(), canvas.height()) {
+            return Ok(false);
+        }
+        *canvas = Canvas::new(size.0, size.1)?;
+        Ok(true)
+    }
+
+    /// Shows `canvas` on `backend`, scaled and placed by the policy.
+    pub fn present(&mut self, backend: &mut impl Backend, canvas: &Canvas) -> Result<(), Error> {
+        let window = backend.size();
+        self.viewport = self.policy.viewport((canvas.width(), canvas.height()), window);
+        if self.viewport.rect == Rect::new((0, 0), window) && window == (canvas.width(), canvas.height()) {
+            return backend.present(canvas);
+        }
+        if window.0 == 0 || window.1 == 0 || self.viewport.rect.is_empty() {
+            return Ok(());
+        }
+
+        if self.frame.as_ref().map(|frame| (frame.width(), frame.height())) != Some(window) {
+            self.frame = Some(Canvas::new(window.0, window.1)?);
+        }
+        let frame = self.frame.as_mut().unwrap();
+        blit(canvas, &self.viewport, frame);
+        backend.present(frame)
+    }
+
+    /// Viewport of the last presented canvas.
+    pub fn viewport(&self) -> &Viewport {
+        &self.viewport
+    }
+
+    /// Turns cursor positions in `events` from window into canvas pixels, as of the last present.
+    pub fn map_events(&self, events: &mut [Event]) {
+        for event in events {
+            if let Event::MouseMove { x, y } = event {
+                let (canvas_x, canvas_y) = self.viewport.to_canvas((*x, *y));
+                *x = canvas_x;
+                *y = canvas_y;
+            }
+        }
+    }
+}
@@ -0,0 +1,421 @@
+//! Antialiasing by tracing several samples per pixel.
+//!
+//! `Pattern` places the samples in a pixel, `Filter` weights them by their distance
+//! to the centers of the pixels around, and `render` puts the two together for any
+//! function from a point on the canvas to a color. With `Adaptive`, every pixel gets
+//! a few samples first and only those that differ from a neighbor get the rest:
+//!
+//! ```no_run
+//! # use gfx::{canvas::Canvas, raytracer::{self, Scene}, sampling::{Adaptive, Filter, Pattern, Supersampling}};
+//! # let mut canvas = Canvas::new(1280, 720).unwrap();
+//! let settings = Supersampling {
+//!     pattern: Pattern::Halton,
+//!     samples: 32,
+//!     filter: Filter::MITCHELL,
+//!     adaptive: Some(Adaptive { initial: 4, threshold: 16 }),
+//!     ..Supersampling::new()
+//! };
+//! raytracer::render_supersampled(&mut canvas, &Scene::demo(), &settings);
+//! ```
+
+use crate::{
+    canvas::{Canvas, Color},
+    math::Num,
+};
+
+/// Where samples go in a pixel, as offsets from its top left corner in `0..1`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// Centers of the cells of a square grid, in a random order.
+    Grid,
+    /// Random point in every cell of a square grid, in a random order.
+    Jittered,
+    /// Halton sequence in bases 2 and 3.
+    Halton,
+    /// First two dimensions of the Sobol sequence.
+    Sobol,
+}
+
+impl Pattern {
+    /// Samples a pixel gets when asked for `samples`. Grids use the largest square
+    /// number not above it, sequences exactly that many, and at least 1.
+    pub fn count(self, samples: usize) -> usize {
+        match self {
+            Self::Grid | Self::Jittered => grid_side(samples).pow(2),
+            Self::Halton | Self::Sobol => samples.max(1),
+        }
+    }
+
+    /// Sample `index` of `count` in the pixel that `rng` belongs to. Grids take the cell
+    /// `index % count` of an order shuffled for every pixel, so the first samples spread
+    /// over the pixel, sequences the point `index` shifted by the same random offset in
+    /// every pixel, so neighbors do not repeat one another's pattern.
+    pub(crate) fn point(self, index: usize, count: usize, rng: &PixelRng) -> (Num, Num) {
+        match self {
+            Self::Grid | Self::Jittered => {
+                let side = grid_side(count);
+                let cell = permute((index % (side * side)) as u32, (side * side) as u32, rng.scramble) as usize;
+                let (x, y) = if self == Self::Jittered {
+                    rng.jitter(index)
+                } else {
+                    (0.5, 0.5)
+                };
+                (((cell % side) as Num + x) / side as Num, ((cell / side) as Num + y) / side as Num)
+            },
+            Self::Halton => (
+                (radical_inverse(2, index) + rng.shift.0).fract(),
+                (radical_inverse(3, index) + rng.shift.1).fract(),
+            ),
+            Self::Sobol => (
+                (unit((index as u32).reverse_bits()) + rng.shift.0).fract(),
+                (unit(sobol_second(index as u32)) + rng.shift.1).fract(),
+            ),
+        }
+    }
+}
+
+fn grid_side(samples: usize) -> usize {
+    ((samples as f64).sqrt() as usize).max(1)
+}
+
+/// `index` in a permutation of `0..length` picked by `seed`, by Kensler's hash from
+/// "Correlated Multi-Jittered Sampling", retried until it falls in range.
+fn permute(mut index: u32, length: u32, seed: u32) -> u32 {
+    let mut mask = length.wrapping_sub(1);
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+    loop {
+        index ^= seed;
+        index = index.wrapping_mul(0xe170_893d);
+        index ^= seed >> 16;
+        index ^= (index & mask) >> 4;
+        index ^= seed >> 8;
+        index = index.wrapping_mul(0x0929_eb3f);
+        index ^= seed >> 23;
+        index ^= (index & mask) >> 1;
+        index = index.wrapping_mul(1 | seed >> 27);
+        index = index.wrapping_mul(0x6935_fa69);
+        index ^= (index & mask) >> 11;
+        index = index.wrapping_mul(0x74dc_b303);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0x9e50_1cc3);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0xc860_a3df);
+        index &= mask;
+        index ^= index >> 5;
+        if index < length {
+            return (index + seed) % length;
+        }
+    }
+}
+
+/// Digits of `index` in `base` mirrored around the point.
+fn radical_inverse(base: usize, mut index: usize) -> Num {
+    let mut inverse = 0.0;
+    let mut scale = 1.0;
+    while index > 0 {
+        scale /= base as f64;
+        inverse += (index % base) as f64 * scale;
+        index /= base;
+    }
+    inverse as Num
+}
+
+/// Second dimension of the Sobol sequence, by its direction numbers `v ^= v >> 1`.
+fn sobol_second(mut index: u32) -> u32 {
+    let mut result = 0;
+    let mut v = 1 << 31;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+/// `bits` as a fraction of 2^32, below 1 after rounding to `Num`.
+fn unit(bits: u32) -> Num {
+    (bits >> 8) as Num / (1 << 24) as Num
+}
+
+/// Reconstruction filter, weighting samples by their distance to the center of a pixel.
+/// Samples count towards every pixel within `radius` of them along both axes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    /// Same weight within `radius`, the average of the pixel's samples at 0.5.
+    Box { radius: Num },
+    /// Falls off linearly to zero at `radius`.
+    Tent { radius: Num },
+    /// `exp(-alpha * d * d)`, shifted down to reach zero at `radius`.
+    Gaussian { radius: Num, alpha: Num },
+    /// Mitchell-Netravali cubic with parameters `b` and `c` stretched over `radius`,
+    /// sharper than the others thanks to its negative lobes.
+    Mitchell { radius: Num, b: Num, c: Num },
+}
+
+impl Filter {
+    pub const BOX: Filter = Filter::Box { radius: 0.5 };
+    pub const TENT: Filter = Filter::Tent { radius: 1.0 };
+    pub const GAUSSIAN: Filter = Filter::Gaussian { radius: 1.5, alpha: 2.0 };
+    pub const MITCHELL: Filter = Filter::Mitchell { radius: 2.0, b: 1.0 / 3.0, c: 1.0 / 3.0 };
+
+    pub fn radius(self) -> Num {
+        match self {
+            Self::Box { radius }
+                | Self::Tent { radius }
+                | Self::Gaussian { radius, .. }
+                | Self::Mitchell { radius, .. } => radius,
+        }
+    }
+
+    /// Weight of a sample `(dx, dy)` away from the center of a pixel.
+    pub fn weight(self, (dx, dy): (Num, Num)) -> Num {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    fn weight_1d(self, d: Num) -> Num {
+        let d = d.abs();
+        if d > self.radius() {
+            return 0.0;
+        }
+        match self {
+            Self::Box { .. } => 1.0,
+            Self::Tent { radius } => 1.0 - d / radius,
+            Self::Gaussian { radius, alpha } => ((-alpha * d * d).exp() - (-alpha * radius * radius).exp()).max(0.0),
+            Self::Mitchell { radius, b, c } => {
+                let x = 2.0 * d / radius;
+                let polynomial = if x > 1.0 {
+                    (-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)
+                } else {
+                    (12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x + (6.0 - 2.0 * b)
+                };
+                polynomial / 6.0
+            },
+        }
+    }
+}
+
+/// Refinement of only the pixels that need it, see `Supersampling::adaptive`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Adaptive {
+    /// Samples every pixel gets first.
+    pub initial: usize,
+    /// Difference in any channel to one of the 8 neighbors above which a pixel gets
+    /// `Supersampling::samples` more.
+    pub threshold: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Supersampling {
+    pub pattern: Pattern,
+    /// Samples per pixel, see `Pattern::count`.
+    pub samples: usize,
+    pub filter: Filter,
+    /// `None` gives every pixel all `samples`.
+    pub adaptive: Option<Adaptive>,
+    /// Random numbers of jittering and sequence offsets, the same seed gives the same image.
+    pub seed: u64,
+}
+
+impl Default for Supersampling {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Supersampling {
+    /// 16 jittered samples per pixel with a Gaussian filter, no adaptive refinement.
+    pub fn new() -> Self {
+        Self {
+            pattern: Pattern::Jittered,
+            samples: 16,
+            filter: Filter::GAUSSIAN,
+            adaptive: None,
+            seed: 0,
+        }
+    }
+}
+
+/// Small, fast, seedable random number generator (PCG32). Not for cryptography.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+    increment: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self { state: 0, increment: (0xda3e_39cb_94b9_5bdb << 1) | 1 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    /// Uniform in `0..1`.
+    pub fn next_float(&mut self) -> Num {
+        unit(self.next_u32())
+    }
+}
+
+/// Random numbers of one pixel, the same whenever the pixel is sampled.
+pub(crate) struct PixelRng {
+    seed: u64,
+    /// Offset of sequences in the pixel.
+    shift: (Num, Num),
+    /// Picks the order grid cells are visited in.
+    scramble: u32,
+}
+
+impl PixelRng {
+    pub(crate) fn new(seed: u64, (x, y): (usize, usize)) -> Self {
+        let pixel = ((y as u64) << 32) | x as u64;
+        let seed = seed ^ pixel.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let mut rng = Rng::new(seed);
+        let shift = (rng.next_float(), rng.next_float());
+        Self { seed, shift, scramble: rng.next_u32() }
+    }
+
+    /// Offset of sample `index` in its grid cell, the same whichever frame takes it.
+    fn jitter(&self, index: usize) -> (Num, Num) {
+        let mut rng = Rng::new(self.seed ^ (index as u64).wrapping_mul(0xbf58_476d_1ce4_e5b9));
+        (rng.next_float(), rng.next_float())
+    }
+}
+
+/// Channels of `color` in `0..1`.
+pub(crate) fn rgb(color: Color) -> [Num; 3] {
+    [color.r as Num / 255.0, color.g as Num / 255.0, color.b as Num / 255.0]
+}
+
+/// Channels in `0..1` rounded to a color, clamped to its range.
+pub(crate) fn color([r, g, b]: [Num; 3]) -> Color {
+    let channel = |value: Num| (value * 255.0).round().clamp(0.0, 255.0) as u8;
+    Color { r: channel(r), g: channel(g), b: channel(b), a: 255 }
+}
+
+/// Weighted sums of the samples around every pixel.
+struct Film {
+    width: usize,
+    height: usize,
+    filter: Filter,
+    /// Red, green, blue and the sum of the weights, unclamped.
+    sums: Vec<[Num; 4]>,
+}
+
+impl Film {
+    fn new(width: usize, height: usize, filter: Filter) -> Self {
+        Self { width, height, filter, sums: vec![[0.0; 4]; width * height] }
+    }
+
+    /// Adds a sample at `(x, y)` in canvas pixels to the pixels whose centers are within the filter.
+    fn add(&mut self, (x, y): (Num, Num), [r, g, b]: [Num; 3]) {
+        let radius = self.filter.radius();
+        let range = |center: Num, size: usize| {
+            let first = (center - 0.5 - radius).ceil().max(0.0) as usize;
+            let last = ((center - 0.5 + radius).floor() as isize).min(size as isize - 1);
+            first..(last + 1).max(0) as usize
+        };
+        for py in range(y, self.height) {
+            for px in range(x, self.width) {
+                let weight = self.filter.weight((x - (px as Num + 0.5), y - (py as Num + 0.5)));
+                let sum = &mut self.sums[py * self.width + px];
+                sum[0] += weight * r;
+                sum[1] += weight * g;
+                sum[2] += weight * b;
+                sum[3] += weight;
+            }
+        }
+    }
+
+    fn resolve(&self, canvas: &mut Canvas) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let [r, g, b, weight] = self.sums[y * self.width + x];
+                let color = if weight.abs() < Num::EPSILON {
+                    Color { r: 0, g: 0, b: 0, a: 255 }
+                } else {
+                    color([r / weight, g / weight, b / weight])
+                };
+                canvas.set((x, y), color);
+            }
+        }
+    }
+}
+
+/// Fills `canvas` with the colors `sample` gives for points of it, in pixels from the top
+/// left corner as in `gfx::camera::View::ray`. Returns the number of samples taken.
+pub fn render(canvas: &mut Canvas, settings: &Supersampling, mut sample: impl FnMut((Num, Num)) -> Color) -> usize {
+    render_rgb(canvas, settings, |point| rgb(sample(point)))
+}
+
+/// `render` with the red, green and blue of every sample, from 0 to 1 on the canvas.
+/// Samples beyond that count fully in the average and only the result is clamped.
+pub fn render_rgb(canvas: &mut Canvas, settings: &Supersampling, mut sample: impl FnMut((Num, Num)) -> [Num; 3]) -> usize {
+    crate::profile_scope!("supersample");
+    let (width, height) = (canvas.width(), canvas.height());
+    let pattern = settings.pattern;
+    let mut film = Film::new(width, height, settings.filter);
+    let mut taken = 0;
+
+    let mut sample_pixel = |film: &mut Film, (x, y), indices: std::ops::Range<usize>, count| {
+        let rng = PixelRng::new(settings.seed, (x, y));
+        for index in indices {
+            let (dx, dy) = pattern.point(index, count, &rng);
+            let point = (x as Num + dx, y as Num + dy);
+            film.add(point, sample(point));
+        }
+    };
+
+    let initial = pattern.count(settings.adaptive.map_or(settings.samples, |adaptive| adaptive.initial));
+    for y in 0..height {
+        for x in 0..width {
+            sample_pixel(&mut film, (x, y), 0..initial, initial);
+        }
+    }
+    taken += width * height * initial;
+
+    if let Some(adaptive) = settings.adaptive {
+        film.resolve(canvas);
+        let refined = contrasting(canvas, adaptive.threshold);
+        let count = pattern.count(settings.samples);
+        for &(x, y) in &refined {
+            sample_pixel(&mut film, (x, y), initial..initial + count, count);
+        }
+        taken += refined.len() * count;
+    }
+    film.resolve(canvas);
+    taken
+}
+
+/// Pixels that differ from one of their 8 neighbors by more than `threshold` in a channel.
+pub fn contrasting(canvas: &Canvas, threshold: u8) -> Vec<(usize, usize)> {
+    let differs = |a: Color, b: Color| {
+        a.r.abs_diff(b.r) > threshold || a.g.abs_diff(b.g) > threshold || a.b.abs_diff(b.b) > threshold
+    };
+    let mut pixels = Vec::new();
+    for y in 0..canvas.height() {
+        for x in 0..canvas.width() {
+            let color = canvas.get((x, y));
+            let mut neighbors = (y.saturating_sub(1)..(y + 2).min(canvas.height()))
+                .flat_map(|ny| (x.saturating_sub(1)..(x + 2).min(canvas.width())).map(move |nx| (nx, ny)));
+            if neighbors.any(|neighbor| differs(color, canvas.get(neighbor))) {
+                pixels.push((x, y));
+            }
+        }
+    }
+    pixels
+}
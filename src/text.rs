@@ -0,0 +1,484 @@
+//! Text rendering with cached glyph coverage.
+//!
+//! Rasterizing glyphs from outlines is by far the most expensive part of
+//! drawing text, and overlays draw the same few glyphs every frame.
+//! `Font` keeps every glyph it has rasterized in an atlas, keyed by glyph,
+//! scale and subpixel offset, so redrawing text is a matter of copying
+//! coverage masks onto the canvas.
+//!
+//! `draw_text` blends text of any color over what is already on the canvas.
+//! Text is drawn with a `FontSet`, characters the first font lacks are taken
+//! from the fallback fonts that follow it.
+//!
+//! Setting `TextStyle::sdf` draws text from signed distance fields instead,
+//! which scale to any size from a single cached field per glyph and support
+//! outline, glow and shadow effects, at the cost of slightly softer small text.
+
+mod layout;
+mod sdf;
+
+pub use sdf::{FIELD_SIZE, FIELD_SPREAD, Glow, Outline, SdfEffects, Shadow};
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    convert::TryInto,
+    fmt,
+    io,
+    path::Path,
+};
+use rusttype::{GlyphId, Point, Scale, point};
+use crate::canvas::{Canvas, Color, Rect};
+
+/// Glyph positions are rounded to this fraction of a pixel.
+const SUBPIXEL_STEPS: u8 = 4;
+
+const ATLAS_WIDTH: usize = 1024;
+
+/// Atlas is cleared when it grows taller than this.
+const ATLAS_MAX_HEIGHT: usize = 4096;
+
+pub struct Font {
+    font: rusttype::Font<'static>,
+    cache: RefCell<GlyphCache>,
+    fields: RefCell<sdf::FieldCache>,
+}
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(io::Error),
+    /// Data is not a TrueType or OpenType font or collection.
+    Invalid,
+    NoSuchFace {
+        index: u32,
+        faces: u32,
+    },
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FontError::Io(e) => write!(f, "{}", e),
+            FontError::Invalid => write!(f, "not a TrueType or OpenType font"),
+            FontError::NoSuchFace { index, faces } => write!(
+                f,
+                "font has no face {}, it has {} face{}",
+                index,
+                faces,
+                if *faces == 1 { "" } else { "s" },
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FontError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FontError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FontError {
+    fn from(e: io::Error) -> Self {
+        FontError::Io(e)
+    }
+}
+
+impl Font {
+    pub fn try_from_bytes(bytes: &'static [u8]) -> Option<Self> {
+        rusttype::Font::try_from_bytes(bytes).map(Self::new)
+    }
+
+    pub fn try_from_vec(bytes: Vec<u8>) -> Option<Self> {
+        rusttype::Font::try_from_vec(bytes).map(Self::new)
+    }
+
+    /// Face `index` of a font collection (.ttc, .otc). Index 0 of a single font file is the font itself.
+    pub fn from_vec_and_index(bytes: Vec<u8>, index: u32) -> Result<Self, FontError> {
+        let faces = face_count(&bytes)?;
+        if index >= faces {
+            return Err(FontError::NoSuchFace { index, faces });
+        }
+        rusttype::Font::try_from_vec_and_index(bytes, index)
+            .map(Self::new)
+            .ok_or(FontError::Invalid)
+    }
+
+    /// Loads a TrueType or OpenType font file. Takes the first face of a collection.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FontError> {
+        Self::from_vec_and_index(std::fs::read(path)?, 0)
+    }
+
+    /// Loads every face of a font collection, or the only face of a single font file.
+    pub fn load_collection(path: impl AsRef<Path>) -> Result<Vec<Self>, FontError> {
+        let bytes = std::fs::read(path)?;
+        (0..face_count(&bytes)?)
+            .map(|index| Self::from_vec_and_index(bytes.clone(), index))
+            .collect()
+    }
+
+    fn new(font: rusttype::Font<'static>) -> Self {
+        Self {
+            font,
+            cache: RefCell::new(GlyphCache::new()),
+            fields: RefCell::new(sdf::FieldCache::new()),
+        }
+    }
+
+    pub fn rusttype(&self) -> &rusttype::Font<'static> {
+        &self.font
+    }
+
+    /// Whether the font has a glyph for `c`, other than the "missing glyph" box.
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.font.glyph(c).id() != GlyphId(0)
+    }
+
+    /// Number of glyph images currently cached.
+    pub fn cached_glyphs(&self) -> usize {
+        self.cache.borrow().entries.len()
+    }
+
+    /// Number of glyph distance fields currently cached.
+    pub fn cached_fields(&self) -> usize {
+        self.fields.borrow().entries.len()
+    }
+}
+
+/// Number of faces in a font collection, 1 for a single font.
+fn face_count(bytes: &[u8]) -> Result<u32, FontError> {
+    match bytes.get(..12) {
+        Some(header) if &header[..4] == b"ttcf" => Ok(u32::from_be_bytes(header[8..12].try_into().unwrap())),
+        Some(_) => Ok(1),
+        None => Err(FontError::Invalid),
+    }
+}
+
+/// Fonts tried in order for every character.
+///
+/// A character is drawn with the first font that has a glyph for it.
+/// Characters no font has are drawn as the missing glyph of the first font.
+/// Line height and baseline come from the first font.
+pub struct FontSet {
+    fonts: Vec<Font>,
+}
+
+impl FontSet {
+    pub fn new(primary: Font) -> Self {
+        Self { fonts: vec![primary] }
+    }
+
+    /// Adds a font tried after all the fonts already in the set.
+    pub fn push(&mut self, fallback: Font) {
+        self.fonts.push(fallback);
+    }
+
+    pub fn fonts(&self) -> &[Font] {
+        &self.fonts
+    }
+
+    /// Index of the font `c` is drawn with.
+    pub fn font_for(&self, c: char) -> usize {
+        self.fonts.iter().position(|font| font.has_glyph(c)).unwrap_or(0)
+    }
+}
+
+impl From<Font> for FontSet {
+    fn from(font: Font) -> Self {
+        Self::new(font)
+    }
+}
+
+/// Horizontal placement of lines relative to the position text is drawn at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    /// Lines start at the position.
+    Left,
+    /// Lines are centered on the position.
+    Center,
+    /// Lines end at the position.
+    Right,
+    /// Lines start at the position and are stretched to `max_width`, or to the
+    /// widest line if there is no limit. Last lines of paragraphs are not stretched.
+    Justify,
+}
+
+/// Which point of text is placed at the given position vertically.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VAlign {
+    /// Ascent line of the first line.
+    Top,
+    /// Baseline of the first line.
+    Baseline,
+    /// Descent line of the last line.
+    Bottom,
+}
+
+pub struct TextStyle<'a> {
+    pub fonts: &'a FontSet,
+    /// Height of a line from descent to ascent, in pixels.
+    pub size: f32,
+    pub color: Color,
+    pub align: Align,
+    pub valign: VAlign,
+    /// Lines longer than this are wrapped at spaces, or between characters
+    /// if a single word does not fit. Text is only broken at newlines if `None`
+    /// or NaN, negative widths wrap as much as 0.
+    pub max_width: Option<f32>,
+    /// Multiplier of the distance between baselines the font suggests.
+    pub line_spacing: f32,
+    /// Draw from distance fields with these effects instead of from glyph images.
+    pub sdf: Option<SdfEffects>,
+}
+
+impl<'a> TextStyle<'a> {
+    /// White, left and top aligned text without wrapping.
+    pub fn new(fonts: &'a FontSet, size: f32) -> Self {
+        Self {
+            fonts,
+            size,
+            color: Color { r: 255, g: 255, b: 255, a: 255 },
+            align: Align::Left,
+            valign: VAlign::Top,
+            max_width: None,
+            line_spacing: 1.0,
+            sdf: None,
+        }
+    }
+
+    fn layout(&self, text: &str) -> layout::Layout {
+        layout::layout(
+            self.fonts,
+            Scale::uniform(self.size),
+            text,
+            self.max_width,
+            self.align,
+            self.line_spacing,
+        )
+    }
+}
+
+/// Size of text laid out with `style`, without drawing it. Width is of the
+/// widest line, height is from the top of the first line to the bottom of the last.
+pub fn measure(text: &str, style: &TextStyle) -> (f32, f32) {
+    let layout = style.layout(text);
+    (layout.width, layout.height)
+}
+
+/// Draws text, blending it over the canvas. Text is broken into lines at
+/// newlines and wrapped to `style.max_width`. `position` is the point the
+/// text is aligned to. Parts outside the canvas are clipped.
+///
+/// Returns bounding box of the drawn pixels, empty if nothing was drawn.
+pub fn draw_text(canvas: &mut Canvas, text: &str, position: (f32, f32), style: &TextStyle) -> Rect {
+    let scale = Scale::uniform(style.size);
+    let layout = style.layout(text);
+
+    let first_baseline = match style.valign {
+        VAlign::Top => position.1 + layout.ascent,
+        VAlign::Baseline => position.1,
+        VAlign::Bottom => position.1 - layout.height + layout.ascent,
+    };
+    let glyphs = || layout.lines.iter().flat_map(move |line| {
+        let x = match style.align {
+            Align::Left | Align::Justify => position.0,
+            Align::Center => position.0 - line.width / 2.0,
+            Align::Right => position.0 - line.width,
+        };
+        let baseline = first_baseline + line.baseline;
+        line.glyphs.iter().map(move |&(font, id, caret)| (font, id, point(x + caret, baseline)))
+    });
+
+    let mut drawn = Rect::default();
+    match &style.sdf {
+        None => {
+            for (font, id, origin) in glyphs() {
+                let font = &style.fonts.fonts[font];
+                let mut cache = font.cache.borrow_mut();
+                let (entry, position) = cache.get(&font.font, id, scale, origin);
+                let rect = cache.atlas.blit(canvas, entry, position, style.color);
+                drawn = drawn.union(&rect);
+            }
+        },
+        Some(effects) => {
+            // every layer of all glyphs before the next one, so shadows
+            // and glows do not cover neighbouring glyphs
+            for &layer in &sdf::Layer::BACK_TO_FRONT {
+                for (font, id, origin) in glyphs() {
+                    let font = &style.fonts.fonts[font];
+                    let mut fields = font.fields.borrow_mut();
+                    let field = fields.get(&font.font, id);
+                    let rect = fields.draw(canvas, field, origin, layer, style, effects);
+                    drawn = drawn.union(&rect);
+                }
+            }
+        },
+    }
+    drawn
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    glyph: GlyphId,
+    /// `f32::to_bits` of scale, so exactly equal scales share cache entries.
+    scale: (u32, u32),
+    subpixel: (u8, u8),
+}
+
+/// Location of glyph coverage in the atlas.
+#[derive(Clone, Copy)]
+struct Entry {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    /// Offset of the top left corner of coverage from the glyph origin.
+    offset: (i32, i32),
+}
+
+struct GlyphCache {
+    entries: HashMap<GlyphKey, Entry>,
+    atlas: Atlas,
+}
+
+impl GlyphCache {
+    fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            atlas: Atlas::new(ATLAS_WIDTH),
+        }
+    }
+
+    /// Returns cached coverage of the glyph drawn at `position`,
+    /// together with the canvas position of its top left corner.
+    fn get(&mut self, font: &rusttype::Font<'static>, glyph: GlyphId, scale: Scale, position: Point<f32>) -> (Entry, (i32, i32)) {
+        let steps = SUBPIXEL_STEPS as f32;
+        let snap = |x: f32| -> (i32, u8) {
+            let snapped = (x * steps).round();
+            let whole = (snapped / steps).floor();
+            (whole as i32, (snapped - whole * steps) as u8)
+        };
+        let (x, subpixel_x) = snap(position.x);
+        let (y, subpixel_y) = snap(position.y);
+
+        let key = GlyphKey {
+            glyph,
+            scale: (scale.x.to_bits(), scale.y.to_bits()),
+            subpixel: (subpixel_x, subpixel_y),
+        };
+
+        let entry = match self.entries.get(&key) {
+            Some(&entry) => entry,
+            None => {
+                let positioned = font
+                    .glyph(glyph)
+                    .scaled(scale)
+                    .positioned(point(subpixel_x as f32 / steps, subpixel_y as f32 / steps));
+
+                let entry = match positioned.pixel_bounding_box() {
+                    Some(bbox) => {
+                        let width = bbox.width() as usize;
+                        let height = bbox.height() as usize;
+                        if width > self.atlas.width || self.atlas.height() + height > ATLAS_MAX_HEIGHT {
+                            self.entries.clear();
+                            self.atlas = Atlas::new(width.max(ATLAS_WIDTH));
+                        }
+
+                        let (ax, ay) = self.atlas.allocate(width, height);
+                        let atlas = &mut self.atlas;
+                        positioned.draw(|gx, gy, v| {
+                            atlas.set((ax + gx as usize, ay + gy as usize), (v * 255.0).round() as u8);
+                        });
+
+                        Entry { x: ax, y: ay, width, height, offset: (bbox.min.x, bbox.min.y) }
+                    },
+                    // whitespace
+                    None => Entry { x: 0, y: 0, width: 0, height: 0, offset: (0, 0) },
+                };
+                self.entries.insert(key, entry);
+                entry
+            },
+        };
+
+        (entry, (x + entry.offset.0, y + entry.offset.1))
+    }
+}
+
+/// Single channel coverage image packed with glyphs in rows ("shelves").
+struct Atlas {
+    width: usize,
+    data: Vec<u8>,
+    shelf_y: usize,
+    shelf_height: usize,
+    shelf_x: usize,
+}
+
+impl Atlas {
+    fn new(width: usize) -> Self {
+        Self {
+            width,
+            data: Vec::new(),
+            shelf_y: 0,
+            shelf_height: 0,
+            shelf_x: 0,
+        }
+    }
+
+    fn height(&self) -> usize {
+        self.data.len() / self.width
+    }
+
+    /// Reserves `width` x `height` area and returns its top left corner.
+    fn allocate(&mut self, width: usize, height: usize) -> (usize, usize) {
+        if self.shelf_x + width > self.width {
+            self.shelf_y += self.shelf_height;
+            self.shelf_x = 0;
+            self.shelf_height = 0;
+        }
+
+        let pos = (self.shelf_x, self.shelf_y);
+        self.shelf_x += width;
+        self.shelf_height = self.shelf_height.max(height);
+
+        let rows = self.shelf_y + self.shelf_height;
+        if rows > self.height() {
+            self.data.resize(rows * self.width, 0);
+        }
+
+        pos
+    }
+
+    fn set(&mut self, (x, y): (usize, usize), coverage: u8) {
+        self.data[x + y * self.width] = coverage;
+    }
+
+    /// Blends `color` onto canvas at `(x, y)` with coverage of `entry` as opacity,
+    /// clipping to canvas bounds. Returns bounding box of the pixels it touched.
+    fn blit(&self, canvas: &mut Canvas, entry: Entry, (x, y): (i32, i32), color: Color) -> Rect {
+        let rect = Rect::new((x as isize, y as isize), (entry.width, entry.height));
+        let clipped = rect.intersection(&canvas.rect());
+
+        let (mut min, mut max) = ((isize::MAX, isize::MAX), (isize::MIN, isize::MIN));
+        for cy in clipped.y..clipped.bottom() {
+            let row = (entry.y + (cy - rect.y) as usize) * self.width + entry.x;
+            for cx in clipped.x..clipped.right() {
+                let coverage = self.data[row + (cx - rect.x) as usize];
+                if coverage == 0 {
+                    continue;
+                }
+
+                let a = (color.a as u32 * coverage as u32 + 127) / 255;
+                canvas.blend((cx as usize, cy as usize), Color { a: a as u8, ..color });
+                min = (min.0.min(cx), min.1.min(cy));
+                max = (max.0.max(cx + 1), max.1.max(cy + 1));
+            }
+        }
+        if min.0 < max.0 {
+            Rect::from_corners(min, max)
+        } else {
+            Rect::default()
+        }
+    }
+}
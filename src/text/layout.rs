@@ -0,0 +1,160 @@
+//! Breaking text into lines and placing glyphs on them.
+
+use rusttype::{GlyphId, Scale};
+use super::{Align, FontSet};
+
+pub(super) struct Layout {
+    pub lines: Vec<Line>,
+    /// Width of the widest line.
+    pub width: f32,
+    /// From the ascent line of the first line to the descent line of the last one.
+    pub height: f32,
+    /// Distance from the top of the text to the first baseline.
+    pub ascent: f32,
+}
+
+pub(super) struct Line {
+    /// Index of the font in the set, glyph and its offset from the start of the line.
+    pub glyphs: Vec<(usize, GlyphId, f32)>,
+    pub width: f32,
+    /// Offset of the baseline from the first baseline.
+    pub baseline: f32,
+}
+
+pub(super) fn layout(
+    fonts: &FontSet,
+    scale: Scale,
+    text: &str,
+    max_width: Option<f32>,
+    align: Align,
+    line_spacing: f32,
+) -> Layout {
+    let v_metrics = fonts.fonts[0].font.v_metrics(scale);
+    let line_height = (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap) * line_spacing;
+
+    let mut breaks = Vec::new();
+    for paragraph in text.split('\n') {
+        // Windows line endings
+        let paragraph = paragraph.strip_suffix('\r').unwrap_or(paragraph);
+        wrap(fonts, scale, paragraph, max_width, &mut breaks);
+    }
+
+    let mut lines: Vec<Line> = breaks
+        .iter()
+        .enumerate()
+        .map(|(i, &(text, _))| Line {
+            baseline: i as f32 * line_height,
+            ..layout_line(fonts, scale, text, 0.0)
+        })
+        .collect();
+
+    let widest = lines.iter().map(|line| line.width).fold(0.0, f32::max);
+    if align == Align::Justify {
+        let target = max_width.unwrap_or(widest);
+        for (line, &(text, ends_paragraph)) in lines.iter_mut().zip(&breaks) {
+            let spaces = text.chars().filter(|&c| c == ' ').count();
+            if !ends_paragraph && spaces > 0 && line.width < target {
+                let extra = (target - line.width) / spaces as f32;
+                *line = Line {
+                    baseline: line.baseline,
+                    ..layout_line(fonts, scale, text, extra)
+                };
+            }
+        }
+    }
+
+    let height = match lines.len() {
+        0 => 0.0,
+        n => (n - 1) as f32 * line_height + v_metrics.ascent - v_metrics.descent,
+    };
+    Layout {
+        width: lines.iter().map(|line| line.width).fold(0.0, f32::max),
+        height,
+        ascent: v_metrics.ascent,
+        lines,
+    }
+}
+
+/// Appends lines of a paragraph, each with a flag telling whether it ends the paragraph.
+///
+/// Lines are broken at spaces, which are dropped. Words that are too long
+/// for a line on their own are broken between characters.
+fn wrap<'t>(fonts: &FontSet, scale: Scale, paragraph: &'t str, max_width: Option<f32>, lines: &mut Vec<(&'t str, bool)>) {
+    let max_width = match max_width {
+        // a negative limit wraps as much as 0, NaN as much as no limit
+        Some(max_width) if !max_width.is_nan() => max_width.max(0.0),
+        _ => {
+            lines.push((paragraph, true));
+            return;
+        },
+    };
+    let fits = |text: &str| layout_line(fonts, scale, text, 0.0).width <= max_width;
+
+    let mut rest = paragraph;
+    loop {
+        // empty paragraphs have nothing to break at
+        if rest.is_empty() || fits(rest) {
+            lines.push((rest, true));
+            return;
+        }
+
+        let mut end = None;
+        let mut prev_whitespace = true;
+        for (i, c) in rest.char_indices() {
+            if c.is_whitespace() && !prev_whitespace {
+                if fits(&rest[..i]) {
+                    end = Some(i);
+                } else {
+                    break;
+                }
+            }
+            prev_whitespace = c.is_whitespace();
+        }
+
+        let end = end.unwrap_or_else(|| {
+            // no word fits, break the first one, keeping at least one character per line
+            let mut ends = rest.char_indices().map(|(i, c)| i + c.len_utf8());
+            let first = ends.next().unwrap();
+            ends.take_while(|&i| fits(&rest[..i])).last().unwrap_or(first)
+        });
+
+        lines.push((&rest[..end], false));
+        rest = rest[end..].trim_start();
+        if rest.is_empty() {
+            return;
+        }
+    }
+}
+
+/// Places glyphs of a single line, adding `extra_space` to the advance of every space.
+/// Kerning only applies between glyphs of the same font.
+pub(super) fn layout_line(fonts: &FontSet, scale: Scale, text: &str, extra_space: f32) -> Line {
+    let mut glyphs = Vec::with_capacity(text.len());
+    let mut caret = 0.0;
+    let mut prev = None;
+    for c in text.chars() {
+        let index = fonts.font_for(c);
+        let font = &fonts.fonts[index].font;
+        let glyph = font.glyph(c);
+        let id = glyph.id();
+        match prev {
+            Some((prev_index, prev_id)) if prev_index == index => {
+                caret += font.pair_kerning(scale, prev_id, id);
+            },
+            _ => {},
+        }
+        prev = Some((index, id));
+
+        glyphs.push((index, id, caret));
+        caret += glyph.scaled(scale).h_metrics().advance_width;
+        if c == ' ' {
+            caret += extra_space;
+        }
+    }
+
+    Line {
+        glyphs,
+        width: caret,
+        baseline: 0.0,
+    }
+}
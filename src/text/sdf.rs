@@ -0,0 +1,328 @@
+//! Glyphs drawn from signed distance fields.
+//!
+//! Every glyph gets a field once, at `FIELD_SIZE`, holding the distance from
+//! each texel to the outline. Text of any size is drawn by sampling the field,
+//! so edges stay sharp when scaled up without rasterizing glyphs again, and
+//! the same distances give outlines, glows and shadows.
+
+use std::collections::HashMap;
+use rusttype::{GlyphId, OutlineBuilder, Point, Scale, point};
+use crate::canvas::{Canvas, Color, Rect};
+use super::{ATLAS_MAX_HEIGHT, ATLAS_WIDTH, Atlas, TextStyle};
+
+/// Line height glyph fields are generated at.
+pub const FIELD_SIZE: f32 = 48.0;
+
+/// Largest distance from the outline a field holds, in pixels at `FIELD_SIZE`.
+/// Effects are cut off at this distance, which is `FIELD_SPREAD / FIELD_SIZE`
+/// of the line height on the canvas.
+pub const FIELD_SPREAD: f32 = 12.0;
+
+/// Band of the glyph's color around the fill, drawn under it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Outline {
+    /// In canvas pixels.
+    pub width: f32,
+    pub color: Color,
+}
+
+/// Light around the text that fades out with distance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glow {
+    /// Distance at which the glow fades out completely, in canvas pixels.
+    pub radius: f32,
+    pub color: Color,
+}
+
+/// Copy of the text, outline included, drawn under it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shadow {
+    /// In canvas pixels, positive moves the shadow right and down.
+    pub offset: (f32, f32),
+    /// Width of the blurred edge in canvas pixels, `0.0` for a sharp shadow.
+    pub softness: f32,
+    pub color: Color,
+}
+
+/// Effects of text drawn from distance fields. Layers are drawn
+/// back to front: shadow, glow, outline, then the text itself.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SdfEffects {
+    pub outline: Option<Outline>,
+    pub glow: Option<Glow>,
+    pub shadow: Option<Shadow>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Layer {
+    Shadow,
+    Glow,
+    Outline,
+    Fill,
+}
+
+impl Layer {
+    pub(super) const BACK_TO_FRONT: [Layer; 4] = [Layer::Shadow, Layer::Glow, Layer::Outline, Layer::Fill];
+}
+
+/// Location of a glyph field in the atlas.
+#[derive(Clone, Copy)]
+pub(super) struct Field {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    /// Offset of the top left corner of the field from the glyph origin, in field pixels.
+    offset: (f32, f32),
+}
+
+pub(super) struct FieldCache {
+    pub entries: HashMap<GlyphId, Field>,
+    atlas: Atlas,
+}
+
+impl FieldCache {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            atlas: Atlas::new(ATLAS_WIDTH),
+        }
+    }
+
+    pub fn get(&mut self, font: &rusttype::Font<'static>, glyph: GlyphId) -> Field {
+        if let Some(&field) = self.entries.get(&glyph) {
+            return field;
+        }
+
+        let mut segments = Segments::default();
+        font.glyph(glyph).scaled(Scale::uniform(FIELD_SIZE)).build_outline(&mut segments);
+
+        let field = match segments.bounds() {
+            Some((min, max)) => {
+                let padding = FIELD_SPREAD.ceil() + 1.0;
+                let x0 = min.x.floor() - padding;
+                let y0 = min.y.floor() - padding;
+                let width = (max.x.ceil() + padding - x0) as usize;
+                let height = (max.y.ceil() + padding - y0) as usize;
+                if width > self.atlas.width || self.atlas.height() + height > ATLAS_MAX_HEIGHT {
+                    self.entries.clear();
+                    self.atlas = Atlas::new(width.max(ATLAS_WIDTH));
+                }
+
+                let (ax, ay) = self.atlas.allocate(width, height);
+                for y in 0..height {
+                    for x in 0..width {
+                        let distance = segments.signed_distance(point(x0 + x as f32 + 0.5, y0 + y as f32 + 0.5));
+                        let v = 128.0 + distance / FIELD_SPREAD * 127.0;
+                        self.atlas.set((ax + x, ay + y), v.round().clamp(0.0, 255.0) as u8);
+                    }
+                }
+
+                Field { x: ax, y: ay, width, height, offset: (x0, y0) }
+            },
+            // whitespace
+            None => Field { x: 0, y: 0, width: 0, height: 0, offset: (0.0, 0.0) },
+        };
+        self.entries.insert(glyph, field);
+        field
+    }
+
+    /// Draws one layer of a glyph with its origin at `origin`.
+    /// Returns bounding box of the pixels it touched.
+    pub fn draw(&self, canvas: &mut Canvas, field: Field, origin: Point<f32>, layer: Layer, style: &TextStyle, effects: &SdfEffects) -> Rect {
+        if field.width == 0 {
+            return Rect::default();
+        }
+
+        let scale = style.size / FIELD_SIZE;
+        let outline_width = effects.outline.map_or(0.0, |outline| outline.width);
+        let (shift, color) = match layer {
+            Layer::Shadow => match effects.shadow {
+                Some(shadow) => (shadow.offset, shadow.color),
+                None => return Rect::default(),
+            },
+            Layer::Glow => match effects.glow {
+                Some(glow) => ((0.0, 0.0), glow.color),
+                None => return Rect::default(),
+            },
+            Layer::Outline => match effects.outline {
+                Some(outline) => ((0.0, 0.0), outline.color),
+                None => return Rect::default(),
+            },
+            Layer::Fill => ((0.0, 0.0), style.color),
+        };
+
+        // coverage of a pixel `distance` canvas pixels inside the glyph outline
+        let coverage = |distance: f32| -> f32 {
+            match layer {
+                Layer::Fill => distance + 0.5,
+                Layer::Outline => distance + outline_width + 0.5,
+                Layer::Glow => {
+                    let radius = effects.glow.map_or(0.0, |glow| glow.radius).max(f32::EPSILON);
+                    let t = (1.0 + (distance + outline_width) / radius).clamp(0.0, 1.0);
+                    t * t
+                },
+                Layer::Shadow => {
+                    let softness = effects.shadow.map_or(0.0, |shadow| shadow.softness).max(1.0);
+                    (distance + outline_width) / softness + 0.5
+                },
+            }
+            .clamp(0.0, 1.0)
+        };
+
+        let left = origin.x + shift.0 + field.offset.0 * scale;
+        let top = origin.y + shift.1 + field.offset.1 * scale;
+        let rect = Rect::from_corners(
+            (left.floor() as isize, top.floor() as isize),
+            (
+                (left + field.width as f32 * scale).ceil() as isize,
+                (top + field.height as f32 * scale).ceil() as isize,
+            ),
+        );
+        let clipped = rect.intersection(&canvas.rect());
+
+        let (mut min, mut max) = ((isize::MAX, isize::MAX), (isize::MIN, isize::MIN));
+        for cy in clipped.y..clipped.bottom() {
+            for cx in clipped.x..clipped.right() {
+                let fx = (cx as f32 + 0.5 - left) / scale - 0.5;
+                let fy = (cy as f32 + 0.5 - top) / scale - 0.5;
+                let coverage = coverage(self.sample(field, fx, fy) * scale);
+                let a = (color.a as f32 * coverage).round() as u8;
+                if a == 0 {
+                    continue;
+                }
+
+                canvas.blend((cx as usize, cy as usize), Color { a, ..color });
+                min = (min.0.min(cx), min.1.min(cy));
+                max = (max.0.max(cx + 1), max.1.max(cy + 1));
+            }
+        }
+        if min.0 < max.0 {
+            Rect::from_corners(min, max)
+        } else {
+            Rect::default()
+        }
+    }
+
+    /// Bilinearly interpolated signed distance in field pixels at field texel coordinates,
+    /// positive inside the glyph.
+    fn sample(&self, field: Field, x: f32, y: f32) -> f32 {
+        let texel = |tx: isize, ty: isize| -> f32 {
+            if tx < 0 || ty < 0 || tx >= field.width as isize || ty >= field.height as isize {
+                return -FIELD_SPREAD;
+            }
+            let v = self.atlas.data[field.x + tx as usize + (field.y + ty as usize) * self.atlas.width];
+            (v as f32 - 128.0) / 127.0 * FIELD_SPREAD
+        };
+
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+        let top = texel(x0, y0) * (1.0 - fx) + texel(x0 + 1, y0) * fx;
+        let bottom = texel(x0, y0 + 1) * (1.0 - fx) + texel(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+/// Glyph outline flattened into line segments.
+#[derive(Default)]
+struct Segments {
+    segments: Vec<(Point<f32>, Point<f32>)>,
+    start: Point<f32>,
+    current: Point<f32>,
+}
+
+impl Segments {
+    fn bounds(&self) -> Option<(Point<f32>, Point<f32>)> {
+        let mut points = self.segments.iter().map(|&(a, _)| a);
+        let first = points.next()?;
+        Some(points.fold((first, first), |(min, max), p| {
+            (point(min.x.min(p.x), min.y.min(p.y)), point(max.x.max(p.x), max.y.max(p.y)))
+        }))
+    }
+
+    /// Distance to the closest segment, positive inside by the non-zero winding rule.
+    fn signed_distance(&self, p: Point<f32>) -> f32 {
+        let mut closest = f32::INFINITY;
+        let mut winding = 0;
+        for &(a, b) in &self.segments {
+            let ab = b - a;
+            let ap = p - a;
+            let t = ((ap.x * ab.x + ap.y * ab.y) / (ab.x * ab.x + ab.y * ab.y).max(f32::EPSILON)).clamp(0.0, 1.0);
+            let d = ap - ab * t;
+            closest = closest.min(d.x * d.x + d.y * d.y);
+
+            let cross = ab.x * ap.y - ab.y * ap.x;
+            if a.y <= p.y && p.y < b.y && cross > 0.0 {
+                winding += 1;
+            } else if b.y <= p.y && p.y < a.y && cross < 0.0 {
+                winding -= 1;
+            }
+        }
+
+        let distance = closest.sqrt();
+        if winding != 0 { distance } else { -distance }
+    }
+
+    fn push(&mut self, to: Point<f32>) {
+        if to != self.current {
+            self.segments.push((self.current, to));
+        }
+        self.current = to;
+    }
+
+    /// Number of segments a curve with control polygon of `length` is split into.
+    fn steps(length: f32) -> usize {
+        ((length / 2.0).ceil() as usize).clamp(1, 32)
+    }
+}
+
+impl OutlineBuilder for Segments {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = point(x, y);
+        self.current = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.push(point(x, y));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (p0, p1, p2) = (self.current, point(x1, y1), point(x, y));
+        let length = distance(p0, p1) + distance(p1, p2);
+        let steps = Self::steps(length);
+        for i in 1..=steps {
+            let t = i as f32 / steps as f32;
+            let u = 1.0 - t;
+            self.push(point(
+                u * u * p0.x + 2.0 * u * t * p1.x + t * t * p2.x,
+                u * u * p0.y + 2.0 * u * t * p1.y + t * t * p2.y,
+            ));
+        }
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (p0, p1, p2, p3) = (self.current, point(x1, y1), point(x2, y2), point(x, y));
+        let length = distance(p0, p1) + distance(p1, p2) + distance(p2, p3);
+        let steps = Self::steps(length);
+        for i in 1..=steps {
+            let t = i as f32 / steps as f32;
+            let u = 1.0 - t;
+            let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+            self.push(point(
+                a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+                a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+            ));
+        }
+    }
+
+    fn close(&mut self) {
+        let start = self.start;
+        self.push(start);
+    }
+}
+
+fn distance(a: Point<f32>, b: Point<f32>) -> f32 {
+    let d = b - a;
+    (d.x * d.x + d.y * d.y).sqrt()
+}
@@ -0,0 +1,528 @@
+//! Immediate-mode debug UI.
+//!
+//! Widgets are functions called every frame. They draw themselves right away
+//! and return what the user did to them, there is no widget tree to keep in
+//! sync with the application's data:
+//!
+//! ```no_run
+//! # use gfx::{canvas::Canvas, ui::{Ui, UiInput}, raytracer::Scene};
+//! # let mut canvas = Canvas::new(1280, 720).unwrap();
+//! # let mut scene = Scene::demo();
+//! # let mut ui = Ui::new();
+//! # let input = UiInput::default();
+//! let mut show_graph = true;
+//! let mut frame = ui.frame(&mut canvas, &input, (10, 10), 240);
+//! frame.checkbox("frame time graph", &mut show_graph);
+//! frame.inspect("scene", &mut scene);
+//! if frame.button("reset") {
+//!     scene = Scene::demo();
+//! }
+//! ```
+//!
+//! Widgets are stacked top to bottom in a column and are told apart by their
+//! labels, together with the labels of the panels they are in.
+//! Text is drawn with the built-in bitmap font.
+//!
+//! Keyboard: Tab moves focus to the next widget, Enter and Space press
+//! buttons and checkboxes, Left and Right change sliders and values.
+
+use std::{
+    collections::{HashMap, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+    ops::RangeInclusive,
+};
+use crate::{
+    camera::{Camera, Projection},
+    canvas::{Canvas, Color, Rect},
+    debug_text::{GLYPH_HEIGHT, GLYPH_WIDTH, draw_debug_text},
+    draw::{draw_rect, fill_rect},
+    input::{InputState, Key, MouseButton},
+    math::{Num, V3},
+    raytracer::{Light, LightType, Scene, Sphere},
+};
+
+/// Height of a widget.
+pub const ROW_HEIGHT: usize = GLYPH_HEIGHT + 6;
+/// Indentation of the contents of a panel.
+const INDENT: usize = GLYPH_WIDTH;
+
+const BACKGROUND: Color = Color { r: 24, g: 24, b: 32, a: 200 };
+const WIDGET: Color = Color { r: 60, g: 64, b: 80, a: 255 };
+const HOVERED: Color = Color { r: 80, g: 86, b: 110, a: 255 };
+const ACTIVE: Color = Color { r: 100, g: 110, b: 150, a: 255 };
+const ACCENT: Color = Color { r: 90, g: 170, b: 255, a: 255 };
+const TEXT: Color = Color { r: 230, g: 230, b: 230, a: 255 };
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UiKey {
+    Tab,
+    Enter,
+    Space,
+    Left,
+    Right,
+}
+
+/// Mouse and keyboard state the UI reads, usually made from an `InputState` every frame.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UiInput {
+    /// Mouse position on the canvas.
+    pub mouse: (isize, isize),
+    /// Whether the left mouse button is held.
+    pub mouse_down: bool,
+    /// Keys pressed since the previous frame, in order.
+    pub keys: Vec<UiKey>,
+}
+
+impl From<&InputState> for UiInput {
+    fn from(input: &InputState) -> Self {
+        let keys = input.key_presses()
+            .filter_map(|key| match key {
+                Key::Tab => Some(UiKey::Tab),
+                Key::Enter => Some(UiKey::Enter),
+                Key::Space => Some(UiKey::Space),
+                Key::Left => Some(UiKey::Left),
+                Key::Right => Some(UiKey::Right),
+                _ => None,
+            })
+            .collect();
+        Self {
+            mouse: input.mouse,
+            mouse_down: input.button_down(MouseButton::Left),
+            keys,
+        }
+    }
+}
+
+type Id = u64;
+
+/// State kept between frames: which widget is held, focused, and which panels are open.
+#[derive(Default)]
+pub struct Ui {
+    /// Widget the mouse was pressed on, until it is released.
+    active: Option<Id>,
+    /// Widget keyboard input goes to.
+    focus: Option<Id>,
+    open: HashMap<Id, bool>,
+    /// Widgets in the order they were added in the previous frame, for Tab.
+    order: Vec<Id>,
+    previous_input: UiInput,
+    /// Area covered by widgets in the previous frame.
+    covered: Rect,
+}
+
+impl Ui {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts adding widgets to a column `width` pixels wide with its top left corner at `position`.
+    /// Widgets are drawn onto `canvas` as they are added.
+    pub fn frame<'a>(&'a mut self, canvas: &'a mut Canvas, input: &UiInput, position: (isize, isize), width: usize) -> UiFrame<'a> {
+        let mut keys = input.keys.clone();
+        if keys.contains(&UiKey::Tab) && !self.order.is_empty() {
+            let next = match self.focus.and_then(|focus| self.order.iter().position(|&id| id == focus)) {
+                Some(i) => (i + 1) % self.order.len(),
+                None => 0,
+            };
+            self.focus = Some(self.order[next]);
+            keys.retain(|&key| key != UiKey::Tab);
+        }
+
+        let pressed = input.mouse_down && !self.previous_input.mouse_down;
+        let released = !input.mouse_down && self.previous_input.mouse_down;
+        let mouse_delta = (
+            input.mouse.0 - self.previous_input.mouse.0,
+            input.mouse.1 - self.previous_input.mouse.1,
+        );
+
+        UiFrame {
+            input: UiInput { keys, ..input.clone() },
+            pressed,
+            released,
+            mouse_delta,
+            canvas,
+            x: position.0,
+            y: position.1,
+            width,
+            ids: vec![0],
+            order: Vec::new(),
+            covered: Rect::default(),
+            pressed_on_widget: false,
+            ui: self,
+        }
+    }
+
+    /// Whether the mouse is over the UI or dragging a widget,
+    /// in which case the application should not react to it.
+    pub fn wants_mouse(&self) -> bool {
+        self.active.is_some() || self.covered.contains(self.previous_input.mouse)
+    }
+}
+
+/// Widgets of one frame. Finishes the frame when dropped.
+pub struct UiFrame<'a> {
+    ui: &'a mut Ui,
+    canvas: &'a mut Canvas,
+    input: UiInput,
+    /// Left mouse button went down this frame.
+    pressed: bool,
+    released: bool,
+    mouse_delta: (isize, isize),
+    /// Where the next widget goes.
+    x: isize,
+    y: isize,
+    width: usize,
+    /// Ids of the enclosing panels.
+    ids: Vec<Id>,
+    order: Vec<Id>,
+    covered: Rect,
+    pressed_on_widget: bool,
+}
+
+impl UiFrame<'_> {
+    pub fn label(&mut self, text: &str) {
+        let rect = self.row();
+        self.text(rect, text, TEXT);
+    }
+
+    /// Returns `true` when clicked.
+    pub fn button(&mut self, label: &str) -> bool {
+        let id = self.id(label);
+        let rect = self.row();
+        let (hovered, held) = self.interact(id, rect);
+
+        let clicked = (self.released && hovered && self.ui.active == Some(id))
+            || (self.focused(id) && self.key(UiKey::Enter) | self.key(UiKey::Space));
+
+        fill_rect(self.canvas, rect, if held { ACTIVE } else if hovered { HOVERED } else { WIDGET });
+        self.outline(id, rect);
+        let text_width = label.chars().count() * GLYPH_WIDTH;
+        let text_x = rect.x + (rect.width.saturating_sub(text_width) / 2) as isize;
+        self.text(Rect { x: text_x, ..rect }, label, TEXT);
+        clicked
+    }
+
+    /// Returns `true` when `value` changed.
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let id = self.id(label);
+        let rect = self.row();
+        let (hovered, _) = self.interact(id, rect);
+
+        let toggled = (self.pressed && hovered)
+            || (self.focused(id) && self.key(UiKey::Enter) | self.key(UiKey::Space));
+        if toggled {
+            *value = !*value;
+        }
+
+        let size = GLYPH_HEIGHT + 2;
+        let tick = Rect::new((rect.x + 1, rect.y + 2), (size, size));
+        fill_rect(self.canvas, tick, if hovered { HOVERED } else { WIDGET });
+        if *value {
+            fill_rect(self.canvas, Rect::new((tick.x + 3, tick.y + 3), (size - 6, size - 6)), ACCENT);
+        }
+        self.outline(id, tick);
+        let offset = (size + 4) as isize;
+        self.text(Rect { x: rect.x + offset, width: rect.width.saturating_sub(offset as usize), ..rect }, label, TEXT);
+        toggled
+    }
+
+    /// Slider dragged across the range, Left and Right step by 1% of it.
+    /// Returns `true` when `value` changed.
+    pub fn slider(&mut self, label: &str, value: &mut Num, range: RangeInclusive<Num>) -> bool {
+        let id = self.id(label);
+        let (label_rect, track) = self.split_row();
+        let (hovered, held) = self.interact(id, track);
+        let (min, max) = (*range.start(), *range.end());
+
+        let old = *value;
+        if held && track.width > 1 {
+            let t = (self.input.mouse.0 - track.x) as Num / (track.width - 1) as Num;
+            *value = min + t.clamp(0.0, 1.0) * (max - min);
+        }
+        if self.focused(id) {
+            let step = (max - min) / 100.0;
+            *value += step * self.arrows();
+        }
+        *value = value.clamp(min.min(max), max.max(min));
+
+        self.text(label_rect, label, TEXT);
+        fill_rect(self.canvas, track, if held { ACTIVE } else if hovered { HOVERED } else { WIDGET });
+        let t = if max > min { (*value - min) / (max - min) } else { 0.0 };
+        let filled = (t * track.width as Num).round() as usize;
+        fill_rect(self.canvas, Rect { width: filled, ..track }, Color { a: 120, ..ACCENT });
+        self.outline(id, track);
+        self.value_text(track, *value);
+        *value != old
+    }
+
+    /// Number changed by dragging the mouse horizontally, `speed` per pixel,
+    /// or by Left and Right, `speed` per press. Returns `true` when `value` changed.
+    pub fn drag_value(&mut self, label: &str, value: &mut Num, speed: Num) -> bool {
+        let id = self.id(label);
+        let (label_rect, field) = self.split_row();
+        let (hovered, held) = self.interact(id, field);
+
+        let old = *value;
+        if held && !self.pressed {
+            *value += self.mouse_delta.0 as Num * speed;
+        }
+        if self.focused(id) {
+            *value += speed * self.arrows();
+        }
+
+        self.text(label_rect, label, TEXT);
+        fill_rect(self.canvas, field, if held { ACTIVE } else if hovered { HOVERED } else { WIDGET });
+        self.outline(id, field);
+        self.value_text(field, *value);
+        *value != old
+    }
+
+    /// Header that opens and closes when clicked, with `contents` added indented below it
+    /// while open. Panels start closed. Returns whether the panel is open.
+    pub fn panel(&mut self, title: &str, contents: impl FnOnce(&mut Self)) -> bool {
+        let id = self.id(title);
+        let rect = self.row();
+        let (hovered, _) = self.interact(id, rect);
+
+        let mut open = self.ui.open.get(&id).copied().unwrap_or(false);
+        if (self.pressed && hovered) || (self.focused(id) && self.key(UiKey::Enter) | self.key(UiKey::Space)) {
+            open = !open;
+            self.ui.open.insert(id, open);
+        }
+
+        fill_rect(self.canvas, rect, if hovered { HOVERED } else { WIDGET });
+        self.outline(id, rect);
+        self.text(rect, &format!("{} {}", if open { '-' } else { '+' }, title), TEXT);
+
+        if open {
+            self.ids.push(id);
+            self.x += INDENT as isize;
+            self.width = self.width.saturating_sub(INDENT);
+            contents(self);
+            self.width += INDENT;
+            self.x -= INDENT as isize;
+            self.ids.pop();
+        }
+        open
+    }
+
+    /// Widgets editing `value`, see `Inspect`. Returns `true` when it changed.
+    pub fn inspect(&mut self, label: &str, value: &mut impl Inspect) -> bool {
+        value.inspect(self, label)
+    }
+
+    /// Takes the next row of the column, with the background drawn.
+    fn row(&mut self) -> Rect {
+        let rect = Rect::new((self.x, self.y), (self.width, ROW_HEIGHT));
+        let background = Rect::new((self.x - (self.ids.len() - 1) as isize * INDENT as isize, self.y), (self.width + (self.ids.len() - 1) * INDENT, ROW_HEIGHT + 1));
+        fill_rect(self.canvas, background, BACKGROUND);
+        self.covered = self.covered.union(&background);
+        self.y += ROW_HEIGHT as isize + 1;
+        rect
+    }
+
+    /// Row split into a label on the left and a control on the right.
+    fn split_row(&mut self) -> (Rect, Rect) {
+        let rect = self.row();
+        let label_width = rect.width * 2 / 5;
+        let label = Rect { width: label_width, ..rect };
+        let control = Rect { x: rect.x + label_width as isize, width: rect.width - label_width, ..rect };
+        (label, control)
+    }
+
+    fn id(&mut self, label: &str) -> Id {
+        let mut hasher = DefaultHasher::new();
+        self.ids.last().hash(&mut hasher);
+        label.hash(&mut hasher);
+        let id = hasher.finish();
+        self.order.push(id);
+        id
+    }
+
+    /// Whether the mouse is over `rect`, and whether the widget is held.
+    fn interact(&mut self, id: Id, rect: Rect) -> (bool, bool) {
+        let hovered = rect.contains(self.input.mouse) && self.ui.active.is_none_or(|active| active == id);
+        if self.pressed && hovered {
+            self.ui.active = Some(id);
+            self.ui.focus = Some(id);
+            self.pressed_on_widget = true;
+        }
+        (hovered, self.ui.active == Some(id) && self.input.mouse_down)
+    }
+
+    fn focused(&self, id: Id) -> bool {
+        self.ui.focus == Some(id)
+    }
+
+    fn key(&self, key: UiKey) -> bool {
+        self.input.keys.contains(&key)
+    }
+
+    /// Right presses minus Left presses.
+    fn arrows(&self) -> Num {
+        self.input.keys.iter().map(|key| match key {
+            UiKey::Right => 1.0,
+            UiKey::Left => -1.0,
+            _ => 0.0,
+        }).sum()
+    }
+
+    fn outline(&mut self, id: Id, rect: Rect) {
+        if self.focused(id) {
+            draw_rect(self.canvas, rect, ACCENT);
+        }
+    }
+
+    /// Draws as much of `text` as fits, vertically centered in `rect`.
+    fn text(&mut self, rect: Rect, text: &str, color: Color) {
+        let fits = rect.width.saturating_sub(4) / GLYPH_WIDTH;
+        let shown: String = text.chars().take(fits).collect();
+        let y = rect.y + (rect.height.saturating_sub(GLYPH_HEIGHT) / 2) as isize;
+        draw_debug_text(self.canvas, (rect.x + 2, y), color, format_args!("{}", shown));
+    }
+
+    fn value_text(&mut self, rect: Rect, value: Num) {
+        let text = format!("{:.3}", value);
+        let width = text.len() * GLYPH_WIDTH;
+        let x = rect.x + (rect.width.saturating_sub(width) / 2) as isize;
+        self.text(Rect { x: x - 2, width: width + 4, ..rect }, &text, TEXT);
+    }
+}
+
+impl Drop for UiFrame<'_> {
+    fn drop(&mut self) {
+        if !self.input.mouse_down {
+            self.ui.active = None;
+        }
+        // clicking outside of widgets takes focus away
+        if self.pressed && !self.pressed_on_widget {
+            self.ui.focus = None;
+        }
+        self.ui.order = std::mem::take(&mut self.order);
+        self.ui.covered = self.covered;
+        self.ui.previous_input = UiInput { keys: Vec::new(), ..self.input.clone() };
+    }
+}
+
+/// Values that can be edited with widgets.
+///
+/// Structures show their fields in a panel named after `label`.
+pub trait Inspect {
+    /// Adds widgets editing `self`. Returns `true` when it changed.
+    fn inspect(&mut self, ui: &mut UiFrame, label: &str) -> bool;
+}
+
+impl Inspect for bool {
+    fn inspect(&mut self, ui: &mut UiFrame, label: &str) -> bool {
+        ui.checkbox(label, self)
+    }
+}
+
+impl Inspect for Num {
+    fn inspect(&mut self, ui: &mut UiFrame, label: &str) -> bool {
+        ui.drag_value(label, self, 0.01)
+    }
+}
+
+impl Inspect for V3 {
+    fn inspect(&mut self, ui: &mut UiFrame, label: &str) -> bool {
+        let mut changed = false;
+        ui.panel(label, |ui| {
+            changed |= ui.drag_value("x", &mut self.x, 0.01);
+            changed |= ui.drag_value("y", &mut self.y, 0.01);
+            changed |= ui.drag_value("z", &mut self.z, 0.01);
+        });
+        changed
+    }
+}
+
+impl Inspect for Color {
+    fn inspect(&mut self, ui: &mut UiFrame, label: &str) -> bool {
+        let mut changed = false;
+        ui.panel(label, |ui| {
+            for (name, channel) in [("r", &mut self.r), ("g", &mut self.g), ("b", &mut self.b), ("a", &mut self.a)] {
+                let mut value = *channel as Num;
+                if ui.slider(name, &mut value, 0.0..=255.0) {
+                    *channel = value.round() as u8;
+                    changed = true;
+                }
+            }
+        });
+        changed
+    }
+}
+
+impl<T: Inspect> Inspect for Vec<T> {
+    fn inspect(&mut self, ui: &mut UiFrame, label: &str) -> bool {
+        let mut changed = false;
+        ui.panel(label, |ui| {
+            for (i, item) in self.iter_mut().enumerate() {
+                changed |= item.inspect(ui, &i.to_string());
+            }
+        });
+        changed
+    }
+}
+
+impl Inspect for Sphere {
+    fn inspect(&mut self, ui: &mut UiFrame, label: &str) -> bool {
+        let mut changed = false;
+        ui.panel(label, |ui| {
+            changed |= ui.inspect("center", &mut self.center);
+            changed |= ui.drag_value("radius", &mut self.radius, 0.01);
+            changed |= ui.inspect("color", &mut self.color);
+        });
+        changed
+    }
+}
+
+impl Inspect for Light {
+    fn inspect(&mut self, ui: &mut UiFrame, label: &str) -> bool {
+        let mut changed = false;
+        ui.panel(label, |ui| {
+            changed |= ui.slider("intensity", &mut self.intensity, 0.0..=1.0);
+            match &mut self.light_type {
+                LightType::Ambient => ui.label("ambient"),
+                LightType::Point { pos } => changed |= ui.inspect("position", pos),
+                LightType::Directional { dir } => changed |= ui.inspect("direction", dir),
+            }
+        });
+        changed
+    }
+}
+
+impl Inspect for Scene {
+    fn inspect(&mut self, ui: &mut UiFrame, label: &str) -> bool {
+        let mut changed = false;
+        ui.panel(label, |ui| {
+            changed |= ui.inspect("lights", &mut self.lights);
+            changed |= ui.inspect("spheres", &mut self.spheres);
+            changed |= ui.inspect("camera", &mut self.camera);
+        });
+        changed
+    }
+}
+
+impl Inspect for Camera {
+    fn inspect(&mut self, ui: &mut UiFrame, label: &str) -> bool {
+        let mut changed = false;
+        ui.panel(label, |ui| {
+            changed |= ui.inspect("position", &mut self.position);
+            changed |= ui.drag_value("yaw", &mut self.yaw, 0.01);
+            changed |= ui.drag_value("pitch", &mut self.pitch, 0.01);
+            changed |= ui.drag_value("roll", &mut self.roll, 0.01);
+            match &mut self.projection {
+                Projection::Perspective { fov_y } => {
+                    let mut degrees = fov_y.to_degrees();
+                    if ui.slider("fov", &mut degrees, 1.0..=179.0) {
+                        *fov_y = degrees.to_radians();
+                        changed = true;
+                    }
+                },
+                Projection::Orthographic { height } => changed |= ui.drag_value("height", height, 0.01),
+            }
+            changed |= ui.drag_value("near", &mut self.near, 0.01);
+        });
+        changed
+    }
+}
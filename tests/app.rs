@@ -0,0 +1,161 @@
+use std::time::{Duration, Instant};
+use gfx::{
+    app::{App, Clock, FakeClock, FrameTime, Runner, SystemClock, Timestep},
+    backend::scripted::Scripted,
+    canvas::Canvas,
+    input::{Event, Key},
+};
+
+const MS: Duration = Duration::from_millis(1);
+
+/// Records what the runner calls, and stands for rendering taking `render_time` of `clock`.
+#[derive(Default)]
+struct Recorder {
+    clock: FakeClock,
+    render_time: Duration,
+    updates: Vec<Duration>,
+    /// Number of updates before every frame, with its timing.
+    frames: Vec<(usize, FrameTime)>,
+    events: Vec<Event>,
+    exit: bool,
+}
+
+impl App for Recorder {
+    fn update(&mut self, dt: Duration) {
+        self.updates.push(dt);
+    }
+
+    fn render(&mut self, _canvas: &mut Canvas, frame: &FrameTime) {
+        self.frames.push((self.updates.len(), *frame));
+        self.clock.advance(self.render_time);
+    }
+
+    fn event(&mut self, event: &Event) {
+        self.exit |= *event == Event::KeyDown { key: Key::Escape, repeat: false };
+        self.events.push(event.clone());
+    }
+
+    fn should_exit(&self) -> bool {
+        self.exit
+    }
+}
+
+fn run(runner: &mut Runner<FakeClock>, app: &mut Recorder, script: Vec<Vec<Event>>) -> Scripted {
+    let mut backend = Scripted::new((4, 4), script);
+    let mut canvas = Canvas::new(4, 4).unwrap();
+    runner.run(&mut backend, &mut canvas, app).unwrap();
+    backend
+}
+
+#[test]
+fn deterministic() {
+    let clock = FakeClock::new();
+    let mut runner = Runner::deterministic(10 * MS, clock.clone());
+    runner.frame_limit = Some(5);
+    let mut app = Recorder::default();
+    let backend = run(&mut runner, &mut app, Vec::new());
+
+    assert_eq!(backend.presented(), 5);
+    assert_eq!(app.updates, [10 * MS; 4]);
+    let times: Vec<_> = app.frames.iter().map(|(updates, frame)| (*updates, frame.time, frame.alpha)).collect();
+    assert_eq!(times, [(0, Duration::ZERO, 0.0), (1, 10 * MS, 0.0), (2, 20 * MS, 0.0), (3, 30 * MS, 0.0), (4, 40 * MS, 0.0)]);
+    // no wait after the last frame
+    assert_eq!(clock.now(), 40 * MS);
+
+    // frames slower than the cap are not waited for
+    let mut app = Recorder { clock: clock.clone(), render_time: 25 * MS, ..Recorder::default() };
+    runner.frame_limit = Some(3);
+    run(&mut runner, &mut app, Vec::new());
+    let elapsed: Vec<_> = app.frames.iter().map(|(_, frame)| frame.elapsed).collect();
+    assert_eq!(elapsed, [Duration::ZERO, 25 * MS, 25 * MS]);
+    assert_eq!(clock.now(), 115 * MS);
+}
+
+#[test]
+#[should_panic(expected = "step must not be 0")]
+fn zero_step() {
+    let mut runner = Runner::with_clock(FakeClock::new());
+    runner.timestep = Timestep::Fixed(Duration::ZERO);
+    run(&mut runner, &mut Recorder::default(), Vec::new());
+}
+
+#[test]
+fn fixed_timestep_interpolates() {
+    let clock = FakeClock::new();
+    let mut runner = Runner::with_clock(clock.clone());
+    runner.timestep = Timestep::Fixed(10 * MS);
+    runner.frame_limit = Some(4);
+    let mut app = Recorder { clock, render_time: 25 * MS, ..Recorder::default() };
+    run(&mut runner, &mut app, Vec::new());
+
+    let frames: Vec<_> = app.frames.iter().map(|(updates, frame)| (*updates, frame.alpha)).collect();
+    assert_eq!(frames, [(0, 0.0), (2, 0.5), (5, 0.0), (7, 0.5)]);
+    assert!(app.updates.iter().all(|&dt| dt == 10 * MS));
+}
+
+#[test]
+fn slow_frames_drop_time() {
+    let clock = FakeClock::new();
+    let mut runner = Runner::with_clock(clock.clone());
+    runner.timestep = Timestep::Fixed(10 * MS);
+    runner.max_updates = 3;
+    runner.frame_limit = Some(2);
+    let mut app = Recorder { clock, render_time: 1005 * MS, ..Recorder::default() };
+    run(&mut runner, &mut app, Vec::new());
+
+    assert_eq!(app.updates.len(), 3);
+    assert_eq!(app.frames[1].1.alpha, 0.5);
+}
+
+#[test]
+fn variable_timestep() {
+    let clock = FakeClock::new();
+    let mut runner = Runner::with_clock(clock.clone());
+    runner.timestep = Timestep::Variable;
+    runner.frame_limit = Some(3);
+    let mut app = Recorder { clock, render_time: 7 * MS, ..Recorder::default() };
+    run(&mut runner, &mut app, Vec::new());
+
+    assert_eq!(app.updates, [Duration::ZERO, 7 * MS, 7 * MS]);
+    assert!(app.frames.iter().all(|(_, frame)| frame.alpha == 1.0));
+}
+
+#[test]
+fn events_and_exit() {
+    let mut runner = Runner::deterministic(10 * MS, FakeClock::new());
+    let mut app = Recorder::default();
+    let backend = run(&mut runner, &mut app, vec![
+        vec![Event::MouseMove { x: 1, y: 2 }],
+        vec![Event::Resize { width: 8, height: 2 }],
+        vec![Event::KeyDown { key: Key::Escape, repeat: false }],
+        vec![Event::Text('x')],
+    ]);
+
+    // the frame the app exits on is not rendered
+    assert_eq!(backend.presented(), 2);
+    assert_eq!(backend.remaining(), 1);
+    assert_eq!(backend.last_frame().len(), 8 * 2);
+    assert_eq!(app.events, [
+        Event::MouseMove { x: 1, y: 2 },
+        Event::Resize { width: 8, height: 2 },
+        Event::KeyDown { key: Key::Escape, repeat: false },
+    ]);
+}
+
+#[test]
+fn frame_cap_on_real_time() {
+    let mut clock = SystemClock::new();
+    let deadline = clock.now() + 3 * MS;
+    clock.wait_until(deadline);
+    assert!(clock.now() >= deadline);
+
+    let mut runner = Runner::new();
+    runner.frame_cap = Some(5 * MS);
+    runner.frame_limit = Some(5);
+    let mut backend = Scripted::new((4, 4), Vec::new());
+    let mut canvas = Canvas::new(4, 4).unwrap();
+    let start = Instant::now();
+    runner.run(&mut backend, &mut canvas, &mut Recorder::default()).unwrap();
+    assert!(start.elapsed() >= 20 * MS);
+    assert_eq!(backend.presented(), 5);
+}
@@ -0,0 +1,149 @@
+use std::{f64::consts::FRAC_PI_2, time::Duration};
+use gfx::{
+    camera::{Camera, FlyController, OrbitController, Projection},
+    input::{Event, InputState, Key, MouseButton},
+    math::{Num, V3, dot, len},
+};
+
+fn close(a: V3, b: V3) -> bool {
+    len(a - b) < 1e-4
+}
+
+fn input(events: &[Event]) -> InputState {
+    let mut input = InputState::new();
+    input.update(events);
+    input
+}
+
+#[test]
+fn perspective_rays() {
+    let camera = Camera {
+        projection: Projection::Perspective { fov_y: FRAC_PI_2 },
+        near: 0.5,
+        far: 10.0,
+        ..Camera::new()
+    };
+    let view = camera.view((200, 100));
+
+    let center = view.ray((100.0, 50.0));
+    assert!(close(center.origin, [0, 0, 0].into()));
+    assert!(close(center.direction, [0, 0, 1].into()));
+    assert_eq!((center.t_min, center.t_max), (0.5, 10.0));
+
+    // 90 degrees vertically, twice as wide as tall
+    assert!(close(view.ray((0.0, 0.0)).direction, [-2, 1, 1].into()));
+    assert!(close(view.ray((200.0, 100.0)).direction, [2, -1, 1].into()));
+    // `t` is the distance along the view direction
+    assert!(close(view.ray((0.0, 0.0)).at(3.0), [-6, 3, 3].into()));
+
+    // a square canvas shows as much horizontally as vertically
+    let view = camera.view((100, 100));
+    assert!(close(view.ray((0.0, 50.0)).direction, [-1, 0, 1].into()));
+}
+
+#[test]
+fn orthographic_rays() {
+    let camera = Camera {
+        position: [1, 2, 3].into(),
+        projection: Projection::Orthographic { height: 4.0 },
+        ..Camera::new()
+    };
+    let view = camera.view((40, 20));
+    let corner = view.ray((0.0, 0.0));
+    assert!(close(corner.origin, [-3, 4, 3].into()));
+    assert!(close(corner.direction, [0, 0, 1].into()));
+    assert!(close(view.ray((40.0, 20.0)).origin, [5, 0, 3].into()));
+}
+
+#[test]
+fn orientation() {
+    let mut camera = Camera::look_at([0, 0, 0].into(), [1, 0, 0].into());
+    assert!((camera.yaw - FRAC_PI_2).abs() < 1e-5);
+    let (forward, right, up) = camera.basis();
+    assert!(close(forward, [1, 0, 0].into()));
+    assert!(close(right, [0, 0, -1].into()));
+    assert!(close(up, [0, 1, 0].into()));
+
+    camera.look_along([0, 1, 1].into());
+    assert!((camera.pitch - FRAC_PI_2 / 2.0).abs() < 1e-5);
+    assert!(close(camera.forward(), V3::from([0, 1, 1]) / Num::sqrt(2.0)));
+
+    // up tilts to the left
+    let camera = Camera { roll: FRAC_PI_2, ..Camera::new() };
+    let (_, right, up) = camera.basis();
+    assert!(close(right, [0, 1, 0].into()));
+    assert!(close(up, [-1, 0, 0].into()));
+}
+
+#[test]
+fn projection_inverts_rays() {
+    let camera = Camera::look_at([3, 2, -4].into(), [0, 0, 1].into());
+    for projection in [Projection::Perspective { fov_y: 1.0 }, Projection::Orthographic { height: 3.0 }] {
+        let view = Camera { projection, ..camera }.view((320, 180));
+        for &point in &[(0.5, 0.5), (160.0, 90.0), (300.25, 17.75)] {
+            let (x, y) = view.project(view.ray(point).at(5.0)).unwrap();
+            assert!((x - point.0).abs() < 1e-2 && (y - point.1).abs() < 1e-2, "{:?} {:?}", (x, y), point);
+        }
+    }
+    assert_eq!(camera.view((320, 180)).project([3, 2, -10].into()), None);
+}
+
+#[test]
+fn fly_controller() {
+    let fly = FlyController::new();
+    let mut camera = Camera::new();
+    let second = Duration::from_secs(1);
+
+    assert!(!fly.update(&mut camera, &input(&[]), second));
+    assert_eq!(camera, Camera::new());
+
+    let forward = input(&[Event::KeyDown { key: Key::W, repeat: false }]);
+    assert!(fly.update(&mut camera, &forward, second));
+    assert!(close(camera.position, [0.0, 0.0, fly.speed].into()));
+
+    let boosted = input(&[
+        Event::KeyDown { key: Key::D, repeat: false },
+        Event::KeyDown { key: Key::Shift, repeat: false },
+    ]);
+    fly.update(&mut camera, &boosted, second / 2);
+    assert!(close(camera.position, [fly.speed * fly.boost / 2.0, 0.0, fly.speed].into()));
+
+    // looking around only with the right button held
+    let mut look = input(&[Event::MouseMove { x: 100, y: -50 }]);
+    assert!(!fly.update(&mut camera, &look, second));
+    look.update(&[Event::MouseDown { button: MouseButton::Right }, Event::MouseMove { x: 200, y: -100 }]);
+    assert!(fly.update(&mut camera, &look, second));
+    assert!((camera.yaw - 100.0 * fly.sensitivity).abs() < 1e-5);
+    assert!((camera.pitch - 50.0 * fly.sensitivity).abs() < 1e-5);
+
+    // never past straight up
+    look.update(&[Event::MouseMove { x: 200, y: -100_000 }]);
+    fly.update(&mut camera, &look, second);
+    assert!(camera.pitch < FRAC_PI_2);
+    assert!(dot(camera.basis().2, [0, 1, 0].into()) > 0.0);
+}
+
+#[test]
+fn orbit_controller() {
+    let target = V3::from([1, 0, 5]);
+    let mut camera = Camera::look_at([1, 0, 1].into(), target);
+    let mut orbit = OrbitController::new(&camera, target);
+    assert_eq!(orbit.distance, 4.0);
+
+    let mut drag = input(&[Event::MouseDown { button: MouseButton::Left }]);
+    drag.update(&[Event::MouseMove { x: 100, y: 60 }]);
+    assert!(orbit.update(&mut camera, &drag, Duration::from_millis(16)));
+    assert!((len(camera.position - target) - 4.0).abs() < 1e-4);
+    assert!(close(camera.forward(), (target - camera.position) / 4.0));
+    assert!(camera.pitch < 0.0);
+
+    let wheel = input(&[Event::MouseWheel { x: 0.0, y: 2.0 }]);
+    orbit.update(&mut camera, &wheel, Duration::from_millis(16));
+    assert!((orbit.distance - 4.0 * 0.81).abs() < 1e-4);
+    assert!((len(camera.position - target) - orbit.distance).abs() < 1e-4);
+
+    let turn = input(&[Event::KeyDown { key: Key::Left, repeat: false }]);
+    let yaw = camera.yaw;
+    orbit.update(&mut camera, &turn, Duration::from_secs(1));
+    assert!((camera.yaw - (yaw - orbit.turn_speed)).abs() < 1e-5);
+}
@@ -0,0 +1,24 @@
+//! Fixtures shared by the integration tests, every test crate uses a part of them.
+#![allow(dead_code)]
+
+use gfx::canvas::{Canvas, Color};
+
+/// Canvas with `color(x, y)` at every pixel.
+pub fn canvas(width: usize, height: usize, mut color: impl FnMut(usize, usize) -> Color) -> Canvas {
+    let mut canvas = Canvas::new(width, height).unwrap();
+    for y in 0..height {
+        for x in 0..width {
+            canvas.set((x, y), color(x, y));
+        }
+    }
+    canvas
+}
+
+/// Canvas of a single `color`.
+pub fn filled(width: usize, height: usize, color: Color) -> Canvas {
+    canvas(width, height, |_, _| color)
+}
+
+pub fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color { r, g, b, a: 255 }
+}
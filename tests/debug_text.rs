@@ -0,0 +1,83 @@
+mod common;
+
+use gfx::{
+    canvas::{Canvas, Color, Rect},
+    debug_text::{GLYPH_HEIGHT, GLYPH_WIDTH, LINE_HEIGHT, draw_debug_text, measure_debug_text},
+};
+use common::filled;
+
+const BLACK: Color = Color { r: 0, g: 0, b: 0, a: 255 };
+const WHITE: Color = Color { r: 255, g: 255, b: 255, a: 255 };
+
+/// Canvas as text, `#` for pixels that are not black.
+fn ascii(canvas: &Canvas) -> String {
+    let mut s = String::new();
+    for y in 0..canvas.height() {
+        for x in 0..canvas.width() {
+            s.push(if canvas.get((x, y)) == BLACK { '.' } else { '#' });
+        }
+        s.push('\n');
+    }
+    s
+}
+
+#[test]
+fn draws_glyph_bitmaps() {
+    let mut canvas = filled(16, 8, BLACK);
+    let drawn = draw_debug_text(&mut canvas, (0, 0), WHITE, format_args!("{}{}", 'A', 1));
+
+    let expected = "\
+        ..##......##....\n\
+        .####....###....\n\
+        ##..##....##....\n\
+        ##..##....##....\n\
+        ######....##....\n\
+        ##..##....##....\n\
+        ##..##..######..\n\
+        ................\n";
+    assert_eq!(ascii(&canvas), expected);
+    assert_eq!(drawn, Rect::from_corners((0, 0), (14, 7)));
+}
+
+#[test]
+fn newlines_and_measure() {
+    let args = format_args!("ab\n{}", 12345);
+    assert_eq!(measure_debug_text(args), (5 * GLYPH_WIDTH, LINE_HEIGHT + GLYPH_HEIGHT));
+
+    let mut canvas = filled(64, 32, BLACK);
+    draw_debug_text(&mut canvas, (3, 2), WHITE, format_args!("ab\n{}", 12345));
+    let mut expected = filled(64, 32, BLACK);
+    draw_debug_text(&mut expected, (3, 2), WHITE, format_args!("ab"));
+    draw_debug_text(&mut expected, (3, 2 + LINE_HEIGHT as isize), WHITE, format_args!("12345"));
+    assert!(canvas.pixels() == expected.pixels());
+
+    assert_eq!(measure_debug_text(format_args!("")), (0, GLYPH_HEIGHT));
+}
+
+#[test]
+fn blends_and_clips() {
+    let background = Color { r: 0, g: 0, b: 200, a: 255 };
+    let mut canvas = filled(12, 6, background);
+    let red = Color { r: 255, g: 0, b: 0, a: 128 };
+    let drawn = draw_debug_text(&mut canvas, (-4, -2), red, format_args!("##"));
+
+    // right half of the first '#' and left half of the second, top rows cut off
+    assert_eq!(drawn, Rect::from_corners((0, 0), (11, 5)));
+    let blended = canvas.get((1, 0));
+    assert!(blended.r > 100 && blended.r < 155 && blended.b > 80 && blended.b < 120, "{:?}", blended);
+    assert_eq!(canvas.get((11, 5)), background);
+
+    let outside = draw_debug_text(&mut canvas, (100, 100), red, format_args!("##"));
+    assert!(outside.is_empty());
+}
+
+#[test]
+fn unknown_characters_are_boxes() {
+    let mut a = filled(8, 8, BLACK);
+    let mut b = filled(8, 8, BLACK);
+    draw_debug_text(&mut a, (0, 0), WHITE, format_args!("é"));
+    draw_debug_text(&mut b, (0, 0), WHITE, format_args!("\u{1f600}"));
+    assert!(a.pixels() == b.pixels());
+    assert_eq!(a.get((0, 1)), WHITE);
+    assert_eq!(a.get((2, 3)), BLACK);
+}
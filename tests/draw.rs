@@ -0,0 +1,72 @@
+mod common;
+
+use gfx::{
+    canvas::{Canvas, Color, Rect},
+    draw::{draw_line, draw_rect, fill_rect},
+};
+
+const BLACK: Color = Color { r: 0, g: 0, b: 0, a: 255 };
+const WHITE: Color = Color { r: 255, g: 255, b: 255, a: 255 };
+
+fn lit(canvas: &Canvas) -> Vec<(usize, usize)> {
+    let mut lit = Vec::new();
+    for y in 0..canvas.height() {
+        for x in 0..canvas.width() {
+            if canvas.get((x, y)) != BLACK {
+                lit.push((x, y));
+            }
+        }
+    }
+    lit
+}
+
+#[test]
+fn lines_include_both_ends() {
+    let mut canvas = common::filled(8, 8, BLACK);
+    draw_line(&mut canvas, (1, 1), (5, 3), WHITE);
+    // one pixel per column, ties may round either way
+    let pixels = lit(&canvas);
+    assert_eq!(pixels.len(), 5);
+    assert!(pixels.contains(&(1, 1)) && pixels.contains(&(3, 2)) && pixels.contains(&(5, 3)));
+
+    let mut reversed = common::filled(8, 8, BLACK);
+    draw_line(&mut reversed, (5, 3), (1, 1), WHITE);
+    assert_eq!(lit(&reversed).len(), 5);
+
+    let mut point = common::filled(8, 8, BLACK);
+    draw_line(&mut point, (2, 6), (2, 6), WHITE);
+    assert_eq!(lit(&point), vec![(2, 6)]);
+}
+
+#[test]
+fn lines_are_clipped() {
+    let mut canvas = common::filled(10, 10, BLACK);
+    draw_line(&mut canvas, (-1000, 5), (1000, 5), WHITE);
+    assert_eq!(lit(&canvas), (0..10).map(|x| (x, 5)).collect::<Vec<_>>());
+
+    let mut diagonal = common::filled(10, 10, BLACK);
+    draw_line(&mut diagonal, (-5, -5), (20, 20), WHITE);
+    assert_eq!(lit(&diagonal), (0..10).map(|i| (i, i)).collect::<Vec<_>>());
+
+    let mut outside = common::filled(10, 10, BLACK);
+    draw_line(&mut outside, (-5, -5), (-1, 20), WHITE);
+    draw_line(&mut outside, (isize::MIN / 2, 3), (-1, 3), WHITE);
+    assert!(lit(&outside).is_empty());
+}
+
+#[test]
+fn rects() {
+    let mut canvas = common::filled(6, 5, BLACK);
+    draw_rect(&mut canvas, Rect::new((1, 1), (4, 3)), WHITE);
+    let expected = vec![
+        (1, 1), (2, 1), (3, 1), (4, 1),
+        (1, 2), (4, 2),
+        (1, 3), (2, 3), (3, 3), (4, 3),
+    ];
+    assert_eq!(lit(&canvas), expected);
+
+    let mut filled = common::filled(6, 5, BLACK);
+    fill_rect(&mut filled, Rect::new((-2, 3), (4, 10)), Color { a: 128, ..WHITE });
+    assert_eq!(lit(&filled), vec![(0, 3), (1, 3), (0, 4), (1, 4)]);
+    assert_eq!(filled.get((0, 3)).r, 128);
+}
@@ -0,0 +1,39 @@
+use std::error::Error as _;
+use std::io;
+
+use gfx::{canvas::Canvas, Error};
+
+#[test]
+fn canvas_allocation() {
+    match Canvas::new(usize::MAX, 2) {
+        Err(Error::Alloc { width, height }) => assert_eq!((width, height), (usize::MAX, 2)),
+        other => panic!("expected an allocation error, got {:?}", other.map(|_| ())),
+    }
+    assert_eq!(
+        Error::Alloc { width: 3, height: 4 }.to_string(),
+        "cannot allocate a 3x4 canvas",
+    );
+
+    // empty canvases are fine, there is just nothing to draw on
+    let canvas = Canvas::new(0, 5).unwrap();
+    assert_eq!((canvas.width(), canvas.height()), (0, 5));
+    assert!(canvas.pixels().is_empty());
+}
+
+#[test]
+fn messages_and_sources() {
+    let error = Error::Platform { call: "XOpenDisplay", message: "DISPLAY is not set".to_owned() };
+    assert_eq!(error.to_string(), "XOpenDisplay failed: DISPLAY is not set");
+    assert!(error.source().is_none());
+
+    let error = Error::from(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+    assert_eq!(error.to_string(), "no such file");
+    assert!(error.source().is_some());
+
+    // the OS error is described in words, not only as a number
+    let message = match Error::last_os_error("open") {
+        Error::Platform { call: "open", message } => message,
+        other => panic!("expected a platform error, got {:?}", other),
+    };
+    assert!(message.chars().any(char::is_alphabetic), "{}", message);
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use std::path::PathBuf;
+use gfx::{
+    canvas::{Canvas, Color},
+    golden::{Golden, GoldenError},
+};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("gfx-golden-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn gradient(width: usize, height: usize) -> Canvas {
+    common::canvas(width, height, |x, y| Color { r: (x * 8) as u8, g: (y * 8) as u8, b: 128, a: 255 })
+}
+
+fn bless(dir: &PathBuf, name: &str, canvas: &Canvas) {
+    Golden { bless: true, ..Golden::new(dir) }.check(name, canvas).unwrap();
+}
+
+#[test]
+fn missing_reference() {
+    let dir = temp_dir("missing");
+    let golden = Golden { bless: false, ..Golden::new(&dir) };
+    match golden.check("image", &gradient(4, 4)) {
+        Err(GoldenError::MissingReference(path)) => assert_eq!(path, dir.join("image.png")),
+        r => panic!("unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn identical_after_bless() {
+    let dir = temp_dir("identical");
+    let canvas = gradient(16, 8);
+    bless(&dir, "image", &canvas);
+
+    let golden = Golden { bless: false, ..Golden::new(&dir) };
+    golden.check("image", &canvas).unwrap();
+}
+
+#[test]
+fn tolerances() {
+    let dir = temp_dir("tolerances");
+    let mut canvas = gradient(16, 8);
+    bless(&dir, "image", &canvas);
+
+    let c = canvas.get((3, 3));
+    canvas.set((3, 3), Color { r: c.r + 3, ..c });
+    canvas.set((5, 5), Color { r: 0, g: 0, b: 0, a: 255 });
+
+    let exact = Golden { bless: false, ..Golden::new(&dir) };
+    match exact.check("image", &canvas) {
+        Err(GoldenError::Mismatch { differing_pixels, diff, .. }) => {
+            assert_eq!(differing_pixels, 2);
+            assert!(diff.exists());
+            assert!(dir.join("image.actual.png").exists());
+        },
+        r => panic!("unexpected result: {:?}", r),
+    }
+
+    let channel_tolerant = Golden { channel_tolerance: 3, ..exact };
+    match channel_tolerant.check("image", &canvas) {
+        Err(GoldenError::Mismatch { differing_pixels: 1, .. }) => {},
+        r => panic!("unexpected result: {:?}", r),
+    }
+
+    let pixel_tolerant = Golden { max_differing_pixels: 1, ..channel_tolerant };
+    pixel_tolerant.check("image", &canvas).unwrap();
+    assert!(!dir.join("image.diff.png").exists());
+}
+
+#[test]
+fn size_mismatch() {
+    let dir = temp_dir("size");
+    bless(&dir, "image", &gradient(16, 8));
+
+    let golden = Golden { bless: false, ..Golden::new(&dir) };
+    match golden.check("image", &gradient(8, 16)) {
+        Err(GoldenError::SizeMismatch { expected: (16, 8), actual: (8, 16) }) => {},
+        r => panic!("unexpected result: {:?}", r),
+    }
+}
@@ -0,0 +1,153 @@
+use std::time::Duration;
+use gfx::{
+    canvas::{Canvas, Color, Rect},
+    hud::{FrameStats, FrameTimeGraph, Target},
+};
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn ring_buffer() {
+    let mut stats = FrameStats::new(3);
+    assert!(stats.is_empty());
+    assert_eq!(stats.mean(), None);
+    assert_eq!(stats.percentile(50.0), None);
+
+    for t in 1..=5 {
+        stats.push(ms(t));
+    }
+    assert_eq!(stats.len(), 3);
+    assert_eq!(stats.samples().collect::<Vec<_>>(), vec![ms(3), ms(4), ms(5)]);
+    assert_eq!(stats.last(), Some(ms(5)));
+    assert_eq!(stats.min(), Some(ms(3)));
+    assert_eq!(stats.max(), Some(ms(5)));
+    assert_eq!(stats.mean(), Some(ms(4)));
+
+    stats.clear();
+    stats.push(ms(7));
+    assert_eq!(stats.samples().collect::<Vec<_>>(), vec![ms(7)]);
+}
+
+#[test]
+fn targets() {
+    let red = Color { r: 255, g: 0, b: 0, a: 255 };
+    assert_eq!(Target::fps(50.0, red).frame_time, ms(20));
+}
+
+#[test]
+#[should_panic(expected = "fps: 0 is not positive")]
+fn zero_fps_target() {
+    Target::fps(0.0, Color { r: 255, g: 0, b: 0, a: 255 });
+}
+
+#[test]
+#[should_panic(expected = "fps: NaN is not positive")]
+fn nan_fps_target() {
+    Target::fps(f64::NAN, Color { r: 255, g: 0, b: 0, a: 255 });
+}
+
+#[test]
+fn percentiles_and_lows() {
+    let mut stats = FrameStats::new(1000);
+    for t in 1..=1000 {
+        stats.push(Duration::from_micros(t));
+    }
+
+    assert_eq!(stats.percentile(50.0), Some(Duration::from_micros(500)));
+    assert_eq!(stats.percentile(99.0), Some(Duration::from_micros(990)));
+    assert_eq!(stats.percentile(0.0), Some(Duration::from_micros(1)));
+    assert_eq!(stats.percentile(100.0), Some(Duration::from_micros(1000)));
+
+    // mean of 991..=1000 and of 1000 alone
+    assert_eq!(stats.one_percent_low(), Some(Duration::from_nanos(995_500)));
+    assert_eq!(stats.point_one_percent_low(), Some(Duration::from_micros(1000)));
+
+    // too few frames for 0.1% still gives the slowest one
+    let mut few = FrameStats::new(10);
+    few.push(ms(1));
+    few.push(ms(9));
+    assert_eq!(few.point_one_percent_low(), Some(ms(9)));
+}
+
+const BACKGROUND: Color = Color { r: 0, g: 0, b: 0, a: 255 };
+
+fn graph(rect: Rect) -> FrameTimeGraph {
+    FrameTimeGraph {
+        labels: false,
+        background: BACKGROUND,
+        border: BACKGROUND,
+        targets: vec![Target { frame_time: ms(20), color: Color { r: 255, g: 0, b: 0, a: 255 } }],
+        max: Some(ms(40)),
+        ..FrameTimeGraph::new(rect)
+    }
+}
+
+fn rows_with(canvas: &Canvas, color: Color) -> Vec<usize> {
+    (0..canvas.height())
+        .filter(|&y| (0..canvas.width()).any(|x| canvas.get((x, y)) == color))
+        .collect()
+}
+
+#[test]
+fn equal_samples_are_a_flat_line() {
+    let mut canvas = Canvas::new(64, 41).unwrap();
+    let mut stats = FrameStats::new(64);
+    for _ in 0..64 {
+        stats.push(ms(10));
+    }
+
+    let graph = FrameTimeGraph { targets: Vec::new(), max: None, ..graph(canvas.rect()) };
+    graph.draw(&mut canvas, &stats);
+    let line = graph.line;
+    assert_eq!(rows_with(&canvas, line).len(), 1);
+    let row = rows_with(&canvas, line)[0];
+    assert!((0..64).all(|x| canvas.get((x, row)) == line));
+    // 10ms under the axis that fits it
+    assert!(row > 0 && row < 40);
+}
+
+#[test]
+fn spikes_take_target_color() {
+    let mut canvas = Canvas::new(64, 41).unwrap();
+    let graph = graph(canvas.rect());
+    let mut stats = FrameStats::new(64);
+    for i in 0..64 {
+        stats.push(if i == 32 { ms(30) } else { ms(10) });
+    }
+    graph.draw(&mut canvas, &stats);
+
+    let red = graph.targets[0].color;
+    // 10ms is a quarter of the way up, the spike three quarters
+    assert_eq!(rows_with(&canvas, graph.line), vec![30]);
+    let red_rows = rows_with(&canvas, red);
+    assert_eq!(red_rows.first(), Some(&10));
+    assert_eq!(red_rows.last(), Some(&30));
+
+    // target line is drawn half transparent, at half the height
+    let target_row = canvas.get((5, 20));
+    assert!(target_row.r > 100 && target_row.r < 155, "{:?}", target_row);
+}
+
+#[test]
+fn labels_stay_inside() {
+    let mut canvas = Canvas::new(400, 200).unwrap();
+    let mut stats = FrameStats::new(100);
+    for i in 0..100 {
+        stats.push(Duration::from_micros(5000 + i * 300));
+    }
+
+    let rect = Rect::new((50, 40), (200, 100));
+    let graph = FrameTimeGraph::new(rect);
+    assert!(graph.axis_max(&stats) >= ms(35));
+    graph.draw(&mut canvas, &stats);
+
+    for y in 0..canvas.height() {
+        for x in 0..canvas.width() {
+            if !rect.contains((x as isize, y as isize)) {
+                assert_eq!(canvas.get((x, y)), Color { r: 0, g: 0, b: 0, a: 0 }, "({}, {})", x, y);
+            }
+        }
+    }
+}
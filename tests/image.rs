@@ -0,0 +1,83 @@
+mod common;
+
+use std::{convert::TryInto, io};
+use gfx::canvas::{Canvas, Color};
+
+/// 3 by 2, an odd width to catch row padding, every pixel different.
+fn pixels() -> Canvas {
+    common::canvas(3, 2, |x, y| Color { r: (10 * x + 100 * y) as u8, g: x as u8, b: y as u8, a: (200 + x) as u8 })
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/// `canvas` row by row, the channels of every pixel in `order`.
+fn rows(canvas: &Canvas, order: impl Fn(Color) -> Vec<u8>) -> Vec<u8> {
+    canvas.pixels().iter().flat_map(|&c| order(c)).collect()
+}
+
+#[test]
+fn bmp() {
+    let canvas = pixels();
+    let mut data = Vec::new();
+    canvas.write_bmp(&mut data).unwrap();
+
+    assert_eq!(&data[..2], b"BM");
+    assert_eq!(u32_at(&data, 2) as usize, data.len());
+    let offset = u32_at(&data, 10) as usize;
+    assert_eq!(offset, 14 + 108);
+    assert_eq!(u32_at(&data, 14), 108); // BITMAPV4HEADER
+    assert_eq!(u32_at(&data, 18), 3);
+    // negative height, rows go from the top down
+    assert_eq!(u32_at(&data, 22) as i32, -2);
+    assert_eq!(u16_at(&data, 28), 32);
+    assert_eq!(u32_at(&data, 30), 3); // BI_BITFIELDS
+    assert_eq!(u32_at(&data, 34), 3 * 2 * 4);
+    assert_eq!(u32_at(&data, 54), 0x00ff_0000); // red mask
+    assert_eq!(u32_at(&data, 66), 0xff00_0000); // alpha mask
+
+    // 4 bytes per pixel need no padding even at odd widths, the top row comes first
+    assert_eq!(data[offset..], rows(&canvas, |c| vec![c.b, c.g, c.r, c.a])[..]);
+    assert_eq!(data[offset..offset + 4], [0, 0, 0, 200]);
+}
+
+#[test]
+fn tga() {
+    let canvas = pixels();
+    let mut data = Vec::new();
+    canvas.write_tga(&mut data).unwrap();
+
+    assert_eq!(data[..3], [0, 0, 2]); // no id, no color map, uncompressed true color
+    assert_eq!((u16_at(&data, 8), u16_at(&data, 10)), (0, 0));
+    assert_eq!((u16_at(&data, 12), u16_at(&data, 14)), (3, 2));
+    assert_eq!(data[16], 32);
+    // 8 alpha bits and the origin at the top left, so the top row comes first
+    assert_eq!(data[17], 0x28);
+
+    let image = &data[18..18 + 3 * 2 * 4];
+    assert_eq!(image, &rows(&canvas, |c| vec![c.b, c.g, c.r, c.a])[..]);
+    assert_eq!(image[4 * 3..4 * 4], [1, 0, 100, 200]); // (0, 1), the start of the bottom row
+    assert_eq!(data.len(), 18 + 24 + 26);
+    assert!(data.ends_with(b"TRUEVISION-XFILE.\0"));
+
+    let too_wide = Canvas::new(65536, 1).unwrap();
+    let error = too_wide.write_tga(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn ppm() {
+    let canvas = pixels();
+    let mut data = Vec::new();
+    canvas.write_ppm(&mut data).unwrap();
+
+    let header = b"P6\n3 2\n255\n";
+    assert_eq!(&data[..header.len()], header);
+    // alpha is dropped, no padding either
+    assert_eq!(data[header.len()..], rows(&canvas, |c| vec![c.r, c.g, c.b])[..]);
+}
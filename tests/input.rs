@@ -0,0 +1,129 @@
+use gfx::{
+    backend::{Backend, scripted::Scripted},
+    canvas::{Canvas, Color},
+    input::{Event, InputState, Key, MouseButton},
+    ui::{UiInput, UiKey},
+};
+
+fn key_down(key: Key) -> Event {
+    Event::KeyDown { key, repeat: false }
+}
+
+#[test]
+fn keys_held_and_changed() {
+    let mut input = InputState::new();
+
+    input.update(&[key_down(Key::W), key_down(Key::A), Event::KeyUp { key: Key::A }]);
+    assert!(input.is_down(Key::W) && input.pressed(Key::W));
+    // pressed and released within a frame
+    assert!(!input.is_down(Key::A) && input.pressed(Key::A) && input.released(Key::A));
+
+    input.update(&[Event::KeyDown { key: Key::W, repeat: true }]);
+    assert!(input.is_down(Key::W));
+    assert!(!input.pressed(Key::W));
+    assert_eq!(input.key_presses().collect::<Vec<_>>(), vec![Key::W]);
+    assert!(!input.released(Key::A));
+
+    input.update(&[]);
+    assert_eq!(input.keys_down(), &[Key::W]);
+    assert_eq!(input.key_presses().count(), 0);
+
+    // keys are released when focus is lost
+    input.update(&[Event::Focus(false)]);
+    assert!(!input.focused);
+    assert!(input.keys_down().is_empty());
+    assert!(input.released(Key::W));
+}
+
+#[test]
+fn mouse_text_and_window() {
+    let mut input = InputState::new();
+
+    input.update(&[
+        Event::MouseMove { x: 10, y: 20 },
+        Event::MouseMove { x: 15, y: 18 },
+        Event::MouseDown { button: MouseButton::Left },
+        Event::MouseWheel { x: 0.0, y: 1.0 },
+        Event::MouseWheel { x: 0.5, y: 1.0 },
+        Event::Text('h'),
+        Event::Text('é'),
+        Event::Resize { width: 800, height: 600 },
+    ]);
+    assert_eq!(input.mouse, (15, 18));
+    assert_eq!(input.mouse_delta, (15, 18));
+    assert!(input.button_pressed(MouseButton::Left) && input.button_down(MouseButton::Left));
+    assert!(!input.button_down(MouseButton::Right));
+    assert_eq!(input.wheel, (0.5, 2.0));
+    assert_eq!(input.text, "hé");
+    assert_eq!(input.resized, Some((800, 600)));
+    assert!(!input.close_requested);
+
+    input.update(&[Event::MouseUp { button: MouseButton::Left }, Event::Close]);
+    assert_eq!(input.mouse_delta, (0, 0));
+    assert_eq!(input.wheel, (0.0, 0.0));
+    assert!(input.text.is_empty());
+    assert_eq!(input.resized, None);
+    assert!(input.button_released(MouseButton::Left) && !input.button_down(MouseButton::Left));
+    assert!(input.close_requested);
+}
+
+#[test]
+fn ui_input() {
+    let mut input = InputState::new();
+    input.update(&[
+        Event::MouseMove { x: 3, y: 4 },
+        Event::MouseDown { button: MouseButton::Left },
+        key_down(Key::Tab),
+        key_down(Key::Q),
+        Event::KeyDown { key: Key::Right, repeat: true },
+    ]);
+
+    let ui = UiInput::from(&input);
+    assert_eq!(ui.mouse, (3, 4));
+    assert!(ui.mouse_down);
+    assert_eq!(ui.keys, vec![UiKey::Tab, UiKey::Right]);
+}
+
+#[test]
+fn scripted_backend() {
+    let mut backend = Scripted::new((4, 2), vec![
+        vec![key_down(Key::Space)],
+        vec![],
+        vec![Event::Resize { width: 8, height: 6 }],
+    ]);
+    let mut input = InputState::new();
+    let mut canvas = Canvas::new(4, 2).unwrap();
+
+    let mut frames = 0;
+    loop {
+        let mut events = Vec::new();
+        backend.poll_events(&mut events);
+        input.update(&events);
+        if input.close_requested {
+            break;
+        }
+        canvas.set((0, 0), Color { r: frames, g: 0, b: 0, a: 255 });
+        backend.present(&canvas).unwrap();
+        frames += 1;
+    }
+
+    assert_eq!(frames, 3);
+    assert_eq!(backend.remaining(), 0);
+    assert_eq!(backend.presented(), 3);
+    assert_eq!(backend.size(), (8, 6));
+    assert_eq!(backend.last_frame().len(), 8);
+    assert_eq!(backend.last_frame()[0].r, 2);
+    assert!(input.is_down(Key::Space));
+}
+
+#[test]
+fn key_constructors() {
+    assert_eq!(Key::letter('q'), Some(Key::Q));
+    assert_eq!(Key::letter('Z'), Some(Key::Z));
+    assert_eq!(Key::letter('1'), None);
+    assert_eq!(Key::digit(7), Some(Key::Digit7));
+    assert_eq!(Key::digit(10), None);
+    assert_eq!(Key::function(1), Some(Key::F1));
+    assert_eq!(Key::function(12), Some(Key::F12));
+    assert_eq!(Key::function(0), None);
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use gfx::{
+    canvas::{Canvas, Color},
+    metrics,
+};
+use common::filled;
+
+fn checkerboard(width: usize, height: usize, phase: usize) -> Canvas {
+    common::canvas(width, height, |x, y| {
+        let v = if (x + y + phase).is_multiple_of(2) { 0 } else { 255 };
+        Color { r: v, g: v, b: v, a: 255 }
+    })
+}
+
+#[test]
+fn identical() {
+    let a = checkerboard(32, 16, 0);
+    let m = metrics::compare(&a, &a);
+    assert_eq!(m.mse, 0.0);
+    assert_eq!(m.psnr, f64::INFINITY);
+    assert!((m.ssim - 1.0).abs() < 1e-9);
+    assert_eq!(m.max_abs_diff, 0);
+
+    let heatmap = metrics::diff_heatmap(&a, &a);
+    assert!(heatmap.pixels().iter().all(|c| (c.r, c.g, c.b) == (0, 0, 0)));
+}
+
+#[test]
+fn uniform_difference() {
+    let a = filled(16, 16, Color { r: 100, g: 100, b: 100, a: 255 });
+    let b = filled(16, 16, Color { r: 110, g: 100, b: 90, a: 0 });
+
+    // (10^2 + 0 + 10^2) / 3, alpha is ignored
+    assert!((metrics::mse(&a, &b) - 200.0 / 3.0).abs() < 1e-9);
+    assert!((metrics::psnr(&a, &b) - 10.0 * (255.0f64 * 255.0 * 3.0 / 200.0).log10()).abs() < 1e-9);
+    assert_eq!(metrics::max_abs_diff(&a, &b), 10);
+}
+
+#[test]
+fn ssim_is_structural() {
+    let a = checkerboard(32, 32, 0);
+    let inverted = checkerboard(32, 32, 1);
+    let gray = filled(32, 32, Color { r: 128, g: 128, b: 128, a: 255 });
+
+    assert!(metrics::ssim(&a, &inverted) < -0.9);
+    assert!(metrics::ssim(&a, &gray).abs() < 0.1);
+}
+
+#[test]
+fn ssim_covers_edges() {
+    // windows at 0 and 2 along both sides of 10 pixels, the corner is only in the last one
+    let a = checkerboard(10, 10, 0);
+    let mut b = checkerboard(10, 10, 0);
+    b.set((9, 9), Color { r: 128, g: 128, b: 128, a: 255 });
+    assert!(metrics::ssim(&a, &b) < 0.999, "{}", metrics::ssim(&a, &b));
+}
+
+#[test]
+fn empty_canvases() {
+    let empty = Canvas::new(0, 0).unwrap();
+    let m = metrics::compare(&empty, &empty);
+    assert_eq!(m.mse, 0.0);
+    assert_eq!(m.psnr, f64::INFINITY);
+    assert_eq!(m.ssim, 1.0);
+    assert_eq!(m.max_abs_diff, 0);
+}
+
+#[test]
+fn heatmap_highlights_largest_difference() {
+    let a = filled(4, 1, Color { r: 0, g: 0, b: 0, a: 255 });
+    let mut b = filled(4, 1, Color { r: 0, g: 0, b: 0, a: 255 });
+    b.set((1, 0), Color { r: 0, g: 5, b: 0, a: 255 });
+    b.set((2, 0), Color { r: 0, g: 0, b: 20, a: 255 });
+
+    let heatmap = metrics::diff_heatmap(&a, &b);
+    let rgb = |x| {
+        let c = heatmap.get((x, 0));
+        (c.r, c.g, c.b)
+    };
+    assert_eq!(rgb(0), (0, 0, 0));
+    assert_eq!(rgb(2), (255, 255, 255));
+    assert_ne!(rgb(1), (0, 0, 0));
+}
+
+#[test]
+#[should_panic(expected = "canvas sizes differ")]
+fn size_mismatch() {
+    metrics::mse(&checkerboard(4, 4, 0), &checkerboard(4, 5, 0));
+}
@@ -0,0 +1,152 @@
+use gfx::{
+    camera::Camera,
+    canvas::{Canvas, Color},
+    math::Num,
+    pathtracer::{self, Material, PathTracer, Rgb, Scene, Sphere},
+    progressive::Progressive,
+};
+
+/// A sphere of `material` filling the middle of the view, in a sky of `sky`.
+fn furnace(material: Material, sky: Rgb) -> Scene {
+    Scene {
+        spheres: vec![Sphere { center: [0, 0, 3].into(), radius: 1.0, material }],
+        sky,
+        camera: Camera::look_at([0, 0, 0].into(), [0, 0, 3].into()),
+    }
+}
+
+/// No randomly ended paths.
+fn without_roulette(samples: usize) -> PathTracer {
+    PathTracer { samples, roulette_depth: usize::MAX, ..PathTracer::new() }
+}
+
+fn render(scene: &Scene, tracer: &PathTracer, size: (usize, usize)) -> Canvas {
+    let mut canvas = Canvas::new(size.0, size.1).unwrap();
+    assert_eq!(pathtracer::render(&mut canvas, scene, tracer), size.0 * size.1 * tracer.samples);
+    canvas
+}
+
+/// Average red of the pixels in the middle third of `canvas`.
+fn middle_red(canvas: &Canvas) -> Num {
+    let (width, height) = (canvas.width(), canvas.height());
+    let mut sum = 0.0;
+    let mut count = 0;
+    for y in height / 3..height * 2 / 3 {
+        for x in width / 3..width * 2 / 3 {
+            sum += canvas.get((x, y)).r as Num;
+            count += 1;
+        }
+    }
+    sum / count as Num
+}
+
+#[test]
+fn furnace_is_exact() {
+    // a convex sphere only sees the sky, so every path carries the albedo times the sky
+    let diffuse = furnace(Material::Diffuse { albedo: Rgb::new(0.4, 0.2, 1.0) }, Rgb::gray(1.0));
+    let canvas = render(&diffuse, &without_roulette(4), (16, 16));
+    assert_eq!(canvas.get((8, 8)), Color { r: 102, g: 51, b: 255, a: 255 });
+    assert_eq!(canvas.get((0, 0)), Color { r: 255, g: 255, b: 255, a: 255 });
+
+    let mirror = furnace(Material::Metal { albedo: Rgb::gray(0.8), roughness: 0.0 }, Rgb::gray(1.0));
+    let canvas = render(&mirror, &without_roulette(1), (16, 16));
+    assert_eq!(canvas.get((8, 8)).r, 204);
+
+    // glass absorbs nothing, paths only end when they bounce too often inside
+    let glass = furnace(Material::Dielectric { ior: 1.5 }, Rgb::gray(0.5));
+    let canvas = render(&glass, &without_roulette(16), (16, 16));
+    assert!((middle_red(&canvas) - 128.0).abs() <= 2.0, "{}", middle_red(&canvas));
+}
+
+#[test]
+fn roulette_is_unbiased() {
+    let scene = furnace(Material::Diffuse { albedo: Rgb::gray(0.4) }, Rgb::gray(1.0));
+    let tracer = PathTracer { samples: 256, roulette_depth: 0, ..PathTracer::new() };
+    let canvas = render(&scene, &tracer, (12, 12));
+    // survivors are brightened by as much as the others are lost
+    assert!((middle_red(&canvas) - 102.0).abs() <= 3.0, "{}", middle_red(&canvas));
+    assert!(canvas.pixels().iter().any(|pixel| pixel.r != 102));
+}
+
+#[test]
+fn next_event_estimation() {
+    // a floor under a small light
+    let scene = Scene {
+        spheres: vec![
+            Sphere { center: [0.0, -101.0, 3.0].into(), radius: 100.0, material: Material::Diffuse { albedo: Rgb::gray(0.5) } },
+            Sphere { center: [0.0, -0.3, 3.0].into(), radius: 0.4, material: Material::Emissive { radiance: Rgb::gray(2.0) } },
+        ],
+        sky: Rgb::BLACK,
+        camera: Camera::look_at([0.0, 0.0, 0.0].into(), [0.0, -1.0, 3.0].into()),
+    };
+    let sampled = render(&scene, &PathTracer { samples: 64, ..PathTracer::new() }, (24, 24));
+    let brute = render(&scene, &PathTracer { samples: 1024, next_event: false, ..PathTracer::new() }, (24, 24));
+
+    // the same image, the one hitting the light by chance only gets there with many more samples
+    let lit = middle_red(&sampled);
+    assert!(lit > 16.0, "{}", lit);
+    assert!((lit - middle_red(&brute)).abs() <= lit * 0.1, "{} {}", lit, middle_red(&brute));
+
+    // over enough pixels for the noise of the error not to hide the difference
+    let single = render(&scene, &PathTracer { samples: 1, ..PathTracer::new() }, (24, 24));
+    let single_brute = render(&scene, &PathTracer { samples: 1, next_event: false, ..PathTracer::new() }, (24, 24));
+    let error = |canvas: &Canvas| {
+        canvas.pixels().iter().zip(brute.pixels()).map(|(a, b)| a.r.abs_diff(b.r) as usize).sum::<usize>()
+    };
+    assert!(error(&single) * 2 < error(&single_brute), "{} {}", error(&single), error(&single_brute));
+}
+
+#[test]
+fn bright_lights_keep_their_energy() {
+    // paths that find the light by chance carry 4 times full brightness, they are averaged as that
+    let scene = Scene {
+        spheres: vec![
+            Sphere { center: [0.0, -101.0, 3.0].into(), radius: 100.0, material: Material::Diffuse { albedo: Rgb::gray(0.5) } },
+            Sphere { center: [0.0, -0.3, 3.0].into(), radius: 0.2, material: Material::Emissive { radiance: Rgb::gray(8.0) } },
+        ],
+        sky: Rgb::BLACK,
+        camera: Camera::look_at([0.0, 0.0, 0.0].into(), [0.0, -1.0, 3.0].into()),
+    };
+    let sampled = middle_red(&render(&scene, &PathTracer { samples: 64, ..PathTracer::new() }, (12, 12)));
+    let brute = middle_red(&render(&scene, &PathTracer { samples: 1024, next_event: false, ..PathTracer::new() }, (12, 12)));
+    assert!((sampled - brute).abs() <= sampled * 0.1, "{} {}", sampled, brute);
+
+    let mut canvas = Canvas::new(12, 12).unwrap();
+    let mut progressive = Progressive::new();
+    progressive.first_block = 1;
+    progressive.max_samples = 1024;
+    let tracer = PathTracer { next_event: false, ..PathTracer::new() };
+    while !progressive.is_done() {
+        pathtracer::render_progressive(&mut canvas, &scene, &tracer, &mut progressive);
+    }
+    assert!((sampled - middle_red(&canvas)).abs() <= sampled * 0.1, "{} {}", sampled, middle_red(&canvas));
+}
+
+#[test]
+fn emission_is_seen_directly() {
+    let scene = furnace(Material::Emissive { radiance: Rgb::new(0.5, 2.0, 0.0) }, Rgb::BLACK);
+    let canvas = render(&scene, &PathTracer::new(), (8, 8));
+    assert_eq!(canvas.get((4, 4)), Color { r: 128, g: 255, b: 0, a: 255 });
+    assert_eq!(canvas.get((0, 0)), Color { r: 0, g: 0, b: 0, a: 255 });
+}
+
+#[test]
+fn seeds_reproduce_renders() {
+    let scene = Scene::demo();
+    let tracer = PathTracer { samples: 4, seed: 1, ..PathTracer::new() };
+    let image = render(&scene, &tracer, (32, 18));
+    assert_eq!(image.pixels(), render(&scene, &tracer, (32, 18)).pixels());
+    assert_ne!(image.pixels(), render(&scene, &PathTracer { seed: 2, ..tracer }, (32, 18)).pixels());
+
+    let progressive = |seed| {
+        let mut canvas = Canvas::new(32, 18).unwrap();
+        let mut progressive = Progressive::new();
+        progressive.first_block = 1;
+        for _ in 0..4 {
+            pathtracer::render_progressive(&mut canvas, &scene, &PathTracer { seed, ..tracer }, &mut progressive);
+        }
+        canvas.pixels().to_vec()
+    };
+    assert_eq!(progressive(1), progressive(1));
+    assert_ne!(progressive(1), progressive(2));
+}
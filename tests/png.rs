@@ -0,0 +1,192 @@
+mod common;
+
+use gfx::{
+    canvas::{Canvas, Color},
+    image::PngError,
+};
+
+/// CRC-32 of PNG chunks, bit by bit.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// PNG of the already filtered scanlines `raw`, with the chunks of `extra` before IDAT.
+fn png(width: u32, height: u32, bit_depth: u8, color_type: u8, interlaced: bool, extra: &[(&[u8; 4], &[u8])], raw: &[u8]) -> Vec<u8> {
+    let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    let mut ihdr = Vec::new();
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    ihdr.extend_from_slice(&[bit_depth, color_type, 0, 0, interlaced as u8]);
+    chunk(&mut png, b"IHDR", &ihdr);
+    for (chunk_type, data) in extra {
+        chunk(&mut png, chunk_type, data);
+    }
+    chunk(&mut png, b"IDAT", &miniz_oxide::deflate::compress_to_vec_zlib(raw, 6));
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn read(png: &[u8]) -> Result<Canvas, PngError> {
+    Canvas::read_png(png)
+}
+
+fn gray(v: u8) -> Color {
+    Color { r: v, g: v, b: v, a: 255 }
+}
+
+fn corrupt(result: Result<Canvas, PngError>, expected: &str) {
+    match result {
+        Err(PngError::Corrupt(msg)) => assert!(msg.contains(expected), "{}", msg),
+        other => panic!("{:?}", other.map(|canvas| canvas.pixels().to_vec())),
+    }
+}
+
+#[test]
+fn round_trip() {
+    let mut seed = 1_u32;
+    let mut noise = || {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (seed >> 24) as u8
+    };
+    // noise, flat areas and gradients, so the writer picks every filter
+    let canvas = common::canvas(37, 23, |x, y| match (x / 10 + y / 8) % 3 {
+        0 => Color { r: noise(), g: noise(), b: noise(), a: noise() },
+        1 => Color { r: 9, g: 99, b: 199, a: 255 },
+        _ => Color { r: (x * 7) as u8, g: (y * 11) as u8, b: (x * y) as u8, a: 128 },
+    });
+    let mut data = Vec::new();
+    canvas.write_png(&mut data).unwrap();
+    assert_eq!(read(&data).unwrap().pixels(), canvas.pixels());
+}
+
+#[test]
+fn grayscale() {
+    let canvas = read(&png(2, 1, 8, 0, false, &[], &[0, 0, 255])).unwrap();
+    assert_eq!(canvas.pixels(), [gray(0), gray(255)]);
+
+    // 16 bits are truncated
+    let canvas = read(&png(1, 1, 16, 0, false, &[], &[0, 0x12, 0x34])).unwrap();
+    assert_eq!(canvas.pixels(), [gray(0x12)]);
+
+    // 2 bits are scaled to the full range
+    let canvas = read(&png(4, 1, 2, 0, false, &[], &[0, 0b00_01_10_11])).unwrap();
+    assert_eq!(canvas.pixels(), [gray(0), gray(85), gray(170), gray(255)]);
+
+    // 1 bit across two bytes, the rest of the last one is padding
+    let canvas = read(&png(10, 1, 1, 0, false, &[], &[0, 0b1010_1010, 0b1100_0000])).unwrap();
+    let expected: Vec<Color> = [1, 0, 1, 0, 1, 0, 1, 0, 1, 1].iter().map(|&bit| gray(bit * 255)).collect();
+    assert_eq!(canvas.pixels(), expected.as_slice());
+
+    // tRNS is the color that is transparent
+    let canvas = read(&png(2, 1, 8, 0, false, &[(b"tRNS", &[0, 7])], &[0, 7, 8])).unwrap();
+    assert_eq!(canvas.pixels(), [Color { a: 0, ..gray(7) }, gray(8)]);
+
+    let canvas = read(&png(2, 1, 8, 4, false, &[], &[0, 50, 128, 60, 255])).unwrap();
+    assert_eq!(canvas.pixels(), [Color { a: 128, ..gray(50) }, gray(60)]);
+}
+
+#[test]
+fn palette() {
+    let plte: &[u8] = &[255, 0, 0, 0, 255, 0, 0, 0, 255];
+    // 4-bit indices 0, 1 and 2, tRNS shorter than the palette
+    let canvas = read(&png(3, 1, 4, 3, false, &[(b"PLTE", plte), (b"tRNS", &[0, 100])], &[0, 0x01, 0x20])).unwrap();
+    assert_eq!(canvas.pixels(), [
+        Color { r: 255, g: 0, b: 0, a: 0 },
+        Color { r: 0, g: 255, b: 0, a: 100 },
+        Color { r: 0, g: 0, b: 255, a: 255 },
+    ]);
+
+    corrupt(read(&png(1, 1, 8, 3, false, &[(b"PLTE", plte)], &[0, 3])), "palette index 3");
+    corrupt(read(&png(1, 1, 8, 3, false, &[], &[0, 0])), "without PLTE");
+    corrupt(read(&png(1, 1, 8, 3, false, &[(b"PLTE", plte), (b"tRNS", &[0; 4])], &[0, 0])), "longer than palette");
+}
+
+#[test]
+fn true_color() {
+    let canvas = read(&png(2, 1, 8, 2, false, &[(b"tRNS", &[0, 1, 0, 2, 0, 3])], &[0, 1, 2, 3, 4, 5, 6])).unwrap();
+    assert_eq!(canvas.pixels(), [Color { r: 1, g: 2, b: 3, a: 0 }, Color { r: 4, g: 5, b: 6, a: 255 }]);
+
+    let canvas = read(&png(1, 1, 16, 6, false, &[], &[0, 0x10, 0xff, 0x20, 0xff, 0x30, 0xff, 0x40, 0xff])).unwrap();
+    assert_eq!(canvas.pixels(), [Color { r: 0x10, g: 0x20, b: 0x30, a: 0x40 }]);
+    corrupt(read(&png(1, 1, 8, 6, false, &[(b"tRNS", &[0; 6])], &[0, 1, 2, 3, 4])), "image with alpha");
+}
+
+#[test]
+fn filters() {
+    // rows of 2 gray pixels, each with another filter, all decoding to 10 20
+    let raw = [
+        1, 10, 10, // sub
+        2, 0, 0, // up
+        3, 5, 5, // average: 10 = 5 + (0 + 10) / 2, 20 = 5 + (10 + 20) / 2
+        4, 0, 0, // paeth
+    ];
+    let canvas = read(&png(2, 4, 8, 0, false, &[], &raw)).unwrap();
+    assert_eq!(canvas.pixels(), [gray(10), gray(20)].repeat(4).as_slice());
+    corrupt(read(&png(1, 1, 8, 0, false, &[], &[5, 0])), "unknown filter type 5");
+}
+
+#[test]
+fn adam7() {
+    // 3 by 3, so passes 2 and 3 are empty
+    let value = |x: usize, y: usize| (10 * y + x + 1) as u8;
+    let raw = [
+        0, value(0, 0), // pass 1
+        0, value(2, 0), // pass 4
+        0, value(0, 2), value(2, 2), // pass 5
+        0, value(1, 0), 0, value(1, 2), // pass 6
+        0, value(0, 1), value(1, 1), value(2, 1), // pass 7
+    ];
+    let canvas = read(&png(3, 3, 8, 0, true, &[], &raw)).unwrap();
+    assert_eq!(canvas.pixels(), common::canvas(3, 3, |x, y| gray(value(x, y))).pixels());
+}
+
+#[test]
+fn damaged() {
+    let good = png(2, 1, 8, 0, false, &[], &[0, 0, 255]);
+    assert!(read(&good).is_ok());
+
+    let mut signature = good.clone();
+    signature[1] = b'Q';
+    corrupt(read(&signature), "signature");
+
+    // last byte of the IHDR CRC
+    let mut crc = good.clone();
+    crc[8 + 8 + 13 + 3] ^= 1;
+    corrupt(read(&crc), "CRC mismatch in IHDR");
+
+    corrupt(read(&good[..good.len() - 20]), "unexpected end of file");
+    corrupt(read(&png(2, 2, 8, 0, false, &[], &[0, 0, 255])), "image data is 3 bytes, expected 6");
+    corrupt(read(&png(2, 1, 8, 0, false, &[], &[0, 0, 255, 7])), "image data");
+    corrupt(read(&png(0, 1, 8, 0, false, &[], &[0])), "dimensions");
+    corrupt(read(&png(1, 1, 4, 2, false, &[], &[0, 0])), "bit depth 4");
+    match read(&png(1, 1, 8, 0, false, &[(b"ABCD", &[])], &[0, 0])) {
+        Err(PngError::Unsupported(msg)) => assert!(msg.contains("ABCD"), "{}", msg),
+        other => panic!("{:?}", other.map(|_| ())),
+    }
+    // ancillary chunks are skipped
+    assert!(read(&png(1, 1, 8, 0, false, &[(b"tEXt", b"a\0b")], &[0, 0])).is_ok());
+}
+
+#[test]
+fn huge_header_is_not_allocated() {
+    // claims 4 GB of pixels with a few bytes of data
+    let data = png(1 << 15, 1 << 15, 8, 6, false, &[], &[0; 64]);
+    assert!(data.len() < 200);
+    corrupt(read(&data), "image data");
+}
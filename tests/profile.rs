@@ -0,0 +1,164 @@
+use std::{sync::Mutex, thread, time::Duration};
+use gfx::{
+    canvas::{Canvas, Color, Rect},
+    profile::{self, Capture, FlameView, Frame, Span},
+    profile_scope,
+};
+
+/// Profiler state is global, tests that record must not run at the same time.
+static PROFILER: Mutex<()> = Mutex::new(());
+
+fn recording() -> std::sync::MutexGuard<'static, ()> {
+    let guard = PROFILER.lock().unwrap_or_else(|e| e.into_inner());
+    profile::set_enabled(true);
+    profile::finish_frame();
+    guard
+}
+
+#[test]
+fn nested_scopes_and_threads() {
+    let _recording = recording();
+
+    {
+        profile_scope!("outer");
+        thread::sleep(Duration::from_millis(2));
+        {
+            profile_scope!("inner");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+    thread::Builder::new()
+        .name("worker".to_owned())
+        .spawn(|| {
+            profile_scope!("work");
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+
+    let frame = profile::finish_frame();
+    let find = |name: &str| frame.spans.iter().find(|span| span.name == name).unwrap().clone();
+    let (outer, inner, work) = (find("outer"), find("inner"), find("work"));
+    assert_eq!(frame.spans.len(), 3);
+
+    assert_eq!((outer.depth, inner.depth, work.depth), (0, 1, 0));
+    assert_eq!(outer.thread, inner.thread);
+    assert_ne!(outer.thread, work.thread);
+    assert!(outer.start <= inner.start && inner.end() <= outer.end());
+    assert!(outer.duration >= Duration::from_millis(3));
+    assert!(frame.start <= outer.start && work.end() <= frame.end);
+
+    let worker = frame.threads.iter().find(|(id, _)| *id == work.thread).unwrap();
+    assert_eq!(worker.1, "worker");
+    assert_eq!(frame.threads.len(), 2);
+
+    let next = profile::finish_frame();
+    assert_eq!(next.index, frame.index + 1);
+    assert_eq!(next.start, frame.end);
+    assert!(next.spans.is_empty());
+}
+
+#[test]
+fn disabled_records_nothing() {
+    let _recording = recording();
+    profile::set_enabled(false);
+    {
+        profile_scope!("ignored");
+    }
+    assert!(profile::finish_frame().spans.is_empty());
+}
+
+#[test]
+fn spans_are_capped() {
+    let _recording = recording();
+
+    {
+        profile_scope!("outer");
+        for _ in 0..profile::MAX_SPANS + 10 {
+            profile_scope!("inner");
+        }
+    }
+    for _ in 0..5 {
+        profile_scope!("after");
+    }
+
+    let frame = profile::finish_frame();
+    assert_eq!(frame.spans.len(), profile::MAX_SPANS);
+    // the outer scope finished last, when its inner ones filled the buffer
+    assert!(frame.spans.iter().all(|span| span.name == "inner"));
+    assert_eq!(frame.dropped, 10 + 1 + 5);
+
+    {
+        profile_scope!("next");
+    }
+    let frame = profile::finish_frame();
+    assert_eq!((frame.spans.len(), frame.dropped), (1, 0));
+}
+
+#[test]
+fn chrome_trace() {
+    let _recording = recording();
+    let mut capture = Capture::new();
+    for _ in 0..2 {
+        {
+            profile_scope!("say \"hi\"\n");
+        }
+        capture.push(profile::finish_frame());
+    }
+
+    let mut json = Vec::new();
+    capture.write_chrome_trace(&mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+
+    assert!(json.starts_with("{\"displayTimeUnit\":\"ms\",\"traceEvents\":["));
+    assert!(json.ends_with("]}\n"));
+    assert_eq!(json.matches("\"ph\":\"X\"").count(), 2);
+    assert_eq!(json.matches("\"ph\":\"i\"").count(), 2);
+    // thread names are written once
+    assert_eq!(json.matches("\"ph\":\"M\"").count(), 1);
+    assert!(json.contains("\"name\":\"say \\\"hi\\\"\\n\""));
+    assert_eq!(json.matches('{').count(), json.matches('}').count());
+}
+
+fn span(name: &'static str, depth: usize, start_ms: u64, duration_ms: u64) -> Span {
+    Span {
+        name,
+        thread: 0,
+        depth,
+        start: Duration::from_millis(start_ms),
+        duration: Duration::from_millis(duration_ms),
+    }
+}
+
+#[test]
+fn flame_view() {
+    let frame = Frame {
+        index: 0,
+        start: Duration::from_millis(100),
+        end: Duration::from_millis(110),
+        spans: vec![span("a", 0, 100, 10), span("b", 1, 100, 5)],
+        threads: vec![(0, "main".to_owned())],
+        dropped: 0,
+    };
+
+    let background = Color { r: 0, g: 0, b: 0, a: 255 };
+    let mut canvas = Canvas::new(120, 60).unwrap();
+    let rect = Rect::new((10, 10), (100, 40));
+    let view = FlameView { background, ..FlameView::new(rect) };
+    view.draw(&mut canvas, &frame);
+
+    let row = view.row_height;
+    assert_eq!(canvas.get((60, 15)), FlameView::color("a"));
+    assert_eq!(canvas.get((40, 10 + row + 5)), FlameView::color("b"));
+    // "b" takes the first half of the frame
+    assert_eq!(canvas.get((80, 10 + row + 5)), background);
+    assert_ne!(FlameView::color("a"), FlameView::color("b"));
+
+    for y in 0..canvas.height() {
+        for x in 0..canvas.width() {
+            if !rect.contains((x as isize, y as isize)) {
+                assert_eq!(canvas.get((x, y)), Color { r: 0, g: 0, b: 0, a: 0 });
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use gfx::{
+    canvas::{Canvas, Color},
+    math::Num,
+    progressive::Progressive,
+    raytracer::{self, Scene},
+    sampling::{Filter, Pattern, Supersampling},
+};
+
+const WHITE: Color = Color { r: 255, g: 255, b: 255, a: 255 };
+const BLACK: Color = Color { r: 0, g: 0, b: 0, a: 255 };
+
+fn gray(value: u8) -> Color {
+    Color { r: value, g: value, b: value, a: 255 }
+}
+
+/// Gradient that tells where it was sampled, red by `x` and green by `y`.
+fn position((x, y): (Num, Num)) -> [Num; 3] {
+    [x.floor() / 255.0, y.floor() / 255.0, 0.0]
+}
+
+/// Color of `position` at `(x, y)`.
+fn at((x, y): (Num, Num)) -> Color {
+    Color { r: x as u8, g: y as u8, b: 0, a: 255 }
+}
+
+#[test]
+fn coarse_blocks_first() {
+    let mut canvas = Canvas::new(20, 10).unwrap();
+    let mut progressive = Progressive::new();
+
+    // 3 by 2 blocks of 8, cut at the edges
+    assert_eq!(progressive.render(&mut canvas, position), 6);
+    assert_eq!(canvas.get((0, 0)), at((4.0, 4.0)));
+    assert_eq!(canvas.get((7, 7)), at((4.0, 4.0)));
+    assert_eq!(canvas.get((19, 9)), at((18.0, 9.0)));
+    assert_eq!(progressive.samples(), 0);
+
+    assert_eq!(progressive.render(&mut canvas, position), 5 * 3);
+    assert_eq!(canvas.get((5, 3)), at((6.0, 2.0)));
+    assert_eq!(progressive.render(&mut canvas, position), 10 * 5);
+    assert_eq!(progressive.samples(), 0);
+
+    // then a sample per pixel every frame
+    assert_eq!(progressive.render(&mut canvas, position), 20 * 10);
+    assert_eq!(progressive.samples(), 1);
+    assert_eq!(canvas.get((5, 3)), at((5.5, 3.5)));
+    assert_eq!(progressive.render(&mut canvas, position), 20 * 10);
+    assert_eq!(progressive.samples(), 2);
+}
+
+#[test]
+fn accumulates_and_resets() {
+    let mut canvas = Canvas::new(4, 4).unwrap();
+    let mut progressive = Progressive::new();
+    progressive.first_block = 1;
+
+    // alternating black and white frames average out
+    progressive.render(&mut canvas, |_| [1.0; 3]);
+    assert_eq!(canvas.pixels(), [WHITE; 16]);
+    progressive.render(&mut canvas, |_| [0.0; 3]);
+    assert_eq!(canvas.pixels(), [gray(128); 16]);
+    progressive.render(&mut canvas, |_| [1.0; 3]);
+    assert_eq!(canvas.pixels(), [gray(170); 16]);
+
+    progressive.reset();
+    assert_eq!(progressive.samples(), 0);
+    progressive.render(&mut canvas, |_| [7.0 / 255.0; 3]);
+    assert_eq!(canvas.pixels(), [gray(7); 16]);
+    assert_eq!(progressive.samples(), 1);
+
+    // resizing starts over, with blocks again
+    progressive.first_block = 2;
+    let mut canvas = Canvas::new(4, 2).unwrap();
+    assert_eq!(progressive.render(&mut canvas, |_| [9.0 / 255.0; 3]), 2);
+    assert_eq!(progressive.samples(), 0);
+}
+
+#[test]
+fn converges_and_stops() {
+    // a vertical edge a quarter into the pixels of column 1
+    let edge = |(x, _): (Num, Num)| if x < 1.25 { [1.0; 3] } else { [0.0; 3] };
+    let mut canvas = Canvas::new(3, 1).unwrap();
+    let mut progressive = Progressive::new();
+    progressive.first_block = 1;
+    progressive.max_samples = 64;
+    while !progressive.is_done() {
+        assert_eq!(progressive.render(&mut canvas, edge), 3);
+    }
+    assert_eq!(progressive.samples(), 64);
+    assert_eq!(canvas.get((0, 0)), WHITE);
+    assert!(canvas.get((1, 0)).r.abs_diff(64) <= 4, "{:?}", canvas.get((1, 0)));
+    assert_eq!(canvas.get((2, 0)), BLACK);
+
+    // done, frames only show the image, over whatever was drawn
+    let image = canvas.pixels().to_vec();
+    canvas.set((0, 0), gray(3));
+    assert_eq!(progressive.render(&mut canvas, |_| unreachable!()), 0);
+    assert_eq!(canvas.pixels(), image.as_slice());
+}
+
+#[test]
+fn unclamped_average() {
+    // a sample 4 times too bright among black ones still counts fully
+    let mut canvas = Canvas::new(1, 1).unwrap();
+    let mut progressive = Progressive::new();
+    progressive.first_block = 1;
+    for frame in 0..8 {
+        progressive.render(&mut canvas, |_| if frame == 0 { [4.0, 0.0, -1.0] } else { [0.0; 3] });
+    }
+    assert_eq!(canvas.get((0, 0)), Color { r: 128, g: 0, b: 0, a: 255 });
+}
+
+#[test]
+fn grids_spread_early_samples() {
+    for &pattern in &[Pattern::Grid, Pattern::Jittered] {
+        // the first frames of a 32 by 32 grid are all over the pixels, not in its top rows
+        let mut canvas = Canvas::new(8, 8).unwrap();
+        let mut progressive = Progressive::new();
+        progressive.pattern = pattern;
+        progressive.first_block = 1;
+        let mut offsets = Vec::new();
+        for _ in 0..4 {
+            progressive.render(&mut canvas, |(x, y)| {
+                offsets.push((x.fract(), y.fract()));
+                [0.0; 3]
+            });
+        }
+        let count = offsets.len() as Num;
+        let mean = offsets.iter().fold((0.0, 0.0), |(x, y), &(dx, dy)| (x + dx / count, y + dy / count));
+        assert!((mean.0 - 0.5).abs() < 0.1 && (mean.1 - 0.5).abs() < 0.1, "{:?} {:?}", pattern, mean);
+
+        // and still one in every cell once all are taken
+        let mut canvas = Canvas::new(1, 1).unwrap();
+        progressive.max_samples = 16;
+        progressive.reset();
+        let mut cells = Vec::new();
+        while !progressive.is_done() {
+            progressive.render(&mut canvas, |(x, y)| {
+                cells.push(((y * 4.0) as usize, (x * 4.0) as usize));
+                [0.0; 3]
+            });
+        }
+        cells.sort_unstable();
+        assert_eq!(cells, (0..4).flat_map(|y| (0..4).map(move |x| (y, x))).collect::<Vec<_>>(), "{:?}", pattern);
+    }
+}
+
+#[test]
+fn ray_traced_scene() {
+    let scene = Scene::demo();
+    let mut canvas = Canvas::new(64, 36).unwrap();
+    let mut progressive = Progressive::new();
+    progressive.max_samples = 64;
+    for _ in 0..3 {
+        assert!(raytracer::render_progressive(&mut canvas, &scene, &mut progressive) <= 64 * 36 / 4);
+    }
+    while !progressive.is_done() {
+        assert_eq!(raytracer::render_progressive(&mut canvas, &scene, &mut progressive), 64 * 36);
+    }
+
+    // converges to the average over the pixel, as supersampling with a box filter
+    let mut supersampled = Canvas::new(64, 36).unwrap();
+    let settings = Supersampling { pattern: Pattern::Halton, samples: 64, filter: Filter::BOX, ..Supersampling::new() };
+    raytracer::render_supersampled(&mut supersampled, &scene, &settings);
+    for (a, b) in canvas.pixels().iter().zip(supersampled.pixels()) {
+        assert!(a.r.abs_diff(b.r) <= 8 && a.g.abs_diff(b.g) <= 8 && a.b.abs_diff(b.b) <= 8, "{:?} {:?}", a, b);
+    }
+}
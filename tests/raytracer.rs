@@ -0,0 +1,35 @@
+use gfx::{
+    canvas::Canvas,
+    golden::Golden,
+    raytracer::{self, Scene},
+    sampling::{Adaptive, Filter, Supersampling},
+};
+
+fn golden() -> Golden {
+    Golden {
+        // float math may round differently between platforms and compilers
+        channel_tolerance: 2,
+        max_differing_pixels: 16,
+        ..Golden::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden"))
+    }
+}
+
+#[test]
+fn demo_scene() {
+    let mut canvas = Canvas::new(320, 180).unwrap();
+    raytracer::render(&mut canvas, &Scene::demo());
+    golden().assert("demo_scene", &canvas);
+}
+
+#[test]
+fn demo_scene_supersampled() {
+    let mut canvas = Canvas::new(320, 180).unwrap();
+    let settings = Supersampling {
+        filter: Filter::MITCHELL,
+        adaptive: Some(Adaptive { initial: 4, threshold: 8 }),
+        ..Supersampling::new()
+    };
+    let rays = raytracer::render_supersampled(&mut canvas, &Scene::demo(), &settings);
+    assert!(rays < 320 * 180 * 8, "{}", rays);
+    golden().assert("demo_scene_supersampled", &canvas);
+}
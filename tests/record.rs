@@ -0,0 +1,112 @@
+mod common;
+
+use std::time::Duration;
+use gfx::{
+    canvas::Canvas,
+    metrics,
+    record::{GifRecorder, Recorder, Y4mRecorder},
+};
+use common::{canvas, rgb};
+
+/// Decodes GIF into RGBA frames with their delays.
+fn decode_gif(data: &[u8]) -> Vec<(Vec<u8>, u16)> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(data).unwrap();
+
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        frames.push((frame.buffer.to_vec(), frame.delay));
+    }
+    frames
+}
+
+fn max_diff(canvas: &Canvas, rgba: &[u8]) -> u8 {
+    canvas.pixels()
+        .iter()
+        .zip(rgba.chunks(4))
+        .flat_map(|(c, p)| vec![c.r.abs_diff(p[0]), c.g.abs_diff(p[1]), c.b.abs_diff(p[2])])
+        .max()
+        .unwrap()
+}
+
+#[test]
+fn gif_frames_and_delays() {
+    let few_colors = canvas(40, 30, |x, y| if (x / 5 + y / 5) % 2 == 0 { rgb(255, 0, 0) } else { rgb(0, 0, 255) });
+    let gradient = canvas(40, 30, |x, y| rgb((x * 6) as u8, (y * 8) as u8, ((x + y) * 3) as u8));
+
+    let mut data = Vec::new();
+    let mut recorder = GifRecorder::new(&mut data, 40, 30).unwrap();
+    recorder.frame(&few_colors, Duration::from_millis(40)).unwrap();
+    recorder.frame(&gradient, Duration::from_millis(15)).unwrap();
+    recorder.frame(&gradient, Duration::from_millis(15)).unwrap();
+    recorder.finish().unwrap();
+
+    let frames = decode_gif(&data);
+    assert_eq!(frames.len(), 3);
+
+    // rounding to hundredths of a second does not accumulate: 40, 55 and 70 ms in total
+    assert_eq!(frames.iter().map(|&(_, delay)| delay).collect::<Vec<_>>(), [4, 2, 1]);
+
+    assert_eq!(max_diff(&few_colors, &frames[0].0), 0);
+    let decoded = canvas(40, 30, |x, y| {
+        let p = &frames[1].0[(x + y * 40) * 4..];
+        rgb(p[0], p[1], p[2])
+    });
+    assert!(max_diff(&gradient, &frames[1].0) <= 16);
+    assert!(metrics::psnr(&gradient, &decoded) > 33.0);
+}
+
+#[test]
+fn gif_lzw_dictionary_resets() {
+    // noise of 200 exact colors fills LZW dictionary many times over
+    let mut seed = 1u32;
+    let mut noise = || {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (seed >> 16) as usize % 200
+    };
+    let frame = canvas(300, 200, |_, _| {
+        let i = noise();
+        rgb(i as u8, (i * 7) as u8, 255 - i as u8)
+    });
+
+    let mut data = Vec::new();
+    let mut recorder = GifRecorder::new(&mut data, 300, 200).unwrap();
+    recorder.frame(&frame, Duration::from_millis(100)).unwrap();
+    recorder.finish().unwrap();
+
+    let frames = decode_gif(&data);
+    assert_eq!(frames.len(), 1);
+    assert_eq!(max_diff(&frame, &frames[0].0), 0);
+}
+
+#[test]
+fn wrong_frame_size() {
+    let mut recorder = GifRecorder::new(Vec::new(), 4, 4).unwrap();
+    let error = recorder.frame(&Canvas::new(4, 5).unwrap(), Duration::from_millis(10)).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn y4m_repeats_and_drops_frames() {
+    let white = canvas(4, 2, |_, _| rgb(255, 255, 255));
+    let black = canvas(4, 2, |_, _| rgb(0, 0, 0));
+
+    let mut data = Vec::new();
+    let mut recorder = Y4mRecorder::new(&mut data, 4, 2, 10).unwrap();
+    recorder.frame(&white, Duration::from_millis(300)).unwrap(); // 3 frames
+    recorder.frame(&black, Duration::from_millis(20)).unwrap(); // dropped
+    recorder.frame(&black, Duration::from_millis(100)).unwrap(); // 1 frame
+    recorder.finish().unwrap();
+
+    let header = b"YUV4MPEG2 W4 H2 F10:1 Ip A1:1 C444 XCOLORRANGE=LIMITED\n";
+    assert!(data.starts_with(header));
+
+    let frames: Vec<&[u8]> = data[header.len()..].chunks(6 + 4 * 2 * 3).collect();
+    assert_eq!(frames.len(), 4);
+    for (frame, luma) in frames.iter().zip(&[235, 235, 235, 16]) {
+        assert_eq!(&frame[..6], b"FRAME\n");
+        assert!(frame[6..14].iter().all(|y| y == luma));
+        assert!(frame[14..].iter().all(|&uv| uv == 128));
+    }
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use gfx::{
+    backend::{Backend, scripted::Scripted},
+    canvas::{Canvas, Color, Rect},
+    input::Event,
+    resize::{blit, Presenter, ResizePolicy, Viewport},
+};
+
+const BLACK: Color = Color { r: 0, g: 0, b: 0, a: 255 };
+
+fn numbered(width: usize, height: usize) -> Canvas {
+    common::canvas(width, height, |x, y| Color { r: x as u8, g: y as u8, b: 1, a: 255 })
+}
+
+#[test]
+fn canvas_size() {
+    assert_eq!(ResizePolicy::Reallocate.canvas_size((320, 180), (800, 600)), (800, 600));
+    // minimized
+    assert_eq!(ResizePolicy::Reallocate.canvas_size((320, 180), (0, 0)), (320, 180));
+    assert_eq!(ResizePolicy::IntegerScale.canvas_size((320, 180), (800, 600)), (320, 180));
+    assert_eq!(ResizePolicy::Letterbox.canvas_size((320, 180), (800, 600)), (320, 180));
+}
+
+#[test]
+fn viewports() {
+    let rect = |policy: ResizePolicy, canvas, window| policy.viewport(canvas, window).rect;
+
+    assert_eq!(rect(ResizePolicy::Reallocate, (800, 600), (800, 600)), Rect::new((0, 0), (800, 600)));
+    // before the canvas is reallocated it is shown unscaled
+    assert_eq!(rect(ResizePolicy::Reallocate, (800, 600), (400, 900)), Rect::new((0, 0), (800, 600)));
+
+    // largest whole factor, centered
+    assert_eq!(rect(ResizePolicy::IntegerScale, (320, 180), (1280, 720)), Rect::new((0, 0), (1280, 720)));
+    assert_eq!(rect(ResizePolicy::IntegerScale, (320, 180), (1000, 700)), Rect::new((20, 80), (960, 540)));
+    assert_eq!(rect(ResizePolicy::IntegerScale, (320, 180), (1000, 500)), Rect::new((180, 70), (640, 360)));
+    // smaller window than canvas, unscaled and cropped
+    assert_eq!(rect(ResizePolicy::IntegerScale, (320, 180), (100, 100)), Rect::new((-110, -40), (320, 180)));
+
+    // bars on the sides or at the top and bottom
+    assert_eq!(rect(ResizePolicy::Letterbox, (320, 180), (1000, 700)), Rect::new((0, 68), (1000, 563)));
+    assert_eq!(rect(ResizePolicy::Letterbox, (320, 180), (1000, 500)), Rect::new((55, 0), (889, 500)));
+    assert_eq!(rect(ResizePolicy::Letterbox, (320, 180), (160, 90)), Rect::new((0, 0), (160, 90)));
+    assert_eq!(rect(ResizePolicy::Letterbox, (1000, 1), (10, 10)), Rect::new((0, 4), (10, 1)));
+
+    for policy in [ResizePolicy::Reallocate, ResizePolicy::IntegerScale, ResizePolicy::Letterbox] {
+        assert!(rect(policy, (320, 180), (0, 600)).is_empty());
+        assert!(rect(policy, (0, 180), (800, 600)).is_empty());
+    }
+}
+
+#[test]
+fn coordinates() {
+    let viewport = ResizePolicy::IntegerScale.viewport((320, 180), (1000, 700));
+    assert_eq!(viewport, Viewport { rect: Rect::new((20, 80), (960, 540)), canvas: (320, 180) });
+    assert_eq!(viewport.to_canvas((20, 80)), (0, 0));
+    assert_eq!(viewport.to_canvas((22, 82)), (0, 0));
+    assert_eq!(viewport.to_canvas((23, 83)), (1, 1));
+    assert_eq!(viewport.to_canvas((979, 619)), (319, 179));
+    // outside, in the bars
+    assert_eq!(viewport.to_canvas((19, 79)), (-1, -1));
+    assert_eq!(viewport.to_canvas((0, 0)), (-7, -27));
+    assert_eq!(viewport.to_window((1, 1)), (23, 83));
+    assert_eq!(viewport.to_window((320, 180)), (980, 620));
+
+    let viewport = ResizePolicy::Letterbox.viewport((320, 180), (160, 90));
+    assert_eq!(viewport.to_canvas((1, 1)), (2, 2));
+    assert_eq!(viewport.to_window((3, 3)), (1, 1));
+
+    // nothing is shown yet
+    let viewport = ResizePolicy::Letterbox.viewport((320, 180), (0, 0));
+    assert_eq!(viewport.to_canvas((5, 6)), (5, 6));
+}
+
+#[test]
+fn blit_scales_and_clears() {
+    let canvas = numbered(2, 2);
+    let mut target = numbered(7, 5);
+    let viewport = ResizePolicy::IntegerScale.viewport((2, 2), (7, 5));
+    assert_eq!(viewport.rect, Rect::new((1, 0), (4, 4)));
+    blit(&canvas, &viewport, &mut target);
+
+    let pixel = |x, y| Color { r: x, g: y, b: 1, a: 255 };
+    let expected = [
+        [BLACK, pixel(0, 0), pixel(0, 0), pixel(1, 0), pixel(1, 0), BLACK, BLACK],
+        [BLACK, pixel(0, 0), pixel(0, 0), pixel(1, 0), pixel(1, 0), BLACK, BLACK],
+        [BLACK, pixel(0, 1), pixel(0, 1), pixel(1, 1), pixel(1, 1), BLACK, BLACK],
+        [BLACK, pixel(0, 1), pixel(0, 1), pixel(1, 1), pixel(1, 1), BLACK, BLACK],
+        [BLACK; 7],
+    ];
+    assert_eq!(target.pixels(), expected.concat().as_slice());
+
+    // cropped when the canvas is larger than the target
+    let canvas = numbered(4, 4);
+    let mut target = numbered(2, 2);
+    blit(&canvas, &ResizePolicy::IntegerScale.viewport((4, 4), (2, 2)), &mut target);
+    assert_eq!(target.pixels(), [pixel(1, 1), pixel(2, 1), pixel(1, 2), pixel(2, 2)]);
+}
+
+#[test]
+fn presenter() {
+    let mut backend = Scripted::new((8, 4), vec![
+        vec![Event::MouseMove { x: 5, y: 3 }],
+        vec![Event::Resize { width: 4, height: 4 }],
+    ]);
+    let mut canvas = numbered(4, 2);
+
+    let mut presenter = Presenter::new(ResizePolicy::IntegerScale);
+    assert!(!presenter.fit(&mut canvas, backend.size()).unwrap());
+    presenter.present(&mut backend, &canvas).unwrap();
+    assert_eq!(backend.last_frame().len(), 8 * 4);
+    assert_eq!(backend.last_frame()[3 * 8 + 5], canvas.get((2, 1)));
+
+    let mut events = Vec::new();
+    backend.poll_events(&mut events);
+    presenter.map_events(&mut events);
+    assert_eq!(events, [Event::MouseMove { x: 2, y: 1 }]);
+
+    // a canvas that fits the window goes to the backend as is
+    presenter.policy = ResizePolicy::Reallocate;
+    events.clear();
+    backend.poll_events(&mut events);
+    assert!(presenter.fit(&mut canvas, backend.size()).unwrap());
+    assert_eq!((canvas.width(), canvas.height()), (4, 4));
+    canvas.set((3, 3), Color { r: 9, g: 9, b: 9, a: 255 });
+    presenter.present(&mut backend, &canvas).unwrap();
+    assert_eq!(backend.last_frame(), canvas.pixels());
+    assert_eq!(presenter.viewport().rect, Rect::new((0, 0), (4, 4)));
+    assert_eq!(backend.presented(), 2);
+}
@@ -0,0 +1,168 @@
+use gfx::{
+    canvas::{Canvas, Color},
+    math::Num,
+    sampling::{self, Adaptive, Filter, Pattern, Rng, Supersampling},
+};
+
+const WHITE: Color = Color { r: 255, g: 255, b: 255, a: 255 };
+const BLACK: Color = Color { r: 0, g: 0, b: 0, a: 255 };
+
+fn settings(pattern: Pattern, samples: usize, filter: Filter) -> Supersampling {
+    Supersampling { pattern, samples, filter, ..Supersampling::new() }
+}
+
+/// Points of `pattern` in a single pixel.
+fn points(pattern: Pattern, samples: usize) -> Vec<(Num, Num)> {
+    let mut canvas = Canvas::new(1, 1).unwrap();
+    let mut points = Vec::new();
+    let taken = sampling::render(&mut canvas, &settings(pattern, samples, Filter::BOX), |point| {
+        points.push(point);
+        WHITE
+    });
+    assert_eq!(taken, points.len());
+    points
+}
+
+/// Whether `values` are `0..count` over `count`, shifted by the same amount around 1.
+fn evenly_spaced(mut values: Vec<Num>) -> bool {
+    let count = values.len() as Num;
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let shift = values[0];
+    values.iter().enumerate().all(|(i, value)| (value - shift - i as Num / count).abs() < 1e-4)
+}
+
+#[test]
+fn patterns() {
+    // cells in a shuffled order
+    let mut grid = points(Pattern::Grid, 4);
+    grid.sort_by(|a, b| (a.1, a.0).partial_cmp(&(b.1, b.0)).unwrap());
+    assert_eq!(grid, [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)]);
+    assert_eq!(Pattern::Grid.count(10), 9);
+    assert_eq!(Pattern::Jittered.count(0), 1);
+    assert_eq!(Pattern::Sobol.count(10), 10);
+
+    let mut cells: Vec<_> = points(Pattern::Jittered, 9).iter().map(|&(x, y)| ((y * 3.0) as usize, (x * 3.0) as usize)).collect();
+    cells.sort_unstable();
+    assert_eq!(cells, (0..3).flat_map(|y| (0..3).map(move |x| (y, x))).collect::<Vec<_>>());
+
+    // every row and column of a 16 by 16 grid gets one point
+    for pattern in [Pattern::Halton, Pattern::Sobol] {
+        let points = points(pattern, 16);
+        assert!(points.iter().all(|&(x, y)| (0.0..1.0).contains(&x) && (0.0..1.0).contains(&y)));
+        assert!(evenly_spaced(points.iter().map(|point| point.0).collect()), "{:?}", pattern);
+        if pattern == Pattern::Sobol {
+            assert!(evenly_spaced(points.iter().map(|point| point.1).collect()));
+        }
+    }
+    assert!(evenly_spaced(points(Pattern::Halton, 9).iter().map(|point| point.1).collect()));
+}
+
+#[test]
+fn filters() {
+    assert_eq!(Filter::BOX.weight((0.5, -0.5)), 1.0);
+    assert_eq!(Filter::BOX.weight((0.6, 0.0)), 0.0);
+    assert_eq!(Filter::TENT.weight((0.0, 0.0)), 1.0);
+    assert_eq!(Filter::TENT.weight((0.5, 0.0)), 0.5);
+    assert_eq!(Filter::TENT.weight((0.5, 0.5)), 0.25);
+
+    for filter in [Filter::GAUSSIAN, Filter::MITCHELL] {
+        let radius = filter.radius();
+        assert!(filter.weight((0.0, 0.0)) > filter.weight((radius / 2.0, 0.0)));
+        assert!(filter.weight((radius, 0.0)).abs() < 1e-5);
+        assert_eq!(filter.weight((radius + 0.1, 0.0)), 0.0);
+    }
+    // negative lobes
+    assert!(Filter::MITCHELL.weight((1.5, 0.0)) < 0.0);
+    assert!((Filter::MITCHELL.weight((0.0, 0.0)) - 64.0 / 81.0).abs() < 1e-5);
+}
+
+#[test]
+fn flat_color_stays_exact() {
+    let color = Color { r: 10, g: 120, b: 250, a: 255 };
+    for filter in [Filter::BOX, Filter::TENT, Filter::GAUSSIAN, Filter::MITCHELL] {
+        let mut canvas = Canvas::new(7, 5).unwrap();
+        sampling::render(&mut canvas, &settings(Pattern::Jittered, 4, filter), |_| color);
+        assert!(canvas.pixels().iter().all(|&pixel| pixel == color), "{:?}", filter);
+    }
+}
+
+#[test]
+fn samples_are_clamped_after_averaging() {
+    // a quarter of the pixel twice as bright as the canvas shows
+    let mut canvas = Canvas::new(1, 1).unwrap();
+    sampling::render_rgb(&mut canvas, &settings(Pattern::Grid, 4, Filter::BOX), |(x, y)| {
+        if x < 0.5 && y < 0.5 { [2.0, 0.0, -1.0] } else { [0.0; 3] }
+    });
+    assert_eq!(canvas.get((0, 0)), Color { r: 128, g: 0, b: 0, a: 255 });
+}
+
+#[test]
+fn edges_are_antialiased() {
+    let edge = |(x, _): (Num, Num)| if x < 2.3 { WHITE } else { BLACK };
+    let mut canvas = Canvas::new(4, 1).unwrap();
+    sampling::render(&mut canvas, &settings(Pattern::Grid, 16, Filter::BOX), edge);
+    // columns of the grid at 2.125, 2.375, 2.625 and 2.875
+    assert_eq!(canvas.pixels(), [WHITE, WHITE, Color { r: 64, g: 64, b: 64, a: 255 }, BLACK]);
+
+    // wider filters blur into the neighbors, as far as their radius
+    sampling::render(&mut canvas, &settings(Pattern::Grid, 16, Filter::TENT), edge);
+    let red: Vec<u8> = canvas.pixels().iter().map(|pixel| pixel.r).collect();
+    assert!(red[0] == 255 && red[1] < 255 && red[2] > 64 && red[3] == 0, "{:?}", red);
+}
+
+#[test]
+fn seeds() {
+    let noise = |(x, y): (Num, Num)| {
+        let value = ((x * 12.9898 + y * 78.233).sin() * 43758.547).fract().abs();
+        Color { r: (value * 255.0) as u8, g: 0, b: 0, a: 255 }
+    };
+    let render = |seed| {
+        let mut canvas = Canvas::new(8, 8).unwrap();
+        let settings = Supersampling { seed, ..settings(Pattern::Jittered, 4, Filter::BOX) };
+        sampling::render(&mut canvas, &settings, noise);
+        canvas.pixels().to_vec()
+    };
+    assert_eq!(render(1), render(1));
+    assert_ne!(render(1), render(2));
+
+    let mut a = Rng::new(7);
+    let mut b = Rng::new(7);
+    let values: Vec<u32> = (0..4).map(|_| a.next_u32()).collect();
+    assert_eq!(values, (0..4).map(|_| b.next_u32()).collect::<Vec<_>>());
+    assert_ne!(values[0], Rng::new(8).next_u32());
+    let mean = (0..10_000).map(|_| a.next_float()).sum::<Num>() / 10_000.0;
+    assert!((mean - 0.5).abs() < 0.01, "{}", mean);
+}
+
+#[test]
+fn adaptive_refines_edges_only() {
+    // a disk of radius 5 around the middle of a 32 by 32 canvas
+    let disk = |(x, y): (Num, Num)| {
+        if (x - 16.0).powi(2) + (y - 16.0).powi(2) < 25.0 { WHITE } else { BLACK }
+    };
+    let mut full = Canvas::new(32, 32).unwrap();
+    let all = sampling::render(&mut full, &settings(Pattern::Grid, 16, Filter::BOX), disk);
+    assert_eq!(all, 32 * 32 * 16);
+
+    let mut canvas = Canvas::new(32, 32).unwrap();
+    let adaptive = Supersampling {
+        adaptive: Some(Adaptive { initial: 1, threshold: 0 }),
+        ..settings(Pattern::Grid, 16, Filter::BOX)
+    };
+    let taken = sampling::render(&mut canvas, &adaptive, disk);
+
+    // the first pass is one sample per pixel, an aliased disk
+    let mut aliased = Canvas::new(32, 32).unwrap();
+    sampling::render(&mut aliased, &settings(Pattern::Grid, 1, Filter::BOX), disk);
+    let refined = sampling::contrasting(&aliased, 0);
+    assert!(!refined.is_empty() && refined.len() < 200, "{}", refined.len());
+    assert_eq!(taken, 32 * 32 + refined.len() * 16);
+    assert!(taken < all / 4);
+
+    // far from the edge nothing changes, near it the result is close to full supersampling
+    assert_eq!(canvas.get((0, 0)), BLACK);
+    assert_eq!(canvas.get((16, 16)), WHITE);
+    for (a, b) in canvas.pixels().iter().zip(full.pixels()) {
+        assert!(a.r.abs_diff(b.r) <= 16, "{:?} {:?}", a, b);
+    }
+}
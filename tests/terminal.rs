@@ -0,0 +1,163 @@
+mod common;
+
+use gfx::backend::{Backend, terminal::{Mode, Terminal}};
+use common::{canvas, rgb};
+
+/// Output since the previous call.
+fn take(terminal: &mut Terminal<Vec<u8>>) -> String {
+    String::from_utf8(std::mem::take(terminal.get_mut())).unwrap()
+}
+
+#[test]
+fn half_blocks() {
+    let mut terminal = Terminal::new(Vec::new(), Mode::HalfBlocks, (2, 1));
+    assert_eq!(terminal.size(), (2, 2));
+
+    // 4x4 scaled to 2x2, each half block the average of four pixels
+    let mut frame = canvas(4, 4, |_, y| if y < 2 { rgb(200, 0, 0) } else { rgb(0, 0, 100) });
+    frame.set((0, 0), rgb(0, 0, 0));
+    terminal.present(&frame).unwrap();
+    let out = take(&mut terminal);
+    assert!(out.starts_with("\x1b[?25l\x1b[0m\x1b[2J\x1b[1;1H"));
+    assert_eq!(out.matches('▀').count(), 2);
+    assert!(out.contains("\x1b[48;2;0;0;100m\x1b[38;2;150;0;0m▀"));
+    // the background stays, only the foreground changes
+    assert!(out.ends_with("\x1b[38;2;200;0;0m▀"));
+
+    terminal.present(&frame).unwrap();
+    assert_eq!(take(&mut terminal), "");
+
+    // only the changed cell, with the cursor moved to it
+    frame.set((3, 3), rgb(0, 0, 0));
+    frame.set((2, 3), rgb(0, 0, 0));
+    frame.set((3, 2), rgb(0, 0, 0));
+    frame.set((2, 2), rgb(0, 0, 0));
+    terminal.present(&frame).unwrap();
+    assert_eq!(take(&mut terminal), "\x1b[1;2H\x1b[48;2;0;0;0m▀");
+
+    // same colors in both halves
+    let mut terminal = Terminal::new(Vec::new(), Mode::HalfBlocks, (1, 1));
+    terminal.present(&canvas(1, 2, |_, _| rgb(1, 2, 3))).unwrap();
+    assert!(take(&mut terminal).ends_with("\x1b[1;1H\x1b[48;2;1;2;3m "));
+}
+
+#[test]
+fn resize_redraws() {
+    let mut terminal = Terminal::new(Vec::new(), Mode::HalfBlocks, (2, 1));
+    let frame = canvas(2, 2, |_, _| rgb(9, 9, 9));
+    terminal.present(&frame).unwrap();
+    take(&mut terminal);
+
+    terminal.cells = (1, 1);
+    terminal.present(&frame).unwrap();
+    let out = take(&mut terminal);
+    assert!(out.contains("\x1b[2J"));
+    assert_eq!(out.matches(' ').count(), 1);
+}
+
+/// Pixels of the first sixel image in `out`, by palette color.
+fn decode_sixel(out: &str) -> (usize, usize, Vec<[u8; 3]>) {
+    let start = out.find("\x1bPq").unwrap() + 3;
+    let end = out.find("\x1b\\").unwrap();
+    let data = &out.as_bytes()[start..end];
+
+    let number = |i: &mut usize| {
+        let begin = *i;
+        while data[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        std::str::from_utf8(&data[begin..*i]).unwrap().parse::<usize>().unwrap()
+    };
+
+    let mut i = 0;
+    assert_eq!(data[i], b'"');
+    i += 1;
+    let mut attributes = Vec::new();
+    for _ in 0..4 {
+        attributes.push(number(&mut i));
+        if data[i] == b';' {
+            i += 1;
+        }
+    }
+    let (width, height) = (attributes[2], attributes[3]);
+
+    let mut palette = vec![[0u8; 3]; 256];
+    let mut pixels = vec![[255u8, 0, 255]; width * height];
+    let (mut x, mut band, mut color) = (0, 0, 0);
+    while i < data.len() {
+        match data[i] {
+            b'#' => {
+                i += 1;
+                color = number(&mut i);
+                if data[i] == b';' {
+                    let mut values = Vec::new();
+                    for _ in 0..4 {
+                        i += 1;
+                        values.push(number(&mut i));
+                    }
+                    assert_eq!(values[0], 2);
+                    let channel = |percent: usize| (percent * 255 / 100) as u8;
+                    palette[color] = [channel(values[1]), channel(values[2]), channel(values[3])];
+                }
+            },
+            b'$' => { x = 0; i += 1; },
+            b'-' => { x = 0; band += 6; i += 1; },
+            c => {
+                let mut count = 1;
+                let mut c = c;
+                if c == b'!' {
+                    i += 1;
+                    count = number(&mut i);
+                    c = data[i];
+                }
+                let bits = c - 63;
+                for _ in 0..count {
+                    for bit in 0..6 {
+                        if bits & 1 << bit != 0 && band + bit < height {
+                            pixels[(band + bit) * width + x] = palette[color];
+                        }
+                    }
+                    x += 1;
+                }
+                i += 1;
+            },
+        }
+    }
+    (width, height, pixels)
+}
+
+#[test]
+fn sixel() {
+    let mut terminal = Terminal::new(Vec::new(), Mode::Sixel, (1, 1));
+    terminal.pixels = (16, 9);
+    assert_eq!(terminal.size(), (16, 9));
+
+    // 2x scaled down
+    let frame = canvas(32, 18, |x, y| rgb((x * 8) as u8, (y * 14) as u8, 255));
+    terminal.present(&frame).unwrap();
+    let out = take(&mut terminal);
+    assert!(out.starts_with("\x1b[?25l\x1b[0m\x1b[2J\x1b[H\x1bPq"));
+    assert!(out.ends_with("\x1b\\"));
+
+    let (width, height, pixels) = decode_sixel(&out);
+    assert_eq!((width, height), (16, 9));
+    for y in 0..height {
+        for x in 0..width {
+            let expected = [(x * 16 + 4) as i32, (y * 28 + 7) as i32, 255];
+            let decoded = pixels[y * width + x];
+            for channel in 0..3 {
+                // within a palette step
+                assert!((decoded[channel] as i32 - expected[channel]).abs() <= 51, "{:?} {:?} at {}, {}", decoded, expected, x, y);
+            }
+        }
+    }
+    // dithering mixes neighbouring palette colors in flat areas
+    let flat = canvas(16, 9, |_, _| rgb(128, 128, 128));
+    terminal.present(&flat).unwrap();
+    let (_, _, pixels) = decode_sixel(&take(&mut terminal));
+    let mean = pixels.iter().map(|p| p[0] as f64).sum::<f64>() / pixels.len() as f64;
+    assert!((mean - 128.0).abs() < 16.0, "{}", mean);
+
+    terminal.present(&flat).unwrap();
+    assert_eq!(take(&mut terminal), "");
+}
@@ -0,0 +1,382 @@
+mod common;
+
+use gfx::{
+    canvas::{Canvas, Color, Rect},
+    metrics,
+    text::{Align, Font, FontError, FontSet, Glow, Outline, SdfEffects, Shadow, TextStyle, VAlign, draw_text, measure},
+};
+use rusttype::{Scale, point};
+use common::filled;
+
+const FONT_DATA: &[u8] = include_bytes!("../data/Inconsolata-Regular.ttf");
+
+fn fonts() -> FontSet {
+    FontSet::from(Font::try_from_bytes(FONT_DATA).unwrap())
+}
+
+const BLACK: Color = Color { r: 0, g: 0, b: 0, a: 255 };
+const WHITE: Color = Color { r: 255, g: 255, b: 255, a: 255 };
+
+#[test]
+fn matches_uncached_rasterization() {
+    let fonts = fonts();
+    let s = "  16.667 ms per frame";
+
+    let mut cached = filled(256, 32, BLACK);
+    draw_text(&mut cached, s, (3.0, 2.0), &TextStyle::new(&fonts, 20.0));
+
+    let mut expected = filled(256, 32, BLACK);
+    let scale = Scale::uniform(20.0);
+    let ascent = fonts.fonts()[0].rusttype().v_metrics(scale).ascent;
+    for g in fonts.fonts()[0].rusttype().layout(s, scale, point(3.0, 2.0 + ascent)) {
+        if let Some(bbox) = g.pixel_bounding_box() {
+            g.draw(|x, y, v| {
+                let n = (v * 255.0).round() as u8;
+                expected.set(((bbox.min.x + x as i32) as usize, (bbox.min.y + y as i32) as usize), Color { r: n, g: n, b: n, a: 255 });
+            });
+        }
+    }
+
+    // positions are rounded to a quarter of a pixel, so coverage is close, but not exact
+    assert!(metrics::psnr(&cached, &expected) > 30.0);
+}
+
+#[test]
+fn glyphs_are_cached() {
+    let fonts = fonts();
+    let mut canvas = Canvas::new(256, 32).unwrap();
+
+    draw_text(&mut canvas, "60.000 fps", (0.0, 0.0), &TextStyle::new(&fonts, 20.0));
+    let cached = fonts.fonts()[0].cached_glyphs();
+    assert!(cached > 0);
+
+    draw_text(&mut canvas, "60.000 fps", (0.0, 0.0), &TextStyle::new(&fonts, 20.0));
+    assert_eq!(fonts.fonts()[0].cached_glyphs(), cached);
+
+    draw_text(&mut canvas, "60.000 fps", (0.0, 0.0), &TextStyle::new(&fonts, 30.0));
+    assert!(fonts.fonts()[0].cached_glyphs() > cached);
+}
+
+#[test]
+fn blends_color_over_background() {
+    let fonts = fonts();
+    let background = Color { r: 0, g: 0, b: 200, a: 255 };
+    let mut canvas = filled(64, 32, background);
+
+    let style = TextStyle { color: Color { r: 255, g: 0, b: 0, a: 128 }, ..TextStyle::new(&fonts, 24.0) };
+    let drawn = draw_text(&mut canvas, "#", (4.0, 4.0), &style);
+    assert!(!drawn.is_empty());
+
+    let mut touched = 0;
+    for y in 0..canvas.height() {
+        for x in 0..canvas.width() {
+            let c = canvas.get((x, y));
+            if drawn.contains((x as isize, y as isize)) {
+                // never more than half red, as text is half transparent
+                assert!(c.r <= 128 && c.b >= 100 && c.a == 255, "{:?}", c);
+                touched += (c != background) as usize;
+            } else {
+                assert_eq!(c, background);
+            }
+        }
+    }
+    assert!(touched > 0);
+}
+
+#[test]
+fn alignment() {
+    let fonts = fonts();
+    let mut canvas = Canvas::new(200, 100).unwrap();
+
+    let style = |align, valign| TextStyle { align, valign, ..TextStyle::new(&fonts, 20.0) };
+    let left = draw_text(&mut canvas, "text", (100.0, 50.0), &style(Align::Left, VAlign::Top));
+    let center = draw_text(&mut canvas, "text", (100.0, 50.0), &style(Align::Center, VAlign::Baseline));
+    let right = draw_text(&mut canvas, "text", (100.0, 50.0), &style(Align::Right, VAlign::Bottom));
+
+    assert!(left.x >= 100 && left.y >= 50);
+    assert!(center.x < 100 && center.right() > 100 && center.bottom() <= 51);
+    assert!(right.right() <= 101 && right.bottom() <= 51);
+    assert!(right.y < center.y);
+}
+
+#[test]
+fn clipped_to_canvas() {
+    let fonts = fonts();
+    let mut canvas = Canvas::new(16, 8).unwrap();
+    let style = TextStyle::new(&fonts, 20.0);
+
+    let drawn = draw_text(&mut canvas, "clipped", (-5.5, -7.0), &style);
+    assert_eq!(drawn.intersection(&canvas.rect()), drawn);
+
+    let outside = draw_text(&mut canvas, "outside", (100.0, -100.0), &style);
+    assert_eq!(outside, Rect::default());
+    assert!(canvas.pixels().iter().any(|c| c.a > 0));
+}
+
+const PARAGRAPH: &str = "The quick brown fox jumps over the lazy dog, again and again.";
+
+#[test]
+fn newlines() {
+    let fonts = fonts();
+    let style = TextStyle::new(&fonts, 20.0);
+    let v_metrics = fonts.fonts()[0].rusttype().v_metrics(Scale::uniform(20.0));
+    let line_height = v_metrics.ascent - v_metrics.descent + v_metrics.line_gap;
+
+    let (one_width, one_height) = measure("long line", &style);
+    let (two_width, two_height) = measure("long line\nline", &style);
+    assert_eq!(one_width, two_width);
+    assert!((one_height - 20.0).abs() < 0.01);
+    assert!((two_height - one_height - line_height).abs() < 0.01);
+
+    let double = TextStyle { line_spacing: 2.0, ..TextStyle::new(&fonts, 20.0) };
+    assert!((measure("a\nb", &double).1 - one_height - 2.0 * line_height).abs() < 0.01);
+
+    assert_eq!(measure("a\r\nb", &style), measure("a\nb", &style));
+}
+
+#[test]
+fn wrapping() {
+    let fonts = fonts();
+    let unwrapped = TextStyle::new(&fonts, 20.0);
+    let wrapped = TextStyle { max_width: Some(150.0), ..TextStyle::new(&fonts, 20.0) };
+
+    let (width, height) = measure(PARAGRAPH, &unwrapped);
+    assert!(width > 150.0);
+    assert!((height - 20.0).abs() < 0.01);
+
+    let (width, height) = measure(PARAGRAPH, &wrapped);
+    assert!(width <= 150.0);
+    assert!(height > 80.0);
+
+    let mut canvas = Canvas::new(300, 300).unwrap();
+    let drawn = draw_text(&mut canvas, PARAGRAPH, (10.0, 10.0), &wrapped);
+    assert!(drawn.x >= 10 && drawn.right() <= 161);
+    assert!(drawn.height as f32 > 80.0);
+}
+
+#[test]
+fn long_words_are_broken() {
+    let fonts = fonts();
+    let style = TextStyle { max_width: Some(30.0), ..TextStyle::new(&fonts, 20.0) };
+
+    let (width, height) = measure("abcdefghij", &style);
+    assert!(width <= 30.0);
+    assert!(height > 60.0);
+
+    // a single character wider than the limit still gets a line of its own
+    let narrow = TextStyle { max_width: Some(1.0), ..TextStyle::new(&fonts, 20.0) };
+    let (width, _) = measure("ab", &narrow);
+    assert!(width > 1.0);
+}
+
+#[test]
+fn degenerate_widths() {
+    let fonts = fonts();
+    let style = |max_width| TextStyle { max_width: Some(max_width), ..TextStyle::new(&fonts, 20.0) };
+    let zero = measure("a\n\nb", &style(0.0));
+
+    // negative widths wrap as 0 does, empty paragraphs included
+    assert_eq!(measure("a\n\nb", &style(-1.0)), zero);
+    assert_eq!(measure("", &style(-1.0)), (0.0, measure("x", &style(0.0)).1));
+
+    // NaN does not wrap
+    let unwrapped = TextStyle::new(&fonts, 20.0);
+    assert_eq!(measure("", &style(f32::NAN)), measure("", &unwrapped));
+    assert_eq!(measure("ab cd\n\nef", &style(f32::NAN)), measure("ab cd\n\nef", &unwrapped));
+}
+
+#[test]
+fn justify_stretches_all_but_last_line() {
+    let fonts = fonts();
+    let left = TextStyle { max_width: Some(200.0), ..TextStyle::new(&fonts, 20.0) };
+    let justified = TextStyle { align: Align::Justify, ..left };
+
+    let left_width = measure(PARAGRAPH, &left).0;
+    let justified_width = measure(PARAGRAPH, &justified).0;
+    assert!(left_width < 200.0);
+    assert!((justified_width - 200.0).abs() < 0.01);
+
+    // last line stays as is, so a single line is never stretched
+    assert_eq!(measure("a b", &justified), measure("a b", &left));
+}
+
+#[test]
+fn multiline_alignment() {
+    let fonts = fonts();
+    let mut canvas = Canvas::new(200, 200).unwrap();
+    let text = "wide line\nnarrow";
+
+    let right = TextStyle { align: Align::Right, ..TextStyle::new(&fonts, 20.0) };
+    let drawn = draw_text(&mut canvas, text, (150.0, 10.0), &right);
+    assert!(drawn.right() <= 151 && drawn.right() >= 145);
+
+    let bottom = TextStyle { valign: VAlign::Bottom, ..TextStyle::new(&fonts, 20.0) };
+    let drawn = draw_text(&mut canvas, text, (10.0, 150.0), &bottom);
+    assert!(drawn.bottom() <= 151 && drawn.y < 150 - 20);
+}
+
+const FALLBACK_DATA: &[u8] = include_bytes!("../data/DejaVuSansMono.ttf");
+
+fn with_fallback() -> FontSet {
+    let mut fonts = fonts();
+    fonts.push(Font::try_from_bytes(FALLBACK_DATA).unwrap());
+    fonts
+}
+
+#[test]
+fn missing_glyphs_come_from_fallback() {
+    let primary = fonts();
+    let fonts = with_fallback();
+    let text = "λ Ж ★";
+    assert!(text.chars().filter(|&c| c != ' ').all(|c| !fonts.fonts()[0].has_glyph(c)));
+
+    assert_eq!(fonts.font_for('a'), 0);
+    assert_eq!(fonts.font_for('★'), 1);
+    // neither font has it
+    assert_eq!(fonts.font_for('日'), 0);
+
+    let style = TextStyle::new(&fonts, 20.0);
+    let mut canvas = Canvas::new(128, 32).unwrap();
+    draw_text(&mut canvas, text, (0.0, 0.0), &style);
+    assert_eq!(fonts.fonts()[0].cached_glyphs(), 1); // space
+    assert_eq!(fonts.fonts()[1].cached_glyphs(), 3);
+
+    // the missing glyph box of the primary font looks different from real glyphs
+    let mut missing = Canvas::new(128, 32).unwrap();
+    draw_text(&mut missing, text, (0.0, 0.0), &TextStyle::new(&primary, 20.0));
+    assert!(metrics::mse(&canvas, &missing) > 0.0);
+}
+
+#[test]
+fn fallback_does_not_change_line_metrics() {
+    let single = fonts();
+    let fonts = with_fallback();
+    let (_, height) = measure("x", &TextStyle::new(&single, 20.0));
+    assert_eq!(measure("x★", &TextStyle::new(&fonts, 20.0)).1, height);
+}
+
+/// Packs single font files into a font collection.
+fn collection(fonts: &[&[u8]]) -> Vec<u8> {
+    let header = 12 + 4 * fonts.len();
+    let mut ttc = b"ttcf".to_vec();
+    ttc.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    ttc.extend_from_slice(&(fonts.len() as u32).to_be_bytes());
+
+    let mut offset = header;
+    for font in fonts {
+        ttc.extend_from_slice(&(offset as u32).to_be_bytes());
+        offset += font.len();
+    }
+
+    for font in fonts {
+        let start = ttc.len();
+        ttc.extend_from_slice(font);
+        // table offsets are from the start of the file
+        let tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+        for table in 0..tables {
+            let at = start + 12 + 16 * table + 8;
+            let offset = u32::from_be_bytes([ttc[at], ttc[at + 1], ttc[at + 2], ttc[at + 3]]);
+            ttc[at..at + 4].copy_from_slice(&(offset + start as u32).to_be_bytes());
+        }
+    }
+    ttc
+}
+
+#[test]
+fn loads_collections() {
+    let ttc = collection(&[FONT_DATA, FALLBACK_DATA]);
+
+    let dir = std::env::temp_dir().join(format!("gfx-fonts-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("both.ttc");
+    std::fs::write(&path, &ttc).unwrap();
+
+    let faces = Font::load_collection(&path).unwrap();
+    assert_eq!(faces.len(), 2);
+    assert!(!faces[0].has_glyph('★'));
+    assert!(faces[1].has_glyph('★'));
+
+    assert!(!Font::load(&path).unwrap().has_glyph('★'));
+    assert!(Font::from_vec_and_index(ttc.clone(), 1).unwrap().has_glyph('★'));
+    assert!(matches!(
+        Font::from_vec_and_index(ttc, 2),
+        Err(FontError::NoSuchFace { index: 2, faces: 2 }),
+    ));
+
+    let single = dir.join("single.ttf");
+    std::fs::write(&single, FALLBACK_DATA).unwrap();
+    assert_eq!(Font::load_collection(&single).unwrap().len(), 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn load_errors() {
+    assert!(matches!(Font::load("does/not/exist.ttf"), Err(FontError::Io(_))));
+    assert!(matches!(Font::from_vec_and_index(b"not a font at all".to_vec(), 0), Err(FontError::Invalid)));
+    assert!(matches!(Font::from_vec_and_index(Vec::new(), 0), Err(FontError::Invalid)));
+}
+
+fn sdf_style(fonts: &FontSet, size: f32, effects: SdfEffects) -> TextStyle<'_> {
+    TextStyle { sdf: Some(effects), ..TextStyle::new(fonts, size) }
+}
+
+#[test]
+fn sdf_is_close_to_glyph_images() {
+    let fonts = fonts();
+    let mut images = filled(320, 64, BLACK);
+    let mut fields = filled(320, 64, BLACK);
+    draw_text(&mut images, "Sphinx of quartz", (4.0, 4.0), &TextStyle::new(&fonts, 40.0));
+    draw_text(&mut fields, "Sphinx of quartz", (4.0, 4.0), &sdf_style(&fonts, 40.0, SdfEffects::default()));
+
+    assert!(metrics::psnr(&images, &fields) > 20.0);
+    assert_eq!(fonts.fonts()[0].cached_glyphs(), 15);
+    assert_eq!(fonts.fonts()[0].cached_fields(), 15);
+}
+
+#[test]
+fn sdf_fields_are_shared_between_sizes() {
+    let fonts = fonts();
+    let mut canvas = Canvas::new(640, 256).unwrap();
+    draw_text(&mut canvas, "Ag", (0.0, 0.0), &sdf_style(&fonts, 20.0, SdfEffects::default()));
+    assert_eq!(fonts.fonts()[0].cached_fields(), 2);
+
+    let big = draw_text(&mut canvas, "Ag", (0.0, 0.0), &sdf_style(&fonts, 200.0, SdfEffects::default()));
+    assert_eq!(fonts.fonts()[0].cached_fields(), 2);
+    assert_eq!(fonts.fonts()[0].cached_glyphs(), 0);
+    assert!(big.height > 100);
+}
+
+#[test]
+fn sdf_effects() {
+    let fonts = fonts();
+    let red = Color { r: 255, g: 0, b: 0, a: 255 };
+    let draw = |effects: SdfEffects, color: Color| {
+        let mut canvas = filled(128, 96, BLACK);
+        let style = TextStyle { color, ..sdf_style(&fonts, 48.0, effects) };
+        let drawn = draw_text(&mut canvas, "H", (20.0, 10.0), &style);
+        (canvas, drawn)
+    };
+
+    let (_, plain) = draw(SdfEffects::default(), WHITE);
+    // `H` has straight vertical stems, the outline makes it wider by its width on both sides
+    let outline = SdfEffects { outline: Some(Outline { width: 3.0, color: red }), ..SdfEffects::default() };
+    let (canvas, outlined) = draw(outline, WHITE);
+    assert_eq!(outlined.width, plain.width + 6);
+    assert_eq!(canvas.get((outlined.x as usize + 1, (outlined.y + outlined.height as isize / 2) as usize)), red);
+
+    let glow = SdfEffects { glow: Some(Glow { radius: 8.0, color: red }), ..SdfEffects::default() };
+    let (canvas, glowing) = draw(glow, WHITE);
+    assert!(glowing.width > plain.width + 8);
+    let near = canvas.get((plain.x as usize - 2, (plain.y + plain.height as isize / 2) as usize));
+    let far = canvas.get((plain.x as usize - 7, (plain.y + plain.height as isize / 2) as usize));
+    assert!(near.r > far.r && far.r > 0, "{:?} {:?}", near, far);
+
+    // with invisible text only the shadow is drawn, moved by the offset
+    let shadow = SdfEffects {
+        shadow: Some(Shadow { offset: (10.0, 5.0), softness: 0.0, color: red }),
+        ..SdfEffects::default()
+    };
+    let (_, shadowed) = draw(shadow, Color { a: 0, ..red });
+    assert_eq!(shadowed, Rect { x: plain.x + 10, y: plain.y + 5, ..plain });
+}
@@ -0,0 +1,127 @@
+use gfx::{
+    canvas::Canvas,
+    raytracer::Scene,
+    ui::{ROW_HEIGHT, Ui, UiFrame, UiInput, UiKey},
+};
+
+const WIDTH: usize = 200;
+
+fn input(mouse: (isize, isize), mouse_down: bool) -> UiInput {
+    UiInput { mouse, mouse_down, keys: Vec::new() }
+}
+
+fn keys(keys: &[UiKey]) -> UiInput {
+    UiInput { mouse: (-1, -1), mouse_down: false, keys: keys.to_vec() }
+}
+
+/// Middle of the `row`th row, `x` pixels from the left.
+fn row(row: usize, x: isize) -> (isize, isize) {
+    (x, (row * (ROW_HEIGHT + 1) + ROW_HEIGHT / 2) as isize)
+}
+
+/// Runs a frame of widgets added by `f`.
+fn frame<R>(ui: &mut Ui, input: &UiInput, f: impl FnOnce(&mut UiFrame) -> R) -> R {
+    let mut canvas = Canvas::new(WIDTH, 200).unwrap();
+    let mut frame = ui.frame(&mut canvas, input, (0, 0), WIDTH);
+    f(&mut frame)
+}
+
+#[test]
+fn button_clicks_on_release() {
+    let mut ui = Ui::new();
+    let buttons = |frame: &mut UiFrame| (frame.button("a"), frame.button("b"));
+
+    assert_eq!(frame(&mut ui, &input(row(1, 10), false), buttons), (false, false));
+    assert_eq!(frame(&mut ui, &input(row(1, 10), true), buttons), (false, false));
+    assert_eq!(frame(&mut ui, &input(row(1, 10), false), buttons), (false, true));
+    assert_eq!(frame(&mut ui, &input(row(1, 10), false), buttons), (false, false));
+
+    // released somewhere else
+    frame(&mut ui, &input(row(0, 10), true), buttons);
+    assert_eq!(frame(&mut ui, &input(row(1, 10), false), buttons), (false, false));
+
+    // pressing outside and moving onto a button does not click it
+    frame(&mut ui, &input(row(5, 10), true), buttons);
+    frame(&mut ui, &input(row(0, 10), true), buttons);
+    assert_eq!(frame(&mut ui, &input(row(0, 10), false), buttons), (false, false));
+}
+
+#[test]
+fn checkbox_and_keyboard_focus() {
+    let mut ui = Ui::new();
+    let (mut a, mut b) = (false, false);
+
+    frame(&mut ui, &input(row(1, 4), true), |frame| {
+        assert!(!frame.checkbox("a", &mut a));
+        assert!(frame.checkbox("b", &mut b));
+    });
+    assert!(!a && b);
+
+    // clicked "b" has focus, Tab wraps around to "a"
+    frame(&mut ui, &keys(&[UiKey::Tab, UiKey::Space]), |frame| {
+        frame.checkbox("a", &mut a);
+        frame.checkbox("b", &mut b);
+    });
+    assert!(a && b);
+
+    // clicking outside takes focus away
+    frame(&mut ui, &input(row(5, 4), true), |frame| {
+        frame.checkbox("a", &mut a);
+        frame.checkbox("b", &mut b);
+    });
+    frame(&mut ui, &keys(&[UiKey::Enter]), |frame| {
+        frame.checkbox("a", &mut a);
+        frame.checkbox("b", &mut b);
+    });
+    assert!(a && b);
+}
+
+#[test]
+fn slider_drag_and_keys() {
+    let mut ui = Ui::new();
+    let mut value = 0.5;
+    let track = (WIDTH * 2 / 5) as isize;
+
+    frame(&mut ui, &input(row(0, track), true), |frame| frame.slider("v", &mut value, 1.0..=3.0));
+    assert_eq!(value, 1.0);
+    // dragging past the end clamps
+    frame(&mut ui, &input(row(3, 1000), true), |frame| frame.slider("v", &mut value, 1.0..=3.0));
+    assert_eq!(value, 3.0);
+    frame(&mut ui, &input(row(3, 1000), false), |frame| frame.slider("v", &mut value, 1.0..=3.0));
+
+    let changed = frame(&mut ui, &keys(&[UiKey::Left, UiKey::Left]), |frame| frame.slider("v", &mut value, 1.0..=3.0));
+    assert!(changed);
+    assert!((value - 2.96).abs() < 1e-5);
+
+    // the mouse over the slider without a press changes nothing
+    let changed = frame(&mut ui, &input(row(0, track), false), |frame| frame.slider("v", &mut value, 1.0..=3.0));
+    assert!(!changed);
+}
+
+#[test]
+fn inspect_scene() {
+    let mut ui = Ui::new();
+    let mut scene = Scene::demo();
+    let inspect = |ui: &mut Ui, input: UiInput, scene: &mut Scene| {
+        frame(ui, &input, |frame| frame.inspect("scene", scene))
+    };
+
+    // panels start closed, so "scene" is the only row
+    inspect(&mut ui, input(row(1, 10), true), &mut scene);
+    inspect(&mut ui, input(row(1, 10), false), &mut scene);
+    assert!(!ui.wants_mouse());
+
+    // open "scene", "spheres", "0"
+    for (i, r) in [0, 2, 3].iter().enumerate() {
+        inspect(&mut ui, input(row(*r, 10 + 8 * i as isize), true), &mut scene);
+        inspect(&mut ui, input(row(*r, 10 + 8 * i as isize), false), &mut scene);
+    }
+    // rows: scene, lights, spheres, 0, center, radius
+    let radius = row(5, 150);
+    inspect(&mut ui, input(radius, true), &mut scene);
+    assert!(ui.wants_mouse());
+    let changed = inspect(&mut ui, input((radius.0 + 20, radius.1), true), &mut scene);
+    assert!(changed);
+    assert!((scene.spheres[0].radius - 0.7).abs() < 1e-5);
+    assert_eq!(scene.spheres[1].radius, 2.0);
+}
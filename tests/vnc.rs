@@ -0,0 +1,432 @@
+mod common;
+
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+};
+use miniz_oxide::{inflate::stream::{InflateState, inflate}, DataFormat, MZFlush};
+use gfx::{
+    backend::{Backend, vnc::Server},
+    canvas::{Canvas, Color},
+    input::{Event, Key, MouseButton},
+};
+
+/// Viewer stand-in, polling the server while it waits for data since both run on one thread.
+struct Viewer {
+    stream: TcpStream,
+    received: Vec<u8>,
+    events: Vec<Event>,
+    /// Zlib and ZRLE streams.
+    inflate: [Box<InflateState>; 2],
+    /// Bytes per pixel and whether they are big endian.
+    format: (usize, bool),
+}
+
+impl Viewer {
+    fn connect(server: &mut Server) -> Self {
+        let stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        stream.set_nonblocking(true).unwrap();
+        Self {
+            stream,
+            received: Vec::new(),
+            events: Vec::new(),
+            inflate: [InflateState::new_boxed(DataFormat::Zlib), InflateState::new_boxed(DataFormat::Zlib)],
+            format: (4, false),
+        }
+    }
+
+    fn send(&mut self, server: &mut Server, bytes: &[u8]) {
+        self.stream.write_all(bytes).unwrap();
+        self.poll(server);
+    }
+
+    fn poll(&mut self, server: &mut Server) {
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        server.poll_events(&mut self.events);
+        let mut buffer = [0; 65536];
+        while let Ok(read) = self.stream.read(&mut buffer) {
+            if read == 0 {
+                break;
+            }
+            self.received.extend_from_slice(&buffer[..read]);
+        }
+    }
+
+    fn read(&mut self, server: &mut Server, length: usize) -> Vec<u8> {
+        for _ in 0..1000 {
+            if self.received.len() >= length {
+                return self.received.drain(..length).collect();
+            }
+            self.poll(server);
+        }
+        panic!("expected {} bytes, received {:?}", length, self.received);
+    }
+
+    /// Polls until `expected.len()` events arrived, then compares them.
+    fn expect_events(&mut self, server: &mut Server, expected: &[Event]) {
+        for _ in 0..1000 {
+            if self.events.len() >= expected.len() {
+                break;
+            }
+            self.poll(server);
+        }
+        assert_eq!(self.events.drain(..).collect::<Vec<_>>(), expected);
+    }
+
+    fn u16(&mut self, server: &mut Server) -> usize {
+        let bytes = self.read(server, 2);
+        u16::from_be_bytes([bytes[0], bytes[1]]) as usize
+    }
+
+    fn u32(&mut self, server: &mut Server) -> usize {
+        let bytes = self.read(server, 4);
+        u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+    }
+
+    /// Handshake of version 3.8, returns the framebuffer size and name.
+    fn handshake(&mut self, server: &mut Server) -> ((usize, usize), String) {
+        assert_eq!(self.read(server, 12), b"RFB 003.008\n");
+        self.send(server, b"RFB 003.008\n");
+        assert_eq!(self.read(server, 2), [1, 1]);
+        self.send(server, &[1]);
+        assert_eq!(self.u32(server), 0);
+        self.send(server, &[1]);
+        let size = (self.u16(server), self.u16(server));
+        assert_eq!(self.read(server, 16), [32, 24, 0, 1, 0, 255, 0, 255, 0, 255, 16, 8, 0, 0, 0, 0]);
+        let length = self.u32(server);
+        (size, String::from_utf8(self.read(server, length)).unwrap())
+    }
+
+    fn set_encodings(&mut self, server: &mut Server, encodings: &[i32]) {
+        let mut message = vec![2, 0];
+        message.extend_from_slice(&(encodings.len() as u16).to_be_bytes());
+        for encoding in encodings {
+            message.extend_from_slice(&encoding.to_be_bytes());
+        }
+        self.send(server, &message);
+    }
+
+    fn request(&mut self, server: &mut Server, incremental: bool, (width, height): (u16, u16)) {
+        let mut message = vec![3, incremental as u8, 0, 0, 0, 0];
+        message.extend_from_slice(&width.to_be_bytes());
+        message.extend_from_slice(&height.to_be_bytes());
+        self.send(server, &message);
+    }
+
+    /// Reads an update into `frame`, returns its rectangles.
+    fn update(&mut self, server: &mut Server, frame: &mut [u32], width: usize) -> Vec<(usize, usize, usize, usize, i32)> {
+        assert_eq!(self.read(server, 2), [0, 0]);
+        let count = self.u16(server);
+        let mut rects = Vec::new();
+        for _ in 0..count {
+            let (x, y, w, h) = (self.u16(server), self.u16(server), self.u16(server), self.u16(server));
+            let encoding = self.u32(server) as i32;
+            rects.push((x, y, w, h, encoding));
+            let pixels = match encoding {
+                0 => {
+                    let data = self.read(server, w * h * self.format.0);
+                    self.pixels(&data, self.format.0)
+                },
+                6 => {
+                    let length = self.u32(server);
+                    let data = self.read(server, length);
+                    let data = self.inflate(0, &data);
+                    self.pixels(&data, self.format.0)
+                },
+                16 => {
+                    let length = self.u32(server);
+                    let data = self.read(server, length);
+                    let data = self.inflate(1, &data);
+                    self.zrle(&data, w, h)
+                },
+                _ => panic!("unexpected encoding {}", encoding),
+            };
+            assert_eq!(pixels.len(), w * h);
+            for row in 0..h {
+                frame[(y + row) * width + x..][..w].copy_from_slice(&pixels[row * w..][..w]);
+            }
+        }
+        rects
+    }
+
+    fn inflate(&mut self, stream: usize, data: &[u8]) -> Vec<u8> {
+        let mut out = vec![0; 1 << 22];
+        let result = inflate(&mut self.inflate[stream], data, &mut out, MZFlush::Sync);
+        assert_eq!(result.bytes_consumed, data.len());
+        out.truncate(result.bytes_written);
+        out
+    }
+
+    fn pixels(&self, data: &[u8], bytes: usize) -> Vec<u32> {
+        data.chunks(bytes).map(|pixel| {
+            pixel.iter().enumerate().fold(0, |value, (i, &byte)| {
+                let shift = if self.format.1 { bytes - 1 - i } else { i };
+                value | (byte as u32) << (8 * shift)
+            })
+        }).collect()
+    }
+
+    fn zrle(&self, mut data: &[u8], width: usize, height: usize) -> Vec<u32> {
+        let bytes = if self.format.0 == 4 { 3 } else { self.format.0 };
+        let mut pixels = vec![0; width * height];
+        for y in (0..height).step_by(64) {
+            for x in (0..width).step_by(64) {
+                let (w, h) = ((width - x).min(64), (height - y).min(64));
+                let subencoding = data[0] as usize;
+                data = &data[1..];
+                let tile: Vec<u32> = match subencoding {
+                    0 => {
+                        let tile = self.pixels(&data[..w * h * bytes], bytes);
+                        data = &data[w * h * bytes..];
+                        tile
+                    },
+                    1 => {
+                        let color = self.pixels(&data[..bytes], bytes)[0];
+                        data = &data[bytes..];
+                        vec![color; w * h]
+                    },
+                    2..=16 => {
+                        let palette = self.pixels(&data[..subencoding * bytes], bytes);
+                        data = &data[subencoding * bytes..];
+                        let bits = match subencoding { 2 => 1, 3..=4 => 2, _ => 4 };
+                        let row_bytes = (w * bits).div_ceil(8);
+                        let mut tile = Vec::new();
+                        for row in data[..row_bytes * h].chunks(row_bytes) {
+                            for i in 0..w {
+                                let bit = i * bits;
+                                let index = row[bit / 8] >> (8 - bits - bit % 8) & ((1 << bits) - 1);
+                                tile.push(palette[index as usize]);
+                            }
+                        }
+                        data = &data[row_bytes * h..];
+                        tile
+                    },
+                    _ => panic!("unexpected ZRLE subencoding {}", subencoding),
+                };
+                for row in 0..h {
+                    pixels[(y + row) * width + x..][..w].copy_from_slice(&tile[row * w..][..w]);
+                }
+            }
+        }
+        assert!(data.is_empty());
+        pixels
+    }
+}
+
+fn gradient(width: usize, height: usize) -> Canvas {
+    common::canvas(width, height, |x, y| Color { r: x as u8, g: y as u8, b: ((x / 20) * 40) as u8, a: 255 })
+}
+
+/// 0x00RRGGBB like the server's initial pixel format.
+fn rgb(color: Color) -> u32 {
+    (color.r as u32) << 16 | (color.g as u32) << 8 | color.b as u32
+}
+
+#[test]
+fn raw_updates() {
+    let mut server = Server::bind("127.0.0.1:0", "gfx test", 150, 70).unwrap();
+    let canvas = gradient(150, 70);
+    server.present(&canvas).unwrap();
+
+    let mut viewer = Viewer::connect(&mut server);
+    assert_eq!(viewer.handshake(&mut server), ((150, 70), "gfx test".to_owned()));
+    assert_eq!(server.clients(), 1);
+
+    let mut frame = vec![0; 150 * 70];
+    viewer.request(&mut server, false, (150, 70));
+    let rects = viewer.update(&mut server, &mut frame, 150);
+    // a run of tiles per row of them
+    assert_eq!(rects, [(0, 0, 150, 64, 0), (0, 64, 150, 6, 0)]);
+    assert!(frame.iter().zip(canvas.pixels()).all(|(&pixel, &color)| pixel == rgb(color)));
+
+    // nothing changed
+    viewer.request(&mut server, true, (150, 70));
+    server.present(&canvas).unwrap();
+    viewer.poll(&mut server);
+    assert!(viewer.received.is_empty());
+
+    // only the tile that changed, cropped to the framebuffer
+    let mut canvas = gradient(150, 70);
+    canvas.set((140, 65), Color { r: 255, g: 255, b: 255, a: 255 });
+    server.present(&canvas).unwrap();
+    assert_eq!(viewer.update(&mut server, &mut frame, 150), [(128, 64, 22, 6, 0)]);
+    assert_eq!(frame[65 * 150 + 140], 0xffffff);
+
+    // a smaller canvas is padded with black
+    viewer.request(&mut server, true, (150, 70));
+    server.present(&gradient(100, 70)).unwrap();
+    assert_eq!(viewer.update(&mut server, &mut frame, 150), [(64, 0, 86, 64, 0), (64, 64, 86, 6, 0)]);
+    assert_eq!(frame[10 * 150 + 120], 0);
+
+    drop(viewer);
+    for _ in 0..1000 {
+        server.poll_events(&mut Vec::new());
+        if server.clients() == 0 {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    assert_eq!(server.clients(), 0);
+}
+
+#[test]
+fn compressed_updates() {
+    let mut server = Server::bind("127.0.0.1:0", "", 150, 70).unwrap();
+    let canvas = gradient(150, 70);
+    server.present(&canvas).unwrap();
+    let mut viewer = Viewer::connect(&mut server);
+    viewer.handshake(&mut server);
+
+    // unknown encodings are skipped, the first supported one is used
+    viewer.set_encodings(&mut server, &[7, 16, 6, 0]);
+    let mut frame = vec![0; 150 * 70];
+    for _ in 0..2 {
+        // the second update continues the ZRLE stream
+        viewer.request(&mut server, false, (150, 70));
+        assert_eq!(viewer.update(&mut server, &mut frame, 150)[0].4, 16);
+        assert!(frame.iter().zip(canvas.pixels()).all(|(&pixel, &color)| pixel == rgb(color)));
+    }
+
+    // palettes of 1, 2 and 4 bits per pixel
+    let mut stripes = Canvas::new(150, 70).unwrap();
+    for y in 0..70 {
+        for x in 0..150 {
+            let colors = [2, 3, 5][x / 64];
+            let i = ((x / 3 + y) % colors) as u8;
+            stripes.set((x, y), Color { r: i * 50, g: 255 - i, b: x as u8 / 64, a: 255 });
+        }
+    }
+    server.present(&stripes).unwrap();
+    viewer.request(&mut server, true, (150, 70));
+    viewer.update(&mut server, &mut frame, 150);
+    assert!(frame.iter().zip(stripes.pixels()).all(|(&pixel, &color)| pixel == rgb(color)));
+    server.present(&canvas).unwrap();
+
+    // 16-bit big endian 5-6-5
+    viewer.send(&mut server, &[0, 0, 0, 0, 16, 16, 1, 1, 0, 31, 0, 63, 0, 31, 11, 5, 0, 0, 0, 0]);
+    viewer.format = (2, true);
+    viewer.set_encodings(&mut server, &[6]);
+    viewer.request(&mut server, false, (150, 70));
+    assert_eq!(viewer.update(&mut server, &mut frame, 150)[0].4, 6);
+    let rgb565 = |c: Color| ((c.r as u32 * 31 + 127) / 255) << 11 | ((c.g as u32 * 63 + 127) / 255) << 5 | ((c.b as u32 * 31 + 127) / 255);
+    assert!(frame.iter().zip(canvas.pixels()).all(|(&pixel, &color)| pixel == rgb565(color)));
+
+    viewer.set_encodings(&mut server, &[16]);
+    viewer.request(&mut server, false, (150, 70));
+    viewer.update(&mut server, &mut frame, 150);
+    assert!(frame.iter().zip(canvas.pixels()).all(|(&pixel, &color)| pixel == rgb565(color)));
+}
+
+#[test]
+fn input() {
+    let mut server = Server::bind("127.0.0.1:0", "", 64, 64).unwrap();
+    let mut viewer = Viewer::connect(&mut server);
+    viewer.handshake(&mut server);
+
+    let key = |down: bool, keysym: u32| {
+        let mut message = vec![4, down as u8, 0, 0];
+        message.extend_from_slice(&keysym.to_be_bytes());
+        message
+    };
+    let pointer = |buttons: u8, x: u16, y: u16| {
+        let mut message = vec![5, buttons];
+        message.extend_from_slice(&x.to_be_bytes());
+        message.extend_from_slice(&y.to_be_bytes());
+        message
+    };
+
+    // shifted symbols type text and press the key they are on
+    viewer.send(&mut server, &[key(true, 0xffe1), key(true, 0x41), key(true, 0x41), key(false, 0x61), key(true, 0x21)].concat());
+    viewer.expect_events(&mut server, &[
+        Event::KeyDown { key: Key::Shift, repeat: false },
+        Event::KeyDown { key: Key::A, repeat: false },
+        Event::Text('A'),
+        Event::KeyDown { key: Key::A, repeat: true },
+        Event::Text('A'),
+        Event::KeyUp { key: Key::A },
+        Event::KeyDown { key: Key::Digit1, repeat: false },
+        Event::Text('!'),
+    ]);
+
+    // no text with Control held, Unicode key symbols
+    viewer.send(&mut server, &[key(true, 0xffe3), key(true, 0x63), key(false, 0xffe3), key(true, 0x0100_20ac)].concat());
+    viewer.expect_events(&mut server, &[
+        Event::KeyDown { key: Key::Control, repeat: false },
+        Event::KeyDown { key: Key::C, repeat: false },
+        Event::KeyUp { key: Key::Control },
+        Event::KeyDown { key: Key::Other(0x0100_20ac), repeat: false },
+        Event::Text('€'),
+    ]);
+
+    viewer.send(&mut server, &[pointer(0, 10, 20), pointer(1, 10, 20), pointer(1 | 8, 11, 20), pointer(1 | 4, 11, 20)].concat());
+    viewer.expect_events(&mut server, &[
+        Event::MouseMove { x: 10, y: 20 },
+        Event::MouseDown { button: MouseButton::Left },
+        Event::MouseMove { x: 11, y: 20 },
+        Event::MouseWheel { x: 0.0, y: 1.0 },
+        Event::MouseDown { button: MouseButton::Right },
+    ]);
+
+    // what is held is released when the viewer goes away, after its last messages
+    viewer.stream.write_all(&key(true, 0xff1b)).unwrap();
+    drop(viewer.stream);
+    let mut events = Vec::new();
+    while server.clients() > 0 {
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        server.poll_events(&mut events);
+    }
+    events.sort_by_key(|event| format!("{:?}", event));
+    assert_eq!(events, [
+        Event::KeyDown { key: Key::Escape, repeat: false },
+        Event::KeyUp { key: Key::C },
+        Event::KeyUp { key: Key::Digit1 },
+        Event::KeyUp { key: Key::Escape },
+        Event::KeyUp { key: Key::Other(0x0100_20ac) },
+        Event::KeyUp { key: Key::Shift },
+        Event::MouseUp { button: MouseButton::Left },
+        Event::MouseUp { button: MouseButton::Right },
+    ]);
+}
+
+#[test]
+fn cut_text_is_skipped() {
+    let mut server = Server::bind("127.0.0.1:0", "", 8, 8).unwrap();
+    let mut viewer = Viewer::connect(&mut server);
+    viewer.handshake(&mut server);
+
+    // the text arrives over several polls, the key after it is still read
+    let length = 100_000;
+    let mut message = vec![6, 0, 0, 0];
+    message.extend_from_slice(&(length as u32).to_be_bytes());
+    viewer.send(&mut server, &message);
+    for chunk in vec![b'x'; length].chunks(30_000) {
+        viewer.send(&mut server, chunk);
+    }
+    viewer.send(&mut server, &[4, 1, 0, 0, 0, 0, 0, 0x61]);
+    viewer.expect_events(&mut server, &[Event::KeyDown { key: Key::A, repeat: false }, Event::Text('a')]);
+    assert_eq!(server.clients(), 1);
+}
+
+#[test]
+fn older_versions() {
+    let mut server = Server::bind("127.0.0.1:0", "old", 8, 8).unwrap();
+
+    // 3.3, the server picks security type None and sends no result
+    let mut viewer = Viewer::connect(&mut server);
+    viewer.read(&mut server, 12);
+    viewer.send(&mut server, b"RFB 003.003\n");
+    assert_eq!(viewer.u32(&mut server), 1);
+    viewer.send(&mut server, &[0]);
+    assert_eq!((viewer.u16(&mut server), viewer.u16(&mut server)), (8, 8));
+
+    // 3.8 viewer asking for another security type is told why it is refused
+    let mut viewer = Viewer::connect(&mut server);
+    viewer.read(&mut server, 12);
+    viewer.send(&mut server, b"RFB 003.008\n");
+    viewer.read(&mut server, 2);
+    viewer.send(&mut server, &[2]);
+    assert_eq!(viewer.u32(&mut server), 1);
+    let length = viewer.u32(&mut server);
+    assert!(String::from_utf8(viewer.read(&mut server, length)).unwrap().contains("None"));
+    assert_eq!(server.clients(), 1);
+}
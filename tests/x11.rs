@@ -0,0 +1,58 @@
+//! Needs an X server, for example `xvfb-run cargo test --features x11 --test x11`.
+//! Passes without running anything when `DISPLAY` is not set.
+
+mod common;
+
+use gfx::{
+    backend::{Backend, x11::Window},
+    canvas::{Canvas, Color},
+    input::Event,
+    Error,
+};
+
+fn window(width: usize, height: usize) -> Option<Window> {
+    if std::env::var_os("DISPLAY").is_none() {
+        eprintln!("DISPLAY is not set, skipping");
+        return None;
+    }
+    Some(Window::new("gfx test", width, height).unwrap())
+}
+
+fn gradient(width: usize, height: usize) -> Canvas {
+    common::canvas(width, height, |x, y| Color { r: x as u8, g: y as u8, b: 128, a: 255 })
+}
+
+#[test]
+fn no_display() {
+    if std::env::var_os("DISPLAY").is_some() {
+        return;
+    }
+    match Window::new("gfx test", 64, 48) {
+        Err(Error::Platform { call: "XOpenDisplay", message }) => assert_eq!(message, "DISPLAY is not set"),
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("opened a window without DISPLAY"),
+    }
+}
+
+#[test]
+fn present_and_poll() {
+    let mut window = match window(64, 48) {
+        Some(window) => window,
+        None => return,
+    };
+    assert_eq!(window.size(), (64, 48));
+
+    let mut events = Vec::new();
+    for frame in 0..3 {
+        window.poll_events(&mut events);
+        window.present(&gradient(64, 48)).unwrap();
+        // a canvas of another size is shown unscaled
+        window.present(&gradient(32 + frame, 16)).unwrap();
+    }
+    assert!(!events.contains(&Event::Close));
+
+    window.disable_shm();
+    assert!(!window.uses_shm());
+    window.present(&gradient(64, 48)).unwrap();
+    window.poll_events(&mut events);
+}